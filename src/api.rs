@@ -0,0 +1,70 @@
+//! The Chroma endpoints as a trait, so the backend behind a client can be swapped out.
+
+use crate::client::KhromaClient;
+use crate::error::KhromaError;
+#[cfg(feature = "mock")]
+use crate::mock::MockKhromaClient;
+use crate::models::*;
+use async_trait::async_trait;
+use std::fmt;
+
+macro_rules! chroma_api {
+    ($(fn $name:ident(&self $(, $arg:ident: $ty:ty)*) -> $ret:ty;)*) => {
+        /// The endpoint methods of [`KhromaClient`], one per Chroma API call.
+        ///
+        /// Implemented by [`KhromaClient`] and, with the `mock` feature, by
+        /// [`MockKhromaClient`](crate::mock::MockKhromaClient). Code that takes a
+        /// `&dyn ChromaApi` can be tested against a mock or a hand-written fake, and
+        /// [`KhromaClient::from_api`] puts any implementation behind the high-level handles.
+        #[async_trait]
+        pub trait ChromaApi: fmt::Debug + Send + Sync {
+            $(async fn $name(&self $(, $arg: $ty)*) -> Result<$ret, KhromaError>;)*
+        }
+
+        #[async_trait]
+        impl ChromaApi for KhromaClient {
+            $(async fn $name(&self $(, $arg: $ty)*) -> Result<$ret, KhromaError> {
+                KhromaClient::$name(self $(, $arg)*).await
+            })*
+        }
+
+        #[cfg(feature = "mock")]
+        #[async_trait]
+        impl ChromaApi for MockKhromaClient {
+            $(async fn $name(&self $(, $arg: $ty)*) -> Result<$ret, KhromaError> {
+                MockKhromaClient::$name(self $(, $arg)*).await
+            })*
+        }
+    };
+}
+
+chroma_api! {
+    fn get_user_identity(&self) -> GetUserIdentityResponse;
+    fn healthcheck(&self) -> String;
+    fn heartbeat(&self) -> HeartbeatResponse;
+    fn pre_flight_checks(&self) -> ChecklistResponse;
+    fn reset(&self) -> bool;
+    fn version(&self) -> String;
+    fn create_tenant(&self, payload: &CreateTenantPayload) -> CreateTenantResponse;
+    fn get_tenant(&self, tenant_name: &str) -> GetTenantResponse;
+    fn list_databases(&self, tenant: &str, limit: Option<i32>, offset: Option<i32>) -> Vec<Database>;
+    fn create_database(&self, tenant: &str, payload: &CreateDatabasePayload) -> CreateDatabaseResponse;
+    fn get_database(&self, tenant: &str, database: &str) -> Database;
+    fn delete_database(&self, tenant: &str, database: &str) -> DeleteDatabaseResponse;
+    fn list_collections(&self, tenant: &str, database: &str, limit: Option<i32>, offset: Option<i32>) -> Vec<Collection>;
+    fn create_collection(&self, tenant: &str, database: &str, payload: &CreateCollectionPayload) -> Collection;
+    fn get_collection(&self, tenant: &str, database: &str, collection_id: &str) -> Collection;
+    fn update_collection(&self, tenant: &str, database: &str, collection_id: &str, payload: &UpdateCollectionPayload) -> UpdateCollectionResponse;
+    fn delete_collection(&self, tenant: &str, database: &str, collection_id: &str) -> UpdateCollectionResponse;
+    fn collection_add(&self, tenant: &str, database: &str, collection_id: &str, payload: &AddCollectionRecordsPayload) -> AddCollectionRecordsResponse;
+    fn collection_count(&self, tenant: &str, database: &str, collection_id: &str) -> u32;
+    fn collection_delete(&self, tenant: &str, database: &str, collection_id: &str, payload: &DeleteCollectionRecordsPayload) -> DeleteCollectionRecordsResponse;
+    fn fork_collection(&self, tenant: &str, database: &str, collection_id: &str, payload: &ForkCollectionPayload) -> Collection;
+    fn collection_get(&self, tenant: &str, database: &str, collection_id: &str, payload: &GetRequestPayload) -> GetResponse;
+    fn collection_get_arena(&self, tenant: &str, database: &str, collection_id: &str, payload: &GetRequestPayload) -> ArenaGetResponse;
+    fn collection_query(&self, tenant: &str, database: &str, collection_id: &str, limit: Option<i32>, offset: Option<i32>, payload: &QueryRequestPayload) -> QueryResponse;
+    fn collection_query_arena(&self, tenant: &str, database: &str, collection_id: &str, limit: Option<i32>, offset: Option<i32>, payload: &QueryRequestPayload) -> ArenaQueryResponse;
+    fn collection_update(&self, tenant: &str, database: &str, collection_id: &str, payload: &UpdateCollectionRecordsPayload) -> UpdateCollectionRecordsResponse;
+    fn collection_upsert(&self, tenant: &str, database: &str, collection_id: &str, payload: &UpsertCollectionRecordsPayload) -> UpsertCollectionRecordsResponse;
+    fn count_collections(&self, tenant: &str, database: &str) -> u32;
+}
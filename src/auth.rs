@@ -0,0 +1,58 @@
+use crate::error::KhromaError;
+use reqwest::RequestBuilder;
+use reqwest::header::{HeaderName, HeaderValue};
+use std::fmt;
+
+/// How a client authenticates its requests. Credentials are redacted from `Debug` output.
+#[derive(Clone)]
+pub enum Auth {
+    /// Chroma's own `x-chroma-token` header, used by Chroma Cloud and token auth.
+    TokenHeader(String),
+    /// `Authorization: Bearer <token>`, e.g. for deployments behind an OAuth proxy.
+    Bearer(String),
+    /// HTTP basic authentication.
+    Basic { user: String, pass: String },
+    /// An arbitrary header, e.g. an API gateway key.
+    Custom { header: String, value: String },
+}
+
+impl Auth {
+    /// Checks that a custom header can be sent, so a typo fails when the client is built
+    /// rather than on the first request.
+    pub(crate) fn validate(&self) -> Result<(), KhromaError> {
+        let Auth::Custom { header, value } = self else {
+            return Ok(());
+        };
+        HeaderName::from_bytes(header.as_bytes())
+            .map_err(|e| KhromaError::Config(format!("Invalid auth header name: {}", e)))?;
+        HeaderValue::from_str(value)
+            .map_err(|e| KhromaError::Config(format!("Invalid auth header value: {}", e)))?;
+        Ok(())
+    }
+
+    pub(crate) fn apply(&self, builder: RequestBuilder) -> RequestBuilder {
+        match self {
+            Auth::TokenHeader(token) => builder.header("x-chroma-token", token),
+            Auth::Bearer(token) => builder.bearer_auth(token),
+            Auth::Basic { user, pass } => builder.basic_auth(user, Some(pass)),
+            Auth::Custom { header, value } => builder.header(header.as_str(), value),
+        }
+    }
+}
+
+impl fmt::Debug for Auth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Auth::TokenHeader(_) => f.write_str("TokenHeader(..)"),
+            Auth::Bearer(_) => f.write_str("Bearer(..)"),
+            Auth::Basic { user, .. } => f
+                .debug_struct("Basic")
+                .field("user", user)
+                .finish_non_exhaustive(),
+            Auth::Custom { header, .. } => f
+                .debug_struct("Custom")
+                .field("header", header)
+                .finish_non_exhaustive(),
+        }
+    }
+}
@@ -0,0 +1,415 @@
+use crate::error::KhromaError;
+use async_trait::async_trait;
+use futures::{StreamExt, TryStreamExt};
+
+/// The loaded contents of each record's URI, `None` where a record has no URI.
+pub type LoadedBlobs = Vec<Option<Vec<u8>>>;
+
+/// How many URIs the blanket [`DataLoader`] fetches at once.
+pub const LOAD_CONCURRENCY: usize = 16;
+
+/// An object store that holds the binary assets referenced by record `uris`.
+#[async_trait]
+pub trait BlobStore: Send + Sync {
+    /// Stores `data` under `key` and returns the URI to record in Chroma.
+    async fn put(&self, key: &str, data: Vec<u8>) -> Result<String, KhromaError>;
+
+    /// Fetches the bytes previously stored at `uri`.
+    async fn get(&self, uri: &str) -> Result<Vec<u8>, KhromaError>;
+}
+
+/// Resolves record URIs into their binary contents on read.
+#[async_trait]
+pub trait DataLoader: Send + Sync {
+    async fn load(&self, uris: &[String]) -> Result<Vec<Vec<u8>>, KhromaError>;
+}
+
+/// Fetches up to [`LOAD_CONCURRENCY`] URIs at a time, returning them in order.
+#[async_trait]
+impl<S: BlobStore + ?Sized> DataLoader for S {
+    async fn load(&self, uris: &[String]) -> Result<Vec<Vec<u8>>, KhromaError> {
+        let gets: Vec<_> = uris.iter().map(|uri| self.get(uri)).collect();
+        futures::stream::iter(gets)
+            .buffered(LOAD_CONCURRENCY)
+            .try_collect()
+            .await
+    }
+}
+
+/// Rejects keys that could escape a store's root: empty, absolute, or with `.` or `..`
+/// parts.
+pub(crate) fn validate_key(key: &str) -> Result<(), KhromaError> {
+    use std::path::{Component, Path};
+    let escapes = key.is_empty()
+        || key
+            .split(['/', '\\'])
+            .any(|part| part == "." || part == "..")
+        || !Path::new(key)
+            .components()
+            .all(|c| matches!(c, Component::Normal(_)));
+    if escapes {
+        return Err(KhromaError::Validation(format!(
+            "Blob keys must be relative paths without '.' or '..' parts: {}",
+            key
+        )));
+    }
+    Ok(())
+}
+
+/// A `BlobStore` writing assets below a local directory, addressed by `file://` URIs.
+///
+/// Keys are relative paths below the root; keys that are absolute or contain `.` or `..`
+/// parts are rejected, and so are keys whose file is a symlink. `get` only reads files
+/// below the root.
+#[cfg(feature = "fs-store")]
+#[derive(Debug, Clone)]
+pub struct LocalFsStore {
+    root: std::path::PathBuf,
+}
+
+#[cfg(feature = "fs-store")]
+impl LocalFsStore {
+    pub fn new(root: impl Into<std::path::PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// Fails unless `path` resolves to a location below the root, following symlinks.
+    async fn confine(&self, path: &std::path::Path) -> Result<(), KhromaError> {
+        let root = tokio::fs::canonicalize(&self.root).await?;
+        if tokio::fs::canonicalize(path).await?.starts_with(&root) {
+            Ok(())
+        } else {
+            Err(KhromaError::Validation(format!(
+                "{} is outside the store root {}",
+                path.display(),
+                self.root.display()
+            )))
+        }
+    }
+}
+
+#[cfg(feature = "fs-store")]
+#[async_trait]
+impl BlobStore for LocalFsStore {
+    async fn put(&self, key: &str, data: Vec<u8>) -> Result<String, KhromaError> {
+        validate_key(key)?;
+        let path = self.root.join(key);
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+            self.confine(parent).await?;
+        }
+        let leaf = tokio::fs::symlink_metadata(&path).await;
+        if leaf.is_ok_and(|metadata| metadata.file_type().is_symlink()) {
+            return Err(KhromaError::Validation(format!(
+                "{} is a symlink; blob files are never written through links",
+                path.display()
+            )));
+        }
+        tokio::fs::write(&path, data).await?;
+        let path = std::path::absolute(&path)?;
+        url::Url::from_file_path(&path)
+            .map(String::from)
+            .map_err(|_| {
+                KhromaError::Validation(format!("Cannot build URI for {}", path.display()))
+            })
+    }
+
+    async fn get(&self, uri: &str) -> Result<Vec<u8>, KhromaError> {
+        let path = url::Url::parse(uri)?
+            .to_file_path()
+            .map_err(|_| KhromaError::Validation(format!("Not a file URI: {}", uri)))?;
+        self.confine(&path).await?;
+        Ok(tokio::fs::read(path).await?)
+    }
+}
+
+/// A `BlobStore` keeping assets in an S3 bucket, addressed by `s3://bucket/key` URIs.
+///
+/// Requests are signed with static or temporary credentials. Keys follow the same rules
+/// as [`LocalFsStore`]'s, and `get` only reads objects in the store's bucket, below its
+/// prefix.
+#[cfg(feature = "s3-store")]
+#[derive(Clone)]
+pub struct S3Store {
+    client: reqwest::Client,
+    /// Set for S3-compatible services, which are addressed path-style.
+    endpoint: Option<url::Url>,
+    region: String,
+    bucket: String,
+    prefix: String,
+    credentials: crate::sigv4::AwsCredentials,
+}
+
+#[cfg(feature = "s3-store")]
+impl S3Store {
+    pub fn new(
+        region: impl Into<String>,
+        bucket: impl Into<String>,
+        access_key_id: impl Into<String>,
+        secret_access_key: impl Into<String>,
+    ) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            endpoint: None,
+            region: region.into(),
+            bucket: bucket.into(),
+            prefix: String::new(),
+            credentials: crate::sigv4::AwsCredentials {
+                access_key_id: access_key_id.into(),
+                secret_access_key: secret_access_key.into(),
+                session_token: None,
+            },
+        }
+    }
+
+    /// Reads the region and credentials from `AWS_REGION` (or `AWS_DEFAULT_REGION`),
+    /// `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY` and, if set, `AWS_SESSION_TOKEN`.
+    pub fn from_env(bucket: impl Into<String>) -> Result<Self, KhromaError> {
+        let mut store = Self::new(crate::sigv4::region_from_env()?, bucket, "", "");
+        store.credentials = crate::sigv4::AwsCredentials::from_env()?;
+        Ok(store)
+    }
+
+    /// Signs requests with temporary credentials.
+    pub fn with_session_token(mut self, session_token: impl Into<String>) -> Self {
+        self.credentials.session_token = Some(session_token.into());
+        self
+    }
+
+    /// Stores objects under `prefix`, e.g. `"assets/"`, instead of the bucket root.
+    pub fn with_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = prefix.into();
+        self
+    }
+
+    /// Targets an S3-compatible service, such as MinIO, at `endpoint` instead of AWS.
+    pub fn with_endpoint(mut self, endpoint: &str) -> Result<Self, KhromaError> {
+        self.endpoint = Some(url::Url::parse(endpoint)?);
+        Ok(self)
+    }
+
+    pub fn with_http_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    fn object_url(&self, key: &str) -> Result<url::Url, KhromaError> {
+        let key = s3_encode(key);
+        let url = match &self.endpoint {
+            Some(endpoint) => format!(
+                "{}/{}/{}",
+                endpoint.as_str().trim_end_matches('/'),
+                self.bucket,
+                key
+            ),
+            None => format!(
+                "https://{}.s3.{}.amazonaws.com/{}",
+                self.bucket, self.region, key
+            ),
+        };
+        Ok(url::Url::parse(&url)?)
+    }
+
+    async fn send(
+        &self,
+        method: reqwest::Method,
+        key: &str,
+        body: Vec<u8>,
+    ) -> Result<reqwest::Response, KhromaError> {
+        use crate::sigv4::{SigningRequest, payload_hash, signed_headers};
+        let url = self.object_url(key)?;
+        let request = SigningRequest {
+            method: method.as_str(),
+            url: &url,
+            service: "s3",
+            region: &self.region,
+            headers: vec![("x-amz-content-sha256", payload_hash(&body))],
+            payload: &body,
+        };
+        let headers = signed_headers(request, &self.credentials, std::time::SystemTime::now());
+        let mut req = self.client.request(method, url);
+        for (name, value) in headers {
+            req = req.header(name, value);
+        }
+        let res = req.body(body).send().await?;
+        let status = res.status();
+        if status.is_success() {
+            return Ok(res);
+        }
+        let message = res.text().await.unwrap_or_default();
+        Err(KhromaError::from_status(status, None, message, None))
+    }
+}
+
+#[cfg(feature = "s3-store")]
+#[async_trait]
+impl BlobStore for S3Store {
+    async fn put(&self, key: &str, data: Vec<u8>) -> Result<String, KhromaError> {
+        validate_key(key)?;
+        let key = format!("{}{}", self.prefix, key);
+        self.send(reqwest::Method::PUT, &key, data).await?;
+        Ok(format!("s3://{}/{}", self.bucket, key))
+    }
+
+    async fn get(&self, uri: &str) -> Result<Vec<u8>, KhromaError> {
+        let key = uri
+            .strip_prefix("s3://")
+            .and_then(|rest| rest.strip_prefix(self.bucket.as_str()))
+            .and_then(|rest| rest.strip_prefix('/'))
+            .filter(|key| key.starts_with(&self.prefix))
+            .ok_or_else(|| {
+                KhromaError::Validation(format!(
+                    "Not an object of s3://{}/{}: {}",
+                    self.bucket, self.prefix, uri
+                ))
+            })?;
+        validate_key(&key[self.prefix.len()..])?;
+        let res = self.send(reqwest::Method::GET, key, Vec::new()).await?;
+        Ok(res.bytes().await?.to_vec())
+    }
+}
+
+#[cfg(feature = "s3-store")]
+impl std::fmt::Debug for S3Store {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("S3Store")
+            .field("endpoint", &self.endpoint.as_ref().map(url::Url::as_str))
+            .field("region", &self.region)
+            .field("bucket", &self.bucket)
+            .field("prefix", &self.prefix)
+            .field("access_key_id", &self.credentials.access_key_id)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Percent-encodes an object key as S3 signs it: everything but unreserved characters
+/// and `/`.
+#[cfg(feature = "s3-store")]
+fn s3_encode(key: &str) -> String {
+    key.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(any(feature = "fs-store", feature = "s3-store"))]
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    #[test]
+    fn keys_with_dot_parts_are_rejected() {
+        for key in [".", "..", "./a", "a/.", "a/../b", "../b", "a/..", "a/./b"] {
+            assert!(validate_key(key).is_err(), "{}", key);
+        }
+    }
+
+    #[test]
+    fn absolute_empty_and_backslash_keys_are_rejected() {
+        for key in ["", "/etc/passwd", "/", "..\\b", "a\\..\\b", "a\\.\\b"] {
+            assert!(validate_key(key).is_err(), "{:?}", key);
+        }
+    }
+
+    #[test]
+    fn relative_keys_are_accepted() {
+        for key in [
+            "a",
+            "a/b.png",
+            ".hidden",
+            "a..b/c",
+            "dir/...",
+            "images/2024/cat.jpg",
+        ] {
+            assert!(validate_key(key).is_ok(), "{}", key);
+        }
+    }
+
+    #[cfg(feature = "fs-store")]
+    fn temp_dir(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("khroma-blob-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[cfg(feature = "fs-store")]
+    #[test]
+    fn fs_store_round_trips_below_root() {
+        let root = temp_dir("round-trip");
+        let store = LocalFsStore::new(&root);
+        let uri = block_on(store.put("a/b.bin", vec![1, 2, 3])).unwrap();
+        assert!(uri.starts_with("file://"));
+        assert_eq!(block_on(store.get(&uri)).unwrap(), [1, 2, 3]);
+        assert!(block_on(store.put("../escape.bin", vec![0])).is_err());
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[cfg(feature = "fs-store")]
+    #[test]
+    fn fs_store_reads_only_below_root() {
+        let root = temp_dir("confined");
+        let outside = temp_dir("outside");
+        std::fs::write(outside.join("secret"), b"secret").unwrap();
+        let store = LocalFsStore::new(&root);
+        let uri = url::Url::from_file_path(outside.join("secret")).unwrap();
+        assert!(block_on(store.get(uri.as_str())).is_err());
+        let traversal = format!("file://{}/../{}/secret", root.display(), outside.display());
+        assert!(block_on(store.get(&traversal)).is_err());
+        std::fs::remove_dir_all(root).unwrap();
+        std::fs::remove_dir_all(outside).unwrap();
+    }
+
+    #[cfg(all(feature = "fs-store", unix))]
+    #[test]
+    fn fs_store_does_not_write_through_symlinks() {
+        let root = temp_dir("symlinks");
+        let outside = temp_dir("symlink-target");
+        std::fs::write(outside.join("target"), b"original").unwrap();
+        std::os::unix::fs::symlink(outside.join("target"), root.join("leaf")).unwrap();
+        std::os::unix::fs::symlink(&outside, root.join("dir")).unwrap();
+        let store = LocalFsStore::new(&root);
+
+        assert!(block_on(store.put("leaf", b"changed".to_vec())).is_err());
+        assert!(block_on(store.put("dir/new", b"changed".to_vec())).is_err());
+        assert_eq!(std::fs::read(outside.join("target")).unwrap(), b"original");
+        assert!(!outside.join("new").exists());
+        std::fs::remove_dir_all(root).unwrap();
+        std::fs::remove_dir_all(outside).unwrap();
+    }
+
+    #[cfg(feature = "s3-store")]
+    #[test]
+    fn s3_store_reads_only_its_bucket_and_prefix() {
+        let store = S3Store::new("us-east-1", "assets", "key", "secret")
+            .with_prefix("images/")
+            .with_endpoint("http://127.0.0.1:9")
+            .unwrap();
+        for uri in [
+            "s3://other/images/a.png",
+            "s3://assets-other/images/a.png",
+            "s3://assets/docs/a.png",
+            "s3://assets/a.png",
+            "s3://assets/images/../secret",
+            "s3://assets/images/",
+            "https://assets.s3.amazonaws.com/images/a.png",
+        ] {
+            match block_on(store.get(uri)) {
+                Err(KhromaError::Validation(_)) => {}
+                other => panic!("{}: {:?}", uri, other.map(|_| ())),
+            }
+        }
+    }
+}
@@ -0,0 +1,741 @@
+//! Synchronous wrappers around the async API, for programs that don't run a Tokio runtime.
+//!
+//! Every handle drives requests on a private current-thread runtime shared by all handles
+//! created from the same client. Calling these methods from inside an async runtime panics;
+//! use the async API there instead.
+
+use crate::api::ChromaApi;
+use crate::blob::{BlobStore, DataLoader, LoadedBlobs};
+use crate::client;
+use crate::compression::Compression;
+use crate::config::KhromaConfig;
+use crate::degradation::{BudgetedQuery, DegradationPolicy, QueryOutcome};
+use crate::embedding::EmbeddingFunction;
+use crate::error::KhromaError;
+use crate::high_level::{self, ApplyReport, CollectionDetails, CollectionGetResult, DeleteReport, UpsertIfChangedResult};
+use crate::hybrid::{Fusion, HybridHit};
+use crate::ingest::{ImportFormat, ImportReport, ProgressEvent};
+use crate::models::{self, *};
+use crate::observer::ChangeObserver;
+use crate::page::{Page, PageToken};
+use crate::pipeline::{Pipeline, PipelineResult};
+use crate::priority::Priority;
+use crate::quality::QualityReport;
+use crate::read_cache::ReadCache;
+use crate::rerank::{RerankedHit, Reranker};
+use crate::retry::RetryPolicy;
+use futures::StreamExt;
+use std::io::{Read, Seek, Write};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::runtime::Runtime;
+
+fn runtime() -> Result<Arc<Runtime>, KhromaError> {
+    Ok(Arc::new(
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?,
+    ))
+}
+
+macro_rules! block_on {
+    ($(
+        $(#[$meta:meta])*
+        fn $name:ident(&self $(, $arg:ident: $ty:ty)*) -> $ret:ty;
+    )*) => {
+        $(
+            $(#[$meta])*
+            pub fn $name(&self $(, $arg: $ty)*) -> Result<$ret, KhromaError> {
+                self.runtime.block_on(self.inner.$name($($arg),*))
+            }
+        )*
+    };
+}
+
+/// Blocking counterpart of the low-level REST client.
+#[derive(Debug, Clone)]
+pub struct KhromaClient {
+    inner: client::KhromaClient,
+    runtime: Arc<Runtime>,
+}
+
+impl KhromaClient {
+    pub fn new(base_url: &str, token: Option<String>) -> Result<Self, KhromaError> {
+        Ok(Self {
+            inner: client::KhromaClient::new(base_url, token)?,
+            runtime: runtime()?,
+        })
+    }
+
+    /// Wraps a client configured through [`client::KhromaClient::builder`].
+    pub fn from_client(inner: client::KhromaClient) -> Result<Self, KhromaError> {
+        Ok(Self {
+            inner,
+            runtime: runtime()?,
+        })
+    }
+
+    block_on! {
+        fn get_user_identity(&self) -> GetUserIdentityResponse;
+        fn healthcheck(&self) -> String;
+        fn heartbeat(&self) -> HeartbeatResponse;
+        fn pre_flight_checks(&self) -> ChecklistResponse;
+        fn cached_pre_flight_checks(&self) -> ChecklistResponse;
+        fn reset(&self) -> bool;
+        fn version(&self) -> String;
+        fn create_tenant(&self, payload: &CreateTenantPayload) -> CreateTenantResponse;
+        fn get_tenant(&self, tenant_name: &str) -> GetTenantResponse;
+        fn list_databases(&self, tenant: &str, limit: Option<i32>, offset: Option<i32>) -> Vec<models::Database>;
+        fn create_database(&self, tenant: &str, payload: &CreateDatabasePayload) -> CreateDatabaseResponse;
+        fn get_database(&self, tenant: &str, database: &str) -> models::Database;
+        fn delete_database(&self, tenant: &str, database: &str) -> DeleteDatabaseResponse;
+        fn list_collections(&self, tenant: &str, database: &str, limit: Option<i32>, offset: Option<i32>) -> Vec<models::Collection>;
+        fn create_collection(&self, tenant: &str, database: &str, payload: &CreateCollectionPayload) -> models::Collection;
+        fn get_collection(&self, tenant: &str, database: &str, collection_id: &str) -> models::Collection;
+        fn update_collection(&self, tenant: &str, database: &str, collection_id: &str, payload: &UpdateCollectionPayload) -> UpdateCollectionResponse;
+        fn delete_collection(&self, tenant: &str, database: &str, collection_id: &str) -> UpdateCollectionResponse;
+        fn collection_add(&self, tenant: &str, database: &str, collection_id: &str, payload: &AddCollectionRecordsPayload) -> AddCollectionRecordsResponse;
+        fn collection_add_ref(&self, tenant: &str, database: &str, collection_id: &str, payload: &RecordsRef<'_>) -> AddCollectionRecordsResponse;
+        fn collection_count(&self, tenant: &str, database: &str, collection_id: &str) -> u32;
+        fn collection_delete(&self, tenant: &str, database: &str, collection_id: &str, payload: &DeleteCollectionRecordsPayload) -> DeleteCollectionRecordsResponse;
+        fn fork_collection(&self, tenant: &str, database: &str, collection_id: &str, payload: &ForkCollectionPayload) -> models::Collection;
+        fn collection_get(&self, tenant: &str, database: &str, collection_id: &str, payload: &GetRequestPayload) -> GetResponse;
+        fn collection_get_arena(&self, tenant: &str, database: &str, collection_id: &str, payload: &GetRequestPayload) -> ArenaGetResponse;
+        fn collection_query(&self, tenant: &str, database: &str, collection_id: &str, limit: Option<i32>, offset: Option<i32>, payload: &QueryRequestPayload) -> QueryResponse;
+        fn collection_query_arena(&self, tenant: &str, database: &str, collection_id: &str, limit: Option<i32>, offset: Option<i32>, payload: &QueryRequestPayload) -> ArenaQueryResponse;
+        fn collection_update(&self, tenant: &str, database: &str, collection_id: &str, payload: &UpdateCollectionRecordsPayload) -> UpdateCollectionRecordsResponse;
+        fn collection_upsert(&self, tenant: &str, database: &str, collection_id: &str, payload: &UpsertCollectionRecordsPayload) -> UpsertCollectionRecordsResponse;
+        fn collection_upsert_ref(&self, tenant: &str, database: &str, collection_id: &str, payload: &RecordsRef<'_>) -> UpsertCollectionRecordsResponse;
+        fn count_collections(&self, tenant: &str, database: &str) -> u32;
+        fn resolve_collection_id(&self, tenant: &str, database: &str, name: &str) -> CollectionUuid;
+    }
+}
+
+/// Blocking counterpart of [`high_level::Collection`].
+#[derive(Debug, Clone)]
+pub struct Collection {
+    inner: high_level::Collection,
+    runtime: Arc<Runtime>,
+}
+
+impl Collection {
+    pub fn id(&self) -> CollectionId {
+        self.inner.id
+    }
+
+    pub fn name(&self) -> &CollectionName {
+        &self.inner.name
+    }
+
+    pub fn tenant_name(&self) -> &str {
+        &self.inner.tenant_name
+    }
+
+    pub fn database_name(&self) -> &str {
+        &self.inner.database_name
+    }
+
+    pub fn model(&self) -> &models::Collection {
+        self.inner.model()
+    }
+
+    pub fn metadata(&self) -> Option<&Metadata> {
+        self.inner.metadata()
+    }
+
+    pub fn configuration(&self) -> &CollectionConfiguration {
+        self.inner.configuration()
+    }
+
+    pub fn dimension(&self) -> Option<i32> {
+        self.inner.dimension()
+    }
+
+    pub fn version(&self) -> i32 {
+        self.inner.version()
+    }
+
+    pub fn log_position(&self) -> i64 {
+        self.inner.log_position()
+    }
+
+    pub fn reload(&mut self) -> Result<(), KhromaError> {
+        self.runtime.block_on(self.inner.reload())
+    }
+
+    pub fn refresh_if_stale(&mut self) -> Result<bool, KhromaError> {
+        self.runtime.block_on(self.inner.refresh_if_stale())
+    }
+
+    pub fn update_if_unchanged(&mut self, payload: &UpdateCollectionPayload) -> Result<(), KhromaError> {
+        self.runtime.block_on(self.inner.update_if_unchanged(payload))
+    }
+
+    pub fn rename(&mut self, new_name: impl Into<String>) -> Result<(), KhromaError> {
+        self.runtime.block_on(self.inner.rename(new_name))
+    }
+
+    pub fn fork(
+        &self,
+        request: impl Into<ForkCollectionRequest>,
+    ) -> Result<Collection, KhromaError> {
+        Ok(Self {
+            inner: self.runtime.block_on(self.inner.fork(request))?,
+            runtime: self.runtime.clone(),
+        })
+    }
+
+    pub fn set_metadata(&mut self, metadata: Metadata) -> Result<(), KhromaError> {
+        self.runtime.block_on(self.inner.set_metadata(metadata))
+    }
+
+    pub fn with_default_includes(self, includes: DefaultIncludes) -> Self {
+        Self {
+            inner: self.inner.with_default_includes(includes),
+            runtime: self.runtime,
+        }
+    }
+
+    /// The async handle this wraps, e.g. for queuing operations on a [`Pipeline`].
+    pub fn as_async(&self) -> &high_level::Collection {
+        &self.inner
+    }
+
+    pub fn with_base_filter(self, filter: serde_json::Value) -> Self {
+        Self {
+            inner: self.inner.with_base_filter(filter),
+            runtime: self.runtime,
+        }
+    }
+
+    pub fn with_change_observer(self, observer: impl ChangeObserver + 'static) -> Self {
+        Self {
+            inner: self.inner.with_change_observer(observer),
+            runtime: self.runtime,
+        }
+    }
+
+    pub fn with_two_phase_fetch(self, policy: high_level::TwoPhaseFetch) -> Self {
+        Self {
+            inner: self.inner.with_two_phase_fetch(policy),
+            runtime: self.runtime,
+        }
+    }
+
+    pub fn with_read_cache(self, cache: ReadCache) -> Self {
+        Self {
+            inner: self.inner.with_read_cache(cache),
+            runtime: self.runtime,
+        }
+    }
+
+    pub fn with_priority(self, priority: Priority) -> Self {
+        Self {
+            inner: self.inner.with_priority(priority),
+            runtime: self.runtime,
+        }
+    }
+
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        Self {
+            inner: self.inner.with_timeout(timeout),
+            runtime: self.runtime,
+        }
+    }
+
+    pub fn with_degradation_policy(self, policy: DegradationPolicy) -> Self {
+        Self {
+            inner: self.inner.with_degradation_policy(policy),
+            runtime: self.runtime,
+        }
+    }
+
+    pub fn with_embedding_function(self, embedder: impl EmbeddingFunction + 'static) -> Self {
+        Self {
+            inner: self.inner.with_embedding_function(embedder),
+            runtime: self.runtime,
+        }
+    }
+
+    block_on! {
+        fn add(&self, payload: &AddCollectionRecordsPayload) -> ();
+        fn add_ref(&self, payload: &RecordsRef<'_>) -> ();
+        fn add_records(&self, records: Vec<Record>) -> ();
+        fn upsert_records(&self, records: Vec<Record>) -> ();
+        fn add_documents(&self, ids: &[&str], documents: &[&str]) -> ();
+        fn query_text(&self, text: &str, n_results: Option<i32>) -> QueryResponse;
+        fn query_by_texts(&self, texts: &[&str], n_results: Option<i32>) -> QueryResponse;
+        fn query_reranked(&self, payload: &QueryRequestPayload, reranker: &dyn Reranker, top_k: u32) -> Vec<Vec<RerankedHit>>;
+        fn hybrid_query(&self, text_filter: WhereDocument, query_embeddings: impl Into<EmbeddingsPayload>, n_results: u32, fusion: Fusion) -> Vec<HybridHit>;
+        fn upsert(&self, payload: &UpsertCollectionRecordsPayload) -> ();
+        fn upsert_ref(&self, payload: &RecordsRef<'_>) -> ();
+        fn upsert_if_changed(&self, payload: &UpsertCollectionRecordsPayload) -> UpsertIfChangedResult;
+        fn query(&self, payload: &QueryRequestPayload, limit: Option<i32>, offset: Option<i32>) -> QueryResponse;
+        fn query_records(&self, payload: &QueryRequestPayload, limit: Option<i32>, offset: Option<i32>) -> Vec<Vec<Record>>;
+        fn query_resilient(&self, payload: &QueryRequestPayload, limit: Option<i32>, offset: Option<i32>) -> QueryOutcome;
+        fn query_within(&self, payload: &QueryRequestPayload, budget: Duration, limit: Option<i32>, offset: Option<i32>) -> BudgetedQuery;
+        fn query_arena(&self, payload: &QueryRequestPayload, limit: Option<i32>, offset: Option<i32>) -> ArenaQueryResponse;
+        fn add_batched(&self, payload: &AddCollectionRecordsPayload, concurrency: usize) -> ();
+        fn add_batched_with_progress(&self, payload: &AddCollectionRecordsPayload, concurrency: usize, on_progress: impl FnMut(&ProgressEvent)) -> ();
+        fn upsert_batched(&self, payload: &UpsertCollectionRecordsPayload, concurrency: usize) -> ();
+        fn upsert_batched_with_progress(&self, payload: &UpsertCollectionRecordsPayload, concurrency: usize, on_progress: impl FnMut(&ProgressEvent)) -> ();
+        fn get(&self, payload: &GetRequestPayload) -> GetResponse;
+        fn get_records(&self, payload: &GetRequestPayload) -> Vec<Record>;
+        fn peek(&self, n: u32) -> GetResponse;
+        fn get_by_ids(&self, ids: &[&str], include: Option<IncludeList>) -> GetResponse;
+        fn get_page(&self, payload: &GetRequestPayload, page_size: u32, token: Option<&PageToken>) -> Page<Record>;
+        fn get_arena(&self, payload: &GetRequestPayload) -> ArenaGetResponse;
+        fn delete(&self, payload: &DeleteCollectionRecordsPayload) -> ();
+        fn delete_where(&self, filter: impl Into<RawWhereFields>, count: bool) -> DeleteReport;
+        fn update_records(&self, payload: &UpdateCollectionRecordsPayload) -> ();
+        fn update(&self, payload: &UpdateCollectionPayload) -> ();
+        fn warmup(&self, sample_queries: &[QueryRequestPayload]) -> ();
+        fn count(&self) -> u32;
+        fn quality_report(&self, sample_size: u32) -> QualityReport;
+    }
+
+    pub fn import(
+        &self,
+        reader: impl Read + Seek + Send + 'static,
+        format: ImportFormat,
+    ) -> ImportReport {
+        self.runtime.block_on(self.inner.import(reader, format))
+    }
+
+    pub fn import_from(
+        &self,
+        reader: impl Read + Seek + Send + 'static,
+        format: ImportFormat,
+        offset: u64,
+    ) -> ImportReport {
+        self.runtime
+            .block_on(self.inner.import_from(reader, format, offset))
+    }
+
+    pub fn export(
+        &self,
+        writer: impl Write + Send + 'static,
+        compression: Option<Compression>,
+    ) -> Result<u64, KhromaError> {
+        self.runtime.block_on(self.inner.export(writer, compression))
+    }
+
+    /// Iterates over the records matching `payload` in pages of `page_size`.
+    pub fn get_paginated(
+        &self,
+        payload: &GetRequestPayload,
+        page_size: u32,
+    ) -> impl Iterator<Item = Result<GetResponse, KhromaError>> + '_ {
+        let mut pages = Box::pin(self.inner.get_paginated(payload, page_size));
+        std::iter::from_fn(move || self.runtime.block_on(pages.next()))
+    }
+
+    pub fn add_with_blobs<S: BlobStore + ?Sized>(
+        &self,
+        store: &S,
+        payload: AddCollectionRecordsPayload,
+        blobs: Vec<Vec<u8>>,
+    ) -> Result<(), KhromaError> {
+        self.runtime
+            .block_on(self.inner.add_with_blobs(store, payload, blobs))
+    }
+
+    pub fn get_with_blobs<L: DataLoader + ?Sized>(
+        &self,
+        loader: &L,
+        payload: &GetRequestPayload,
+    ) -> Result<(GetResponse, LoadedBlobs), KhromaError> {
+        self.runtime
+            .block_on(self.inner.get_with_blobs(loader, payload))
+    }
+}
+
+/// Blocking counterpart of [`high_level::Database`].
+#[derive(Debug, Clone)]
+pub struct Database {
+    inner: high_level::Database,
+    runtime: Arc<Runtime>,
+}
+
+impl Database {
+    pub fn name(&self) -> &str {
+        &self.inner.name
+    }
+
+    pub fn tenant_name(&self) -> &str {
+        &self.inner.tenant_name
+    }
+
+    fn collection(&self, inner: high_level::Collection) -> Collection {
+        Collection {
+            inner,
+            runtime: self.runtime.clone(),
+        }
+    }
+
+    pub fn create_collection(
+        &self,
+        payload: &CreateCollectionPayload,
+    ) -> Result<Collection, KhromaError> {
+        let inner = self
+            .runtime
+            .block_on(self.inner.create_collection(payload))?;
+        Ok(self.collection(inner))
+    }
+
+    pub fn get_collection(&self, collection_id: impl TryInto<CollectionId, Error: Into<KhromaError>>) -> Result<Collection, KhromaError> {
+        let inner = self
+            .runtime
+            .block_on(self.inner.get_collection(collection_id))?;
+        Ok(self.collection(inner))
+    }
+
+    pub fn get_collection_by_name(&self, name: &str) -> Result<Collection, KhromaError> {
+        let inner = self
+            .runtime
+            .block_on(self.inner.get_collection_by_name(name))?;
+        Ok(self.collection(inner))
+    }
+
+    pub fn collection_exists(&self, name: &str) -> Result<bool, KhromaError> {
+        self.runtime.block_on(self.inner.collection_exists(name))
+    }
+
+    pub fn resolve_collection_id(
+        &self,
+        name: impl Into<CollectionName>,
+    ) -> Result<CollectionId, KhromaError> {
+        self.runtime
+            .block_on(self.inner.resolve_collection_id(name))
+    }
+
+    pub fn get_or_create_collection(
+        &self,
+        payload: CreateCollectionPayload,
+    ) -> Result<Collection, KhromaError> {
+        let inner = self
+            .runtime
+            .block_on(self.inner.get_or_create_collection(payload))?;
+        Ok(self.collection(inner))
+    }
+
+    pub fn find_collections(
+        &self,
+        where_metadata: &serde_json::Value,
+    ) -> Result<Vec<Collection>, KhromaError> {
+        let collections = self
+            .runtime
+            .block_on(self.inner.find_collections(where_metadata))?;
+        Ok(collections
+            .into_iter()
+            .map(|inner| self.collection(inner))
+            .collect())
+    }
+
+    pub fn list_collections(
+        &self,
+        limit: Option<i32>,
+        offset: Option<i32>,
+    ) -> Result<Vec<Collection>, KhromaError> {
+        let collections = self
+            .runtime
+            .block_on(self.inner.list_collections(limit, offset))?;
+        Ok(collections
+            .into_iter()
+            .map(|inner| self.collection(inner))
+            .collect())
+    }
+
+    pub fn list_collections_page(
+        &self,
+        page_size: u32,
+        token: Option<&PageToken>,
+    ) -> Result<Page<Collection>, KhromaError> {
+        let collections = self
+            .runtime
+            .block_on(self.inner.list_collections_page(page_size, token))?;
+        Ok(collections.map(|inner| self.collection(inner)))
+    }
+
+    pub fn delete_collection(
+        &self,
+        collection: impl Into<CollectionRef>,
+    ) -> Result<(), KhromaError> {
+        self.runtime
+            .block_on(self.inner.delete_collection(collection))
+    }
+
+    block_on! {
+        fn list_collections_detailed(&self, limit: Option<i32>, offset: Option<i32>, concurrency: usize) -> Vec<CollectionDetails>;
+        fn total_records(&self, concurrency: usize) -> u64;
+        fn apply_config(&self, spec: &DatabaseSpec) -> ApplyReport;
+        fn count_collections(&self) -> u32;
+    }
+}
+
+/// Blocking counterpart of [`high_level::Tenant`].
+#[derive(Debug, Clone)]
+pub struct Tenant {
+    inner: high_level::Tenant,
+    runtime: Arc<Runtime>,
+}
+
+impl Tenant {
+    pub fn name(&self) -> &str {
+        &self.inner.name
+    }
+
+    fn database(&self, inner: high_level::Database) -> Database {
+        Database {
+            inner,
+            runtime: self.runtime.clone(),
+        }
+    }
+
+    pub fn get_database(&self, name: &str) -> Result<Database, KhromaError> {
+        let inner = self.runtime.block_on(self.inner.get_database(name))?;
+        Ok(self.database(inner))
+    }
+
+    pub fn database_exists(&self, name: &str) -> Result<bool, KhromaError> {
+        self.runtime.block_on(self.inner.database_exists(name))
+    }
+
+    pub fn create_database(&self, name: &str) -> Result<Database, KhromaError> {
+        let inner = self.runtime.block_on(self.inner.create_database(name))?;
+        Ok(self.database(inner))
+    }
+
+    pub fn get_or_create_database(&self, name: &str) -> Result<Database, KhromaError> {
+        let inner = self
+            .runtime
+            .block_on(self.inner.get_or_create_database(name))?;
+        Ok(self.database(inner))
+    }
+
+    pub fn list_databases(
+        &self,
+        limit: Option<i32>,
+        offset: Option<i32>,
+    ) -> Result<Vec<Database>, KhromaError> {
+        let databases = self
+            .runtime
+            .block_on(self.inner.list_databases(limit, offset))?;
+        Ok(databases
+            .into_iter()
+            .map(|inner| self.database(inner))
+            .collect())
+    }
+
+    pub fn list_databases_page(
+        &self,
+        page_size: u32,
+        token: Option<&PageToken>,
+    ) -> Result<Page<Database>, KhromaError> {
+        let databases = self
+            .runtime
+            .block_on(self.inner.list_databases_page(page_size, token))?;
+        Ok(databases.map(|inner| self.database(inner)))
+    }
+
+    block_on! {
+        fn delete_database(&self, name: &str) -> ();
+    }
+}
+
+/// Blocking counterpart of [`high_level::Khroma`].
+#[derive(Debug, Clone)]
+pub struct Khroma {
+    inner: high_level::Khroma,
+    runtime: Arc<Runtime>,
+}
+
+impl Khroma {
+    pub fn new(base_url: &str, token: Option<String>) -> Result<Self, KhromaError> {
+        Ok(Self {
+            inner: high_level::Khroma::new(base_url, token)?,
+            runtime: runtime()?,
+        })
+    }
+
+    /// Wraps a client configured through [`client::KhromaClient::builder`].
+    pub fn from_client(client: client::KhromaClient) -> Result<Self, KhromaError> {
+        Ok(Self {
+            inner: high_level::Khroma::from_client(client),
+            runtime: runtime()?,
+        })
+    }
+
+    /// Runs the blocking API against `api` instead of a server.
+    pub fn from_api(api: impl ChromaApi + 'static) -> Result<Self, KhromaError> {
+        Self::from_client(client::KhromaClient::from_api(api))
+    }
+
+    pub fn cloud(
+        api_key: impl Into<String>,
+        tenant: &str,
+        database: &str,
+    ) -> Result<Database, KhromaError> {
+        Ok(Database {
+            inner: high_level::Khroma::cloud(api_key, tenant, database)?,
+            runtime: runtime()?,
+        })
+    }
+
+    pub fn from_config(config: &KhromaConfig) -> Result<Self, KhromaError> {
+        Ok(Self {
+            inner: high_level::Khroma::from_config(config)?,
+            runtime: runtime()?,
+        })
+    }
+
+    pub fn with_default_includes(self, includes: DefaultIncludes) -> Self {
+        Self {
+            inner: self.inner.with_default_includes(includes),
+            runtime: self.runtime,
+        }
+    }
+
+    pub fn with_query_coalescing(self, enabled: bool) -> Self {
+        Self {
+            inner: self.inner.with_query_coalescing(enabled),
+            runtime: self.runtime,
+        }
+    }
+
+    pub fn with_retry_policy(self, policy: RetryPolicy) -> Self {
+        Self {
+            inner: self.inner.with_retry_policy(policy),
+            runtime: self.runtime,
+        }
+    }
+
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        Self {
+            inner: self.inner.with_timeout(timeout),
+            runtime: self.runtime,
+        }
+    }
+
+    pub fn with_schema_drift_detection(self, enabled: bool) -> Self {
+        Self {
+            inner: self.inner.with_schema_drift_detection(enabled),
+            runtime: self.runtime,
+        }
+    }
+
+    pub fn with_idempotency_keys(self, enabled: bool) -> Self {
+        Self {
+            inner: self.inner.with_idempotency_keys(enabled),
+            runtime: self.runtime,
+        }
+    }
+
+    pub fn with_zeroized_request_bodies(self, enabled: bool) -> Self {
+        Self {
+            inner: self.inner.with_zeroized_request_bodies(enabled),
+            runtime: self.runtime,
+        }
+    }
+
+    fn tenant(&self, inner: high_level::Tenant) -> Tenant {
+        Tenant {
+            inner,
+            runtime: self.runtime.clone(),
+        }
+    }
+
+    pub fn default_tenant(&self) -> Option<Tenant> {
+        self.inner.default_tenant().map(|inner| self.tenant(inner))
+    }
+
+    pub fn default_database(&self) -> Option<Database> {
+        self.inner.default_database().map(|inner| Database {
+            inner,
+            runtime: self.runtime.clone(),
+        })
+    }
+
+    pub fn create_tenant(&self, name: &str) -> Result<Tenant, KhromaError> {
+        let inner = self.runtime.block_on(self.inner.create_tenant(name))?;
+        Ok(self.tenant(inner))
+    }
+
+    pub fn get_tenant(&self, name: &str) -> Result<Tenant, KhromaError> {
+        let inner = self.runtime.block_on(self.inner.get_tenant(name))?;
+        Ok(self.tenant(inner))
+    }
+
+    pub fn tenant_exists(&self, name: &str) -> Result<bool, KhromaError> {
+        self.runtime.block_on(self.inner.tenant_exists(name))
+    }
+
+    pub fn get_or_create_tenant(&self, name: &str) -> Result<Tenant, KhromaError> {
+        let inner = self
+            .runtime
+            .block_on(self.inner.get_or_create_tenant(name))?;
+        Ok(self.tenant(inner))
+    }
+
+    pub fn ensure(
+        &self,
+        tenant: &str,
+        database: &str,
+        collections: &[CreateCollectionPayload],
+    ) -> Result<(Database, Vec<Collection>), KhromaError> {
+        let (database, collections) = self
+            .runtime
+            .block_on(self.inner.ensure(tenant, database, collections))?;
+        let collections = collections
+            .into_iter()
+            .map(|inner| Collection {
+                inner,
+                runtime: self.runtime.clone(),
+            })
+            .collect();
+        let database = Database {
+            inner: database,
+            runtime: self.runtime.clone(),
+        };
+        Ok((database, collections))
+    }
+
+    pub fn pipeline(&self) -> Pipeline<'_> {
+        self.inner.pipeline()
+    }
+
+    pub fn execute_pipeline(
+        &self,
+        pipeline: Pipeline<'_>,
+    ) -> Vec<Result<PipelineResult, KhromaError>> {
+        self.runtime.block_on(pipeline.execute())
+    }
+
+    pub fn multi_get(
+        &self,
+        collections: &[Collection],
+        ids: &[String],
+        include: Option<IncludeList>,
+    ) -> Result<Vec<CollectionGetResult>, KhromaError> {
+        let collections: Vec<_> = collections.iter().map(|c| c.inner.clone()).collect();
+        self.runtime
+            .block_on(self.inner.multi_get(&collections, ids, include))
+    }
+
+    pub fn health(&self) -> HealthStatus {
+        self.runtime.block_on(self.inner.health())
+    }
+
+    pub fn self_test(&self) -> SelfTestReport {
+        self.runtime.block_on(self.inner.self_test())
+    }
+
+    block_on! {
+        fn version(&self) -> String;
+        fn heartbeat(&self) -> HeartbeatResponse;
+        fn healthcheck(&self) -> String;
+        fn reset(&self) -> bool;
+        fn reset_confirmed(&self, confirmation: &str) -> bool;
+        fn wait_until_ready(&self, timeout: Duration) -> HeartbeatResponse;
+    }
+}
@@ -0,0 +1,151 @@
+//! A canonical JSON encoding, so equal payloads always hash the same.
+//!
+//! Object keys are sorted, no whitespace is emitted, integral numbers are written without
+//! a fraction (`1.0` and `1` encode alike, as do `-0.0` and `0`) and other numbers use
+//! their shortest round-tripping form.
+
+use crate::error::KhromaError;
+use serde::Serialize;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::fmt::Write;
+
+/// Integral floats below this magnitude are written as integers; above it `f64` cannot
+/// represent every integer and the shortest float form is kept.
+const MAX_EXACT_INTEGER: f64 = 9_007_199_254_740_992.0;
+
+pub fn to_canonical_json<T: Serialize + ?Sized>(value: &T) -> Result<String, KhromaError> {
+    let value = serde_json::to_value(value).map_err(|e| KhromaError::Validation(e.to_string()))?;
+    let mut out = String::new();
+    write_value(&mut out, &value);
+    Ok(out)
+}
+
+/// The hex-encoded SHA-256 of the canonical JSON encoding of `value`.
+pub fn digest<T: Serialize + ?Sized>(value: &T) -> Result<String, KhromaError> {
+    let json = to_canonical_json(value)?;
+    Ok(format!("{:x}", Sha256::digest(json.as_bytes())))
+}
+
+fn write_value(out: &mut String, value: &Value) {
+    match value {
+        Value::Null => out.push_str("null"),
+        Value::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+        Value::Number(n) => write_number(out, n),
+        Value::String(s) => out.push_str(&Value::String(s.clone()).to_string()),
+        Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_value(out, item);
+            }
+            out.push(']');
+        }
+        Value::Object(map) => {
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_unstable_by_key(|(key, _)| key.as_str());
+            out.push('{');
+            for (i, (key, item)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push_str(&Value::String(key.clone()).to_string());
+                out.push(':');
+                write_value(out, item);
+            }
+            out.push('}');
+        }
+    }
+}
+
+fn write_number(out: &mut String, n: &serde_json::Number) {
+    if n.is_i64() || n.is_u64() {
+        let _ = write!(out, "{}", n);
+        return;
+    }
+    match n.as_f64() {
+        Some(f) if f.fract() == 0.0 && f.abs() < MAX_EXACT_INTEGER => {
+            let _ = write!(out, "{}", f as i64);
+        }
+        _ => {
+            let _ = write!(out, "{}", n);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn sorts_keys_at_every_level() {
+        let a = json!({"b": 1, "a": {"d": [{"f": 1, "e": 2}], "c": null}});
+        let b = json!({"a": {"c": null, "d": [{"e": 2, "f": 1}]}, "b": 1});
+        let expected = r#"{"a":{"c":null,"d":[{"e":2,"f":1}]},"b":1}"#;
+        assert_eq!(to_canonical_json(&a).unwrap(), expected);
+        assert_eq!(digest(&a).unwrap(), digest(&b).unwrap());
+    }
+
+    #[test]
+    fn keeps_array_order() {
+        assert_ne!(
+            digest(&json!([1, 2])).unwrap(),
+            digest(&json!([2, 1])).unwrap()
+        );
+    }
+
+    #[test]
+    fn writes_integral_floats_as_integers() {
+        assert_eq!(to_canonical_json(&json!(1.0)).unwrap(), "1");
+        assert_eq!(to_canonical_json(&json!(-3.0)).unwrap(), "-3");
+        assert_eq!(
+            digest(&json!({"n": 1.0})).unwrap(),
+            digest(&json!({"n": 1})).unwrap()
+        );
+    }
+
+    #[test]
+    fn writes_negative_zero_as_zero() {
+        assert_eq!(to_canonical_json(&json!(-0.0)).unwrap(), "0");
+        assert_eq!(digest(&json!(-0.0)).unwrap(), digest(&json!(0)).unwrap());
+    }
+
+    #[test]
+    fn keeps_fractions_in_shortest_form() {
+        assert_eq!(to_canonical_json(&json!(0.1)).unwrap(), "0.1");
+        assert_eq!(
+            to_canonical_json(&json!(0.1f32)).unwrap(),
+            "0.10000000149011612"
+        );
+    }
+
+    #[test]
+    fn keeps_float_form_from_two_to_the_53() {
+        let below = MAX_EXACT_INTEGER - 1.0;
+        assert_eq!(
+            to_canonical_json(&json!(below)).unwrap(),
+            "9007199254740991"
+        );
+        assert_eq!(
+            to_canonical_json(&json!(MAX_EXACT_INTEGER)).unwrap(),
+            "9007199254740992.0"
+        );
+        assert_eq!(to_canonical_json(&json!(1e300)).unwrap(), "1e300");
+        assert_eq!(
+            to_canonical_json(&json!(u64::MAX)).unwrap(),
+            "18446744073709551615"
+        );
+    }
+
+    #[test]
+    fn escapes_strings_and_keys() {
+        let value = json!({"quote\"": "line\nbreak"});
+        assert_eq!(
+            to_canonical_json(&value).unwrap(),
+            r#"{"quote\"":"line\nbreak"}"#
+        );
+    }
+}
@@ -0,0 +1,359 @@
+//! Splitting documents into chunks for ingestion, as records ready for
+//! [`Collection::add_records`](crate::Collection::add_records).
+
+use crate::models::{Metadata, Record};
+use serde_json::Value;
+
+/// A piece of a document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chunk {
+    pub text: String,
+    /// The byte offset of `text` in the document.
+    pub offset: usize,
+    /// The position of the chunk among the document's chunks, from 0.
+    pub index: usize,
+    /// The heading of the Markdown section the chunk belongs to.
+    pub heading: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Strategy {
+    Fixed { size: usize, overlap: usize },
+    Sentences { max_len: usize },
+    Markdown { max_len: usize },
+}
+
+/// Splits documents into chunks. Lengths are in bytes, and chunks never split a character.
+///
+/// Whitespace around chunks is trimmed, and [`Chunk::offset`] points at the trimmed text.
+#[derive(Debug, Clone, Copy)]
+pub struct Chunker {
+    strategy: Strategy,
+}
+
+impl Chunker {
+    /// Windows of `size` bytes, each overlapping the previous one by `overlap` bytes.
+    pub fn fixed(size: usize, overlap: usize) -> Self {
+        let size = size.max(1);
+        Self {
+            strategy: Strategy::Fixed {
+                size,
+                overlap: overlap.min(size - 1),
+            },
+        }
+    }
+
+    /// Whole sentences, packed into chunks of up to `max_len` bytes. Sentences end at `.`,
+    /// `!` or `?` followed by whitespace, and at blank lines. Longer sentences are cut into
+    /// `max_len` windows.
+    pub fn sentences(max_len: usize) -> Self {
+        Self {
+            strategy: Strategy::Sentences {
+                max_len: max_len.max(1),
+            },
+        }
+    }
+
+    /// One chunk per Markdown section, starting at each heading outside code fences.
+    /// Sections longer than `max_len` bytes are split into sentences. Chunks carry the
+    /// heading of their section.
+    pub fn markdown(max_len: usize) -> Self {
+        Self {
+            strategy: Strategy::Markdown {
+                max_len: max_len.max(1),
+            },
+        }
+    }
+
+    pub fn split(&self, text: &str) -> Vec<Chunk> {
+        let mut chunks = Chunks::default();
+        match self.strategy {
+            Strategy::Fixed { size, overlap } => fixed(text, 0, size, overlap, None, &mut chunks),
+            Strategy::Sentences { max_len } => sentences(text, 0, max_len, None, &mut chunks),
+            Strategy::Markdown { max_len } => {
+                for (start, end, heading) in sections(text) {
+                    let section = &text[start..end];
+                    if section.len() <= max_len {
+                        chunks.push(text, 0, start, end, heading.as_deref());
+                    } else {
+                        sentences(section, start, max_len, heading.as_deref(), &mut chunks);
+                    }
+                }
+            }
+        }
+        chunks.0
+    }
+
+    /// Splits `text` into records with ids `{source}#{index}`, the chunk as document, and
+    /// `source`, `offset`, `index` and, for Markdown sections, `heading` metadata.
+    ///
+    /// The records have no embeddings; collections with an embedding function embed them
+    /// when they are added.
+    pub fn records(&self, source: &str, text: &str) -> Vec<Record> {
+        self.split(text)
+            .into_iter()
+            .map(|chunk| {
+                let mut metadata = Metadata::new();
+                metadata.insert("source".into(), Value::from(source));
+                metadata.insert("offset".into(), Value::from(chunk.offset));
+                metadata.insert("index".into(), Value::from(chunk.index));
+                if let Some(heading) = chunk.heading {
+                    metadata.insert("heading".into(), Value::from(heading));
+                }
+                Record::new(format!("{}#{}", source, chunk.index))
+                    .with_document(chunk.text)
+                    .with_metadata(metadata)
+            })
+            .collect()
+    }
+}
+
+#[derive(Default)]
+struct Chunks(Vec<Chunk>);
+
+impl Chunks {
+    /// Adds `text[start..end]`, found at `base + start` in the document, without
+    /// surrounding whitespace, unless nothing is left.
+    fn push(&mut self, text: &str, base: usize, start: usize, end: usize, heading: Option<&str>) {
+        let slice = &text[start..end];
+        let trimmed = slice.trim_start();
+        let offset = base + start + slice.len() - trimmed.len();
+        let trimmed = trimmed.trim_end();
+        if trimmed.is_empty() {
+            return;
+        }
+        self.0.push(Chunk {
+            text: trimmed.to_string(),
+            offset,
+            index: self.0.len(),
+            heading: heading.map(str::to_string),
+        });
+    }
+}
+
+/// Splits `text`, found at `base` in the document, into overlapping windows.
+fn fixed(
+    text: &str,
+    base: usize,
+    size: usize,
+    overlap: usize,
+    heading: Option<&str>,
+    chunks: &mut Chunks,
+) {
+    let mut start = 0;
+    while start < text.len() {
+        let mut end = floor_boundary(text, (start + size).min(text.len()));
+        if end <= start {
+            end = ceil_boundary(text, start + 1);
+        }
+        chunks.push(text, base, start, end, heading);
+        if end == text.len() {
+            break;
+        }
+        start =
+            floor_boundary(text, end.saturating_sub(overlap)).max(ceil_boundary(text, start + 1));
+    }
+}
+
+/// Packs the sentences of `text`, found at `base` in the document, into chunks.
+fn sentences(text: &str, base: usize, max_len: usize, heading: Option<&str>, chunks: &mut Chunks) {
+    let mut start = 0;
+    let mut end = 0;
+    for sentence_end in sentence_ends(text) {
+        if sentence_end - start <= max_len {
+            end = sentence_end;
+            continue;
+        }
+        if end > start {
+            chunks.push(text, base, start, end, heading);
+            start = end;
+        }
+        if sentence_end - start > max_len {
+            fixed(
+                &text[start..sentence_end],
+                base + start,
+                max_len,
+                0,
+                heading,
+                chunks,
+            );
+            start = sentence_end;
+        }
+        end = sentence_end;
+    }
+    if end > start {
+        chunks.push(text, base, start, end, heading);
+    }
+}
+
+/// The byte offsets where the sentences of `text` end, the last being `text.len()`.
+fn sentence_ends(text: &str) -> Vec<usize> {
+    let mut ends = Vec::new();
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let next = chars.peek().map(|&(_, next)| next);
+        let boundary = match c {
+            '.' | '!' | '?' => next.is_some_and(char::is_whitespace),
+            '\n' => next == Some('\n'),
+            _ => false,
+        };
+        if boundary {
+            ends.push(i + c.len_utf8());
+        }
+    }
+    if ends.last() != Some(&text.len()) {
+        ends.push(text.len());
+    }
+    ends
+}
+
+/// The Markdown sections of `text` as byte ranges with their headings. Text before the
+/// first heading forms a section without one.
+fn sections(text: &str) -> Vec<(usize, usize, Option<String>)> {
+    let mut sections = Vec::new();
+    let mut start = 0;
+    let mut heading = None;
+    let mut in_fence = false;
+    let mut line_start = 0;
+    for line in text.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+        } else if !in_fence && trimmed.starts_with('#') {
+            let title = trimmed.trim_start_matches('#');
+            if title.is_empty() || title.starts_with(char::is_whitespace) {
+                if line_start > start {
+                    sections.push((start, line_start, heading.take()));
+                }
+                start = line_start;
+                heading = Some(title.trim().to_string());
+            }
+        }
+        line_start += line.len();
+    }
+    if text.len() > start {
+        sections.push((start, text.len(), heading));
+    }
+    sections
+}
+
+fn floor_boundary(text: &str, mut i: usize) -> usize {
+    while !text.is_char_boundary(i) {
+        i -= 1;
+    }
+    i
+}
+
+fn ceil_boundary(text: &str, mut i: usize) -> usize {
+    while i < text.len() && !text.is_char_boundary(i) {
+        i += 1;
+    }
+    i.min(text.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Every chunk's offset points at its text in the document.
+    fn assert_offsets(text: &str, chunks: &[Chunk]) {
+        for chunk in chunks {
+            assert_eq!(
+                &text[chunk.offset..chunk.offset + chunk.text.len()],
+                chunk.text
+            );
+        }
+    }
+
+    fn texts(chunks: &[Chunk]) -> Vec<&str> {
+        chunks.iter().map(|chunk| chunk.text.as_str()).collect()
+    }
+
+    #[test]
+    fn fixed_windows_overlap() {
+        let chunks = Chunker::fixed(4, 2).split("abcdefghij");
+        assert_eq!(texts(&chunks), ["abcd", "cdef", "efgh", "ghij"]);
+        assert_eq!(
+            chunks.iter().map(|c| c.offset).collect::<Vec<_>>(),
+            [0, 2, 4, 6]
+        );
+        assert_eq!(
+            chunks.iter().map(|c| c.index).collect::<Vec<_>>(),
+            [0, 1, 2, 3]
+        );
+    }
+
+    #[test]
+    fn fixed_windows_never_split_characters() {
+        let text = "héllo wörld ünïcode ✓✓✓";
+        for (size, overlap) in [(1, 0), (3, 1), (5, 2), (7, 6)] {
+            let chunks = Chunker::fixed(size, overlap).split(text);
+            assert_offsets(text, &chunks);
+            assert!(chunks.iter().all(|c| c.text.len() <= size.max(3)));
+            assert_eq!(
+                chunks.last().unwrap().offset + chunks.last().unwrap().text.len(),
+                text.len()
+            );
+        }
+    }
+
+    #[test]
+    fn overlap_is_capped_below_size() {
+        let chunks = Chunker::fixed(2, 10).split("abcd");
+        assert_eq!(texts(&chunks), ["ab", "bc", "cd"]);
+    }
+
+    #[test]
+    fn sentences_are_packed_with_byte_offsets() {
+        let text = "Größe zählt. Äpfel sind rot!  Ende?";
+        let chunks = Chunker::sentences(32).split(text);
+        assert_eq!(texts(&chunks), ["Größe zählt. Äpfel sind rot!", "Ende?"]);
+        assert_offsets(text, &chunks);
+        assert_eq!(chunks[1].offset, text.find("Ende").unwrap());
+    }
+
+    #[test]
+    fn long_sentences_are_cut_into_windows() {
+        let text = "ééééé. Short.";
+        let chunks = Chunker::sentences(4).split(text);
+        assert_offsets(text, &chunks);
+        assert!(chunks.iter().all(|c| c.text.len() <= 4));
+        assert_eq!(chunks.last().unwrap().text, "rt.");
+    }
+
+    #[test]
+    fn markdown_ignores_headings_in_fences() {
+        let text = "Preamble.\n# Intro\nHello.\n```sh\n# not a heading\n```\n~~~\n## nor this\n~~~\n## Next\nBody.\n";
+        let chunks = Chunker::markdown(1000).split(text);
+        assert_eq!(
+            chunks
+                .iter()
+                .map(|c| c.heading.as_deref())
+                .collect::<Vec<_>>(),
+            [None, Some("Intro"), Some("Next")]
+        );
+        assert!(chunks[1].text.contains("# not a heading"));
+        assert!(chunks[1].text.contains("## nor this"));
+        assert_eq!(chunks[2].text, "## Next\nBody.");
+        assert_offsets(text, &chunks);
+    }
+
+    #[test]
+    fn markdown_splits_long_sections_keeping_the_heading() {
+        let text = "# Über\nErster Satz. Zweiter Satz.\n#hashtag is not a heading.\n";
+        let chunks = Chunker::markdown(20).split(text);
+        assert!(chunks.len() > 1);
+        assert!(chunks.iter().all(|c| c.heading.as_deref() == Some("Über")));
+        assert_offsets(text, &chunks);
+    }
+
+    #[test]
+    fn records_carry_chunk_metadata() {
+        let records = Chunker::markdown(100).records("doc", "# A\nx\n# B\ny\n");
+        assert_eq!(records[1].id, "doc#1");
+        let metadata = records[1].metadata.as_ref().unwrap();
+        assert_eq!(metadata["heading"], "B");
+        assert_eq!(metadata["offset"], 6);
+        assert_eq!(metadata["source"], "doc");
+    }
+}
@@ -1,14 +1,394 @@
+use crate::api::ChromaApi;
+#[cfg(any(feature = "gzip", feature = "zstd"))]
+use crate::compression::{Compression, MIN_COMPRESSED_BYTES};
+use crate::auth::Auth;
+use crate::drift::DriftDetector;
 use crate::error::KhromaError;
+use crate::middleware::{Middleware, Middlewares};
+use crate::pipeline::Pipeline;
+use crate::priority::{Priority, PriorityLimits, PriorityQueue};
+use crate::retry::{self, RetryPolicy};
+use crate::secrets::{SecretsProvider, TokenSource};
 use crate::models::*;
+use crate::singleflight::SingleFlight;
+use crate::zeroize;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
 use reqwest::{Client as ReqwestClient, Response};
+use std::collections::HashMap;
+#[cfg(unix)]
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use url::Url;
 
+type NameKey = (String, String, String);
+
+/// Collection name to UUID mappings, keyed by tenant, database and name.
+#[derive(Debug, Clone, Default)]
+struct NameCache {
+    entries: Arc<Mutex<HashMap<NameKey, CollectionUuid>>>,
+}
+
+impl NameCache {
+    fn get(&self, tenant: &str, database: &str, name: &str) -> Option<CollectionUuid> {
+        let key = (tenant.to_string(), database.to_string(), name.to_string());
+        self.entries.lock().unwrap().get(&key).copied()
+    }
+
+    fn insert(&self, collection: &Collection) {
+        let key = (
+            collection.tenant.clone(),
+            collection.database.clone(),
+            collection.name.clone(),
+        );
+        self.entries.lock().unwrap().insert(key, collection.id);
+    }
+
+    fn invalidate(&self, tenant: &str, database: &str, collection_id: &str) {
+        self.entries.lock().unwrap().retain(|(t, d, _), id| {
+            t != tenant || d != database || id.to_string() != collection_id
+        });
+    }
+}
+
+/// Configures transport settings for a [`KhromaClient`].
+#[derive(Debug)]
+pub struct KhromaClientBuilder {
+    base_url: String,
+    auth: Option<Auth>,
+    token_source: Option<TokenSource>,
+    http_client: Option<ReqwestClient>,
+    timeout: Option<Duration>,
+    connect_timeout: Option<Duration>,
+    read_timeout: Option<Duration>,
+    default_headers: HeaderMap,
+    proxies: Vec<reqwest::Proxy>,
+    #[cfg(any(feature = "native-tls", feature = "rustls-tls"))]
+    root_certificates: Vec<reqwest::Certificate>,
+    #[cfg(any(feature = "native-tls", feature = "rustls-tls"))]
+    accept_invalid_certs: bool,
+    #[cfg(unix)]
+    unix_socket: Option<PathBuf>,
+    retry: RetryPolicy,
+    detect_schema_drift: bool,
+    idempotency_keys: bool,
+    base64_embeddings: bool,
+    allow_reset: bool,
+    priority_limits: Option<PriorityLimits>,
+    zeroize_bodies: bool,
+    #[cfg(any(feature = "gzip", feature = "zstd"))]
+    compression: Option<Compression>,
+    middlewares: Middlewares,
+}
+
+impl KhromaClientBuilder {
+    /// Sets the token sent in the 'x-chroma-token' header.
+    pub fn token(self, token: impl Into<String>) -> Self {
+        self.auth(Auth::TokenHeader(token.into()))
+    }
+
+    /// Sets how requests authenticate, replacing any token or secrets provider set before.
+    pub fn auth(mut self, auth: Auth) -> Self {
+        self.auth = Some(auth);
+        self.token_source = None;
+        self
+    }
+
+    /// Reads the token sent in the 'x-chroma-token' header from `provider`, fetching it
+    /// again on the first request after `refresh` has passed. Replaces any auth set before.
+    ///
+    /// Use [`build_async`](Self::build_async) to fetch the token while building, so a
+    /// misconfigured provider fails at startup rather than on the first request.
+    pub fn secrets_provider(
+        mut self,
+        provider: impl SecretsProvider + 'static,
+        refresh: Duration,
+    ) -> Self {
+        self.token_source = Some(TokenSource::new(provider, refresh));
+        self.auth = None;
+        self
+    }
+
+    /// Uses a preconfigured `reqwest::Client`.
+    ///
+    /// Timeouts, proxies and TLS settings must then be configured on that client; setting
+    /// them on this builder as well makes `build` fail.
+    pub fn http_client(mut self, client: ReqwestClient) -> Self {
+        self.http_client = Some(client);
+        self
+    }
+
+    /// Total time allowed for a request, from connecting until the response body is read.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Time allowed between reads from the connection.
+    pub fn read_timeout(mut self, timeout: Duration) -> Self {
+        self.read_timeout = Some(timeout);
+        self
+    }
+
+    /// Adds a header sent with every request.
+    pub fn default_header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.default_headers.insert(name, value);
+        self
+    }
+
+    /// Adds headers sent with every request.
+    pub fn default_headers(mut self, headers: HeaderMap) -> Self {
+        self.default_headers.extend(headers);
+        self
+    }
+
+    pub fn proxy(mut self, proxy: reqwest::Proxy) -> Self {
+        self.proxies.push(proxy);
+        self
+    }
+
+    /// Trusts `certificate` in addition to the system's roots, e.g. the CA of a self-hosted
+    /// server. Load it with `reqwest::Certificate::from_pem` or `from_der`.
+    #[cfg(any(feature = "native-tls", feature = "rustls-tls"))]
+    pub fn add_root_certificate(mut self, certificate: reqwest::Certificate) -> Self {
+        self.root_certificates.push(certificate);
+        self
+    }
+
+    /// Connects through the Unix domain socket at `path` instead of TCP, for servers behind
+    /// a local socket proxy. The host of the base URL is then only sent in the `Host`
+    /// header. A base URL of the form `unix:///path/to/socket` does the same.
+    ///
+    /// Other transports can be plugged in through [`http_client`](Self::http_client), with a
+    /// `reqwest::Client` built with `connector_layer`.
+    #[cfg(unix)]
+    pub fn unix_socket(mut self, path: impl Into<PathBuf>) -> Self {
+        self.unix_socket = Some(path.into());
+        self
+    }
+
+    /// Accepts any server certificate, including expired and self-signed ones. This
+    /// disables protection against impersonation; prefer
+    /// [`add_root_certificate`](Self::add_root_certificate) where possible.
+    #[cfg(any(feature = "native-tls", feature = "rustls-tls"))]
+    pub fn danger_accept_invalid_certs(mut self, accept: bool) -> Self {
+        self.accept_invalid_certs = accept;
+        self
+    }
+
+    /// Retries idempotent requests that fail transiently. Requests are sent once by default.
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry = policy;
+        self
+    }
+
+    /// Allows [`KhromaClient::reset`]. Off by default, since a reset wipes the server.
+    pub fn allow_reset(mut self, enabled: bool) -> Self {
+        self.allow_reset = enabled;
+        self
+    }
+
+    /// Logs a warning, through the `log` crate, the first time a response carries fields
+    /// khroma does not model or lacks fields it does. Meant for debugging server upgrades.
+    ///
+    /// A checked response is held three times over, as raw JSON, as its model and as the
+    /// model serialized back, and walked once more to compare them. To bound that cost,
+    /// only the first 16 responses of each type are checked, and none over 1 MiB, which
+    /// leaves out most gets and queries returning embeddings.
+    pub fn detect_schema_drift(mut self, enabled: bool) -> Self {
+        self.detect_schema_drift = enabled;
+        self
+    }
+
+    /// Sends an `Idempotency-Key` header, unique per call and kept across retries, with
+    /// add, upsert and delete requests. Adds are then retried like other writes, relying on
+    /// the key (or on the server ignoring ids it already has) to avoid double-applying them.
+    pub fn idempotency_keys(mut self, enabled: bool) -> Self {
+        self.idempotency_keys = enabled;
+        self
+    }
+
+    /// Sends the embeddings of adds, upserts and updates as base64-packed `f32`s when the
+    /// server's pre-flight checks report support, which shrinks them and speeds up parsing.
+    /// Query embeddings are always sent as JSON numbers, as the API takes nothing else.
+    pub fn base64_embeddings(mut self, enabled: bool) -> Self {
+        self.base64_embeddings = enabled;
+        self
+    }
+
+    /// Zeroes the memory holding each JSON request body, with its documents and metadata,
+    /// once the request is done, so they do not linger in freed memory or core dumps.
+    ///
+    /// This covers the buffers khroma allocates. Copies made by the TLS layer or the
+    /// operating system, and the payload structs themselves, are not affected.
+    pub fn zeroize_request_bodies(mut self, enabled: bool) -> Self {
+        self.zeroize_bodies = enabled;
+        self
+    }
+
+    /// Compresses JSON request bodies of 1 KiB and more, such as large adds and upserts,
+    /// and sends them with a `Content-Encoding` header. The server, or a proxy in front of
+    /// it, must accept compressed bodies.
+    ///
+    /// Compressed responses are accepted and decoded whenever the `gzip` or `zstd` feature
+    /// is enabled, independently of this setting.
+    #[cfg(any(feature = "gzip", feature = "zstd"))]
+    pub fn request_compression(mut self, compression: Compression) -> Self {
+        self.compression = Some(compression);
+        self
+    }
+
+    /// Caps the requests in flight per [`Priority`] class, so bulk work through
+    /// background handles cannot starve interactive ones. Requests are unlimited by default.
+    pub fn priority_limits(mut self, limits: PriorityLimits) -> Self {
+        self.priority_limits = Some(limits);
+        self
+    }
+
+    /// Runs `middleware` around every request the client sends; see [`Middleware`].
+    /// Can be called several times.
+    pub fn middleware(mut self, middleware: impl Middleware + 'static) -> Self {
+        self.middlewares.push(middleware);
+        self
+    }
+
+    pub fn build(self) -> Result<KhromaClient, KhromaError> {
+        if let Some(auth) = &self.auth {
+            auth.validate()?;
+        }
+        let base_url = Url::parse(&self.base_url)?;
+        #[cfg(unix)]
+        let (base_url, unix_socket) = match base_url.scheme() {
+            "unix" => (Url::parse("http://localhost/")?, Some(PathBuf::from(base_url.path()))),
+            _ => (base_url, self.unix_socket),
+        };
+        #[cfg(not(unix))]
+        if base_url.scheme() == "unix" {
+            return Err(KhromaError::Config(
+                "unix:// base URLs are only supported on Unix".to_string(),
+            ));
+        }
+        #[allow(unused_mut)]
+        let mut has_transport_options = self.timeout.is_some()
+            || self.connect_timeout.is_some()
+            || self.read_timeout.is_some()
+            || !self.proxies.is_empty();
+        #[cfg(any(feature = "native-tls", feature = "rustls-tls"))]
+        {
+            has_transport_options |= !self.root_certificates.is_empty() || self.accept_invalid_certs;
+        }
+        #[cfg(unix)]
+        {
+            if unix_socket.is_some() && !self.proxies.is_empty() {
+                return Err(KhromaError::Config(
+                    "proxies cannot be combined with a unix socket".to_string(),
+                ));
+            }
+            has_transport_options |= unix_socket.is_some();
+        }
+        let client = match self.http_client {
+            Some(_) if has_transport_options => {
+                return Err(KhromaError::Config(
+                    "timeouts, proxies, TLS settings and unix sockets cannot be combined with a custom reqwest::Client"
+                        .to_string(),
+                ));
+            }
+            Some(client) => client,
+            None => {
+                let mut builder = ReqwestClient::builder();
+                if let Some(timeout) = self.timeout {
+                    builder = builder.timeout(timeout);
+                }
+                if let Some(timeout) = self.connect_timeout {
+                    builder = builder.connect_timeout(timeout);
+                }
+                if let Some(timeout) = self.read_timeout {
+                    builder = builder.read_timeout(timeout);
+                }
+                for proxy in self.proxies {
+                    builder = builder.proxy(proxy);
+                }
+                #[cfg(any(feature = "native-tls", feature = "rustls-tls"))]
+                {
+                    for certificate in self.root_certificates {
+                        builder = builder.add_root_certificate(certificate);
+                    }
+                    builder = builder.danger_accept_invalid_certs(self.accept_invalid_certs);
+                }
+                #[cfg(unix)]
+                if let Some(path) = unix_socket {
+                    builder = builder.unix_socket(path);
+                }
+                builder.build()?
+            }
+        };
+        Ok(KhromaClient {
+            client,
+            base_url,
+            auth: self.auth,
+            token_source: self.token_source,
+            default_headers: self.default_headers,
+            retry: self.retry,
+            drift: self.detect_schema_drift.then(DriftDetector::default),
+            idempotency_keys: self.idempotency_keys,
+            base64_embeddings: self.base64_embeddings,
+            allow_reset: self.allow_reset,
+            preflight: Arc::default(),
+            names: NameCache::default(),
+            default_includes: DefaultIncludes::default(),
+            query_flights: None,
+            priority_queue: self.priority_limits.map(PriorityQueue::new),
+            priority: Priority::default(),
+            zeroize_bodies: self.zeroize_bodies,
+            #[cfg(any(feature = "gzip", feature = "zstd"))]
+            compression: self.compression,
+            middlewares: self.middlewares,
+            backend: None,
+            request_timeout: None,
+        })
+    }
+
+    /// Like [`build`](Self::build), but also fetches the token from the secrets provider,
+    /// if one is set.
+    pub async fn build_async(self) -> Result<KhromaClient, KhromaError> {
+        let client = self.build()?;
+        if let Some(source) = &client.token_source {
+            source.auth().await?;
+        }
+        Ok(client)
+    }
+}
+
 /// The main client for interacting with the Chroma API.
 #[derive(Debug, Clone)]
 pub struct KhromaClient {
     client: ReqwestClient,
     base_url: Url,
-    token: Option<String>,
+    auth: Option<Auth>,
+    token_source: Option<TokenSource>,
+    default_headers: HeaderMap,
+    retry: RetryPolicy,
+    drift: Option<DriftDetector>,
+    idempotency_keys: bool,
+    base64_embeddings: bool,
+    allow_reset: bool,
+    preflight: Arc<Mutex<Option<ChecklistResponse>>>,
+    names: NameCache,
+    default_includes: DefaultIncludes,
+    query_flights: Option<SingleFlight<QueryResponse>>,
+    priority_queue: Option<PriorityQueue>,
+    priority: Priority,
+    zeroize_bodies: bool,
+    #[cfg(any(feature = "gzip", feature = "zstd"))]
+    compression: Option<Compression>,
+    middlewares: Middlewares,
+    backend: Option<Arc<dyn ChromaApi>>,
+    request_timeout: Option<Duration>,
 }
 
 impl KhromaClient {
@@ -19,28 +399,222 @@ impl KhromaClient {
     /// * `base_url` - The base URL of the Chroma server (e.g., "http://localhost:8000").
     /// * `token` - An optional authentication token for the 'x-chroma-token' header.
     pub fn new(base_url: &str, token: Option<String>) -> Result<Self, KhromaError> {
-        Ok(Self {
-            client: ReqwestClient::new(),
-            base_url: Url::parse(base_url)?,
-            token,
-        })
+        let mut builder = Self::builder(base_url);
+        if let Some(token) = token {
+            builder = builder.token(token);
+        }
+        builder.build()
+    }
+
+    /// Starts configuring a client with custom transport settings.
+    pub fn builder(base_url: &str) -> KhromaClientBuilder {
+        KhromaClientBuilder {
+            base_url: base_url.to_string(),
+            auth: None,
+            token_source: None,
+            http_client: None,
+            timeout: None,
+            connect_timeout: None,
+            read_timeout: None,
+            default_headers: HeaderMap::new(),
+            proxies: Vec::new(),
+            #[cfg(any(feature = "native-tls", feature = "rustls-tls"))]
+            root_certificates: Vec::new(),
+            #[cfg(any(feature = "native-tls", feature = "rustls-tls"))]
+            accept_invalid_certs: false,
+            #[cfg(unix)]
+            unix_socket: None,
+            retry: RetryPolicy::none(),
+            detect_schema_drift: false,
+            idempotency_keys: false,
+            base64_embeddings: false,
+            allow_reset: false,
+            priority_limits: None,
+            zeroize_bodies: false,
+            #[cfg(any(feature = "gzip", feature = "zstd"))]
+            compression: None,
+            middlewares: Middlewares::default(),
+        }
+    }
+
+    /// A client whose endpoint methods are answered by `api` instead of over HTTP, e.g. a
+    /// [`MockKhromaClient`](crate::mock::MockKhromaClient) in tests. Client-side features
+    /// such as default includes and the name cache still apply; transport settings,
+    /// retries and priority limits do not.
+    pub fn from_api(api: impl ChromaApi + 'static) -> Self {
+        let mut client = Self::builder("http://localhost/")
+            .http_client(ReqwestClient::new())
+            .build()
+            .expect("a client without auth or transport options always builds");
+        client.backend = Some(Arc::new(api));
+        client
+    }
+
+    pub(crate) fn base_url(&self) -> &Url {
+        &self.base_url
+    }
+
+    pub(crate) fn default_includes(&self) -> &DefaultIncludes {
+        &self.default_includes
+    }
+
+    pub(crate) fn set_default_includes(&mut self, includes: DefaultIncludes) {
+        self.default_includes = includes;
+    }
+
+    /// Shares one HTTP call between concurrent identical queries when enabled.
+    pub(crate) fn set_query_coalescing(&mut self, enabled: bool) {
+        self.query_flights = enabled.then(SingleFlight::default);
+    }
+
+    pub(crate) fn set_schema_drift_detection(&mut self, enabled: bool) {
+        self.drift = enabled.then(DriftDetector::default);
+    }
+
+    pub(crate) fn set_idempotency_keys(&mut self, enabled: bool) {
+        self.idempotency_keys = enabled;
+    }
+
+    pub(crate) fn set_retry_policy(&mut self, policy: RetryPolicy) {
+        self.retry = policy;
+    }
+
+    pub(crate) fn set_zeroize_request_bodies(&mut self, enabled: bool) {
+        self.zeroize_bodies = enabled;
+    }
+
+    /// Overrides the client's total timeout for each attempt of this client's requests.
+    pub(crate) fn set_request_timeout(&mut self, timeout: Duration) {
+        self.request_timeout = Some(timeout);
+    }
+
+    /// The class this client's requests are queued in under its priority limits.
+    pub(crate) fn set_priority(&mut self, priority: Priority) {
+        self.priority = priority;
+    }
+
+    /// Sends a request, retrying it under the retry policy when its method is idempotent.
+    async fn send(&self, req: reqwest::RequestBuilder) -> Result<Response, KhromaError> {
+        self.execute(req, false).await
+    }
+
+    /// Sends a `POST` request whose repetition has no further effect, retrying it like a `GET`.
+    async fn send_idempotent(&self, req: reqwest::RequestBuilder) -> Result<Response, KhromaError> {
+        self.execute(req, true).await
     }
 
-    async fn handle_response<T: serde::de::DeserializeOwned>(
+    /// Attaches `payload` as the JSON body, compressed when enabled and large enough, in a
+    /// buffer zeroed after use when enabled.
+    fn with_json<T: serde::Serialize + ?Sized>(&self, req: reqwest::RequestBuilder, payload: &T) -> Result<reqwest::RequestBuilder, KhromaError> {
+        #[cfg(any(feature = "gzip", feature = "zstd"))]
+        let compress = self.compression.is_some();
+        #[cfg(not(any(feature = "gzip", feature = "zstd")))]
+        let compress = false;
+        if !self.zeroize_bodies && !compress {
+            return Ok(req.json(payload));
+        }
+        let body = if self.zeroize_bodies {
+            zeroize::json_body(payload)?
+        } else {
+            serde_json::to_vec(payload).map_err(|e| KhromaError::Validation(e.to_string()))?.into()
+        };
+        let req = req.header(reqwest::header::CONTENT_TYPE, "application/json");
+        #[cfg(any(feature = "gzip", feature = "zstd"))]
+        if let Some(compression) = self.compression.filter(|_| body.len() >= MIN_COMPRESSED_BYTES) {
+            let compressed = compression.compress(&body)?;
+            let compressed = if self.zeroize_bodies { zeroize::body(compressed) } else { compressed.into() };
+            return Ok(req.header(reqwest::header::CONTENT_ENCODING, compression.encoding()).body(compressed));
+        }
+        Ok(req.body(body))
+    }
+
+    /// Whether float embeddings go out as base64: enabled and supported by the server.
+    async fn sends_base64(&self) -> Result<bool, KhromaError> {
+        if !self.base64_embeddings {
+            return Ok(false);
+        }
+        Ok(self.cached_pre_flight_checks().await?.supports_base64_encoding)
+    }
+
+    fn with_idempotency_key(&self, req: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        if self.idempotency_keys {
+            req.header("Idempotency-Key", uuid::Uuid::new_v4().to_string())
+        } else {
+            req
+        }
+    }
+
+    async fn execute(&self, mut req: reqwest::RequestBuilder, idempotent: bool) -> Result<Response, KhromaError> {
+        if let Some(source) = &self.token_source {
+            req = source.auth().await?.apply(req);
+        }
+        let mut request = req.build()?;
+        self.middlewares.on_request(&mut request).await?;
+        #[cfg(feature = "tracing")]
+        let span = tracing::Span::current();
+        #[cfg(feature = "tracing")]
+        span.record("endpoint", request.url().path())
+            .record("payload_bytes", request.body().and_then(|body| body.as_bytes()).map_or(0, <[u8]>::len));
+        let method = request.method().clone();
+        let idempotent = idempotent || method.is_idempotent();
+        let policy = if idempotent { &self.retry } else { &RetryPolicy::none() };
+        let _permit = match &self.priority_queue {
+            Some(queue) => Some(queue.acquire(self.priority).await),
+            None => None,
+        };
+        let started = Instant::now();
+        let mut response = policy.execute(&self.client, request).await?;
+        #[cfg(feature = "tracing")]
+        span.record("status", response.status().as_u16())
+            .record("latency_ms", started.elapsed().as_millis() as u64);
+        self.middlewares.on_response(&method, started.elapsed(), &mut response).await?;
+        Ok(response)
+    }
+
+    async fn handle_response<T: serde::de::DeserializeOwned + serde::Serialize>(
         &self,
         res: Response,
     ) -> Result<T, KhromaError> {
         let status = res.status();
         if status.is_success() {
-            res.json::<T>().await.map_err(|e| {
+            let Some(drift) = self
+                .drift
+                .as_ref()
+                .filter(|drift| drift.wants::<T>(res.content_length()))
+            else {
+                return res.json::<T>().await.map_err(|e| {
+                    KhromaError::Parse(format!("Failed to deserialize successful response: {}", e))
+                });
+            };
+            let endpoint = res.url().path().to_string();
+            let raw: serde_json::Value = res.json().await.map_err(|e| {
                 KhromaError::Parse(format!("Failed to deserialize successful response: {}", e))
-            })
+            })?;
+            let parsed = T::deserialize(&raw).map_err(|e| {
+                KhromaError::Parse(format!("Failed to deserialize successful response: {}", e))
+            })?;
+            if let Ok(modeled) = serde_json::to_value(&parsed) {
+                drift.check::<T>(&endpoint, &raw, &modeled);
+            }
+            Ok(parsed)
         } else {
-            let message = match res.json::<ErrorResponse>().await {
-                Ok(err_res) => err_res.message,
-                Err(_) => format!("Failed to parse error response. Status: {}", status),
-            };
-            Err(KhromaError::Api { status, message })
+            Err(Self::error_from_response(res).await)
+        }
+    }
+
+    async fn error_from_response(res: Response) -> KhromaError {
+        let status = res.status();
+        let retry_after = retry::retry_after(&res);
+        match res.json::<ErrorResponse>().await {
+            Ok(err_res) => {
+                KhromaError::from_status(status, Some(&err_res.error), err_res.message, retry_after)
+            }
+            Err(_) => KhromaError::from_status(
+                status,
+                None,
+                format!("Failed to parse error response. Status: {}", status),
+                retry_after,
+            ),
         }
     }
 
@@ -49,11 +623,7 @@ impl KhromaClient {
         if status.is_success() {
             res.text().await.map_err(KhromaError::from)
         } else {
-            let message = match res.json::<ErrorResponse>().await {
-                Ok(err_res) => err_res.message,
-                Err(_) => format!("Failed to parse error response. Status: {}", status),
-            };
-            Err(KhromaError::Api { status, message })
+            Err(Self::error_from_response(res).await)
         }
     }
 
@@ -64,72 +634,136 @@ impl KhromaClient {
     ) -> Result<reqwest::RequestBuilder, KhromaError> {
         let url = self.base_url.join(path.as_ref())?;
         let mut builder = self.client.request(method, url);
-        if let Some(token) = &self.token {
-            builder = builder.header("x-chroma-token", token);
+        if !self.default_headers.is_empty() {
+            builder = builder.headers(self.default_headers.clone());
+        }
+        if let Some(auth) = &self.auth {
+            builder = auth.apply(builder);
+        }
+        if let Some(timeout) = self.request_timeout {
+            builder = builder.timeout(timeout);
         }
         Ok(builder)
     }
 
     /// GET /api/v2/auth/identity - Retrieves the current user's identity, tenant, and databases.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self), fields(endpoint, status, payload_bytes, latency_ms), err(level = "debug")))]
     pub async fn get_user_identity(&self) -> Result<GetUserIdentityResponse, KhromaError> {
+        if let Some(api) = &self.backend {
+            return api.get_user_identity().await;
+        }
         let req = self.build_request(reqwest::Method::GET, "/api/v2/auth/identity")?;
-        let res = req.send().await?;
+        let res = self.send(req).await?;
         self.handle_response(res).await
     }
 
     /// GET /api/v2/healthcheck - Health check endpoint.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self), fields(endpoint, status, payload_bytes, latency_ms), err(level = "debug")))]
     pub async fn healthcheck(&self) -> Result<String, KhromaError> {
+        if let Some(api) = &self.backend {
+            return api.healthcheck().await;
+        }
         let req = self.build_request(reqwest::Method::GET, "/api/v2/healthcheck")?;
-        let res = req.send().await?;
+        let res = self.send(req).await?;
         self.handle_text_response(res).await
     }
 
     /// GET /api/v2/heartbeat - Heartbeat endpoint.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self), fields(endpoint, status, payload_bytes, latency_ms), err(level = "debug")))]
     pub async fn heartbeat(&self) -> Result<HeartbeatResponse, KhromaError> {
+        if let Some(api) = &self.backend {
+            return api.heartbeat().await;
+        }
         let req = self.build_request(reqwest::Method::GET, "/api/v2/heartbeat")?;
-        let res = req.send().await?;
+        let res = self.send(req).await?;
         self.handle_response(res).await
     }
 
     /// GET /api/v2/pre-flight-checks - Pre-flight checks endpoint.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self), fields(endpoint, status, payload_bytes, latency_ms), err(level = "debug")))]
     pub async fn pre_flight_checks(&self) -> Result<ChecklistResponse, KhromaError> {
+        if let Some(api) = &self.backend {
+            return api.pre_flight_checks().await;
+        }
         let req = self.build_request(reqwest::Method::GET, "/api/v2/pre-flight-checks")?;
-        let res = req.send().await?;
+        let res = self.send(req).await?;
         self.handle_response(res).await
     }
 
-    /// POST /api/v2/reset - Reset the database.
+    /// Starts a batch of reads that run concurrently; see [`Pipeline`].
+    pub fn pipeline(&self) -> Pipeline<'_> {
+        Pipeline::new(self)
+    }
+
+    /// Returns the server's pre-flight checks, fetching them only once per client.
+    pub async fn cached_pre_flight_checks(&self) -> Result<ChecklistResponse, KhromaError> {
+        if let Some(checks) = self.preflight.lock().unwrap().clone() {
+            return Ok(checks);
+        }
+        let checks = self.pre_flight_checks().await?;
+        *self.preflight.lock().unwrap() = Some(checks.clone());
+        Ok(checks)
+    }
+
+    /// POST /api/v2/reset - Reset the database. Fails unless enabled with
+    /// [`KhromaClientBuilder::allow_reset`].
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self), fields(endpoint, status, payload_bytes, latency_ms), err(level = "debug")))]
     pub async fn reset(&self) -> Result<bool, KhromaError> {
+        if !self.allow_reset {
+            return Err(KhromaError::ResetNotAllowed(
+                "reset is disabled; enable it with KhromaClientBuilder::allow_reset(true)"
+                    .to_string(),
+            ));
+        }
+        if let Some(api) = &self.backend {
+            return api.reset().await;
+        }
         let req = self.build_request(reqwest::Method::POST, "/api/v2/reset")?;
-        let res = req.send().await?;
+        let res = self.send(req).await?;
         let text = self.handle_text_response(res).await?;
         text.parse::<bool>().map_err(|e| KhromaError::Parse(e.to_string()))
     }
 
     /// GET /api/v2/version - Returns the version of the server.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self), fields(endpoint, status, payload_bytes, latency_ms), err(level = "debug")))]
     pub async fn version(&self) -> Result<String, KhromaError> {
+        if let Some(api) = &self.backend {
+            return api.version().await;
+        }
         let req = self.build_request(reqwest::Method::GET, "/api/v2/version")?;
-        let res = req.send().await?;
+        let res = self.send(req).await?;
         self.handle_text_response(res).await
     }
 
     /// POST /api/v2/tenants - Creates a new tenant.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, payload), fields(endpoint, status, payload_bytes, latency_ms), err(level = "debug")))]
     pub async fn create_tenant(&self, payload: &CreateTenantPayload) -> Result<CreateTenantResponse, KhromaError> {
-        let req = self.build_request(reqwest::Method::POST, "/api/v2/tenants")?.json(payload);
-        let res = req.send().await?;
+        if let Some(api) = &self.backend {
+            return api.create_tenant(payload).await;
+        }
+        let req = self.with_json(self.build_request(reqwest::Method::POST, "/api/v2/tenants")?, payload)?;
+        let res = self.send(req).await?;
         self.handle_response(res).await
     }
 
     /// GET /api/v2/tenants/{tenant_name} - Returns an existing tenant by name.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self), fields(endpoint, status, payload_bytes, latency_ms), err(level = "debug")))]
     pub async fn get_tenant(&self, tenant_name: &str) -> Result<GetTenantResponse, KhromaError> {
+        if let Some(api) = &self.backend {
+            return api.get_tenant(tenant_name).await;
+        }
         let path = format!("/api/v2/tenants/{}", tenant_name);
         let req = self.build_request(reqwest::Method::GET, &path)?;
-        let res = req.send().await?;
+        let res = self.send(req).await?;
         self.handle_response(res).await
     }
 
     /// GET /api/v2/tenants/{tenant}/databases - Lists all databases for a given tenant.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self), fields(endpoint, status, payload_bytes, latency_ms), err(level = "debug")))]
     pub async fn list_databases(&self, tenant: &str, limit: Option<i32>, offset: Option<i32>) -> Result<Vec<Database>, KhromaError> {
+        if let Some(api) = &self.backend {
+            return api.list_databases(tenant, limit, offset).await;
+        }
         let path = format!("/api/v2/tenants/{}/databases", tenant);
         let mut req = self.build_request(reqwest::Method::GET, &path)?;
         let mut query_params = Vec::new();
@@ -138,37 +772,53 @@ impl KhromaClient {
         if !query_params.is_empty() {
             req = req.query(&query_params);
         }
-        let res = req.send().await?;
+        let res = self.send(req).await?;
         // The spec uses a generic `Vec` schema name, but the items are Databases.
         self.handle_response(res).await
     }
 
     /// POST /api/v2/tenants/{tenant}/databases - Creates a new database for a given tenant.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, payload), fields(endpoint, status, payload_bytes, latency_ms), err(level = "debug")))]
     pub async fn create_database(&self, tenant: &str, payload: &CreateDatabasePayload) -> Result<CreateDatabaseResponse, KhromaError> {
+        if let Some(api) = &self.backend {
+            return api.create_database(tenant, payload).await;
+        }
         let path = format!("/api/v2/tenants/{}/databases", tenant);
-        let req = self.build_request(reqwest::Method::POST, &path)?.json(payload);
-        let res = req.send().await?;
+        let req = self.with_json(self.build_request(reqwest::Method::POST, &path)?, payload)?;
+        let res = self.send(req).await?;
         self.handle_response(res).await
     }
 
     /// GET /api/v2/tenants/{tenant}/databases/{database} - Retrieves a specific database by name.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self), fields(endpoint, status, payload_bytes, latency_ms), err(level = "debug")))]
     pub async fn get_database(&self, tenant: &str, database: &str) -> Result<Database, KhromaError> {
+        if let Some(api) = &self.backend {
+            return api.get_database(tenant, database).await;
+        }
         let path = format!("/api/v2/tenants/{}/databases/{}", tenant, database);
         let req = self.build_request(reqwest::Method::GET, &path)?;
-        let res = req.send().await?;
+        let res = self.send(req).await?;
         self.handle_response(res).await
     }
 
     /// DELETE /api/v2/tenants/{tenant}/databases/{database} - Deletes a specific database.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self), fields(endpoint, status, payload_bytes, latency_ms), err(level = "debug")))]
     pub async fn delete_database(&self, tenant: &str, database: &str) -> Result<DeleteDatabaseResponse, KhromaError> {
+        if let Some(api) = &self.backend {
+            return api.delete_database(tenant, database).await;
+        }
         let path = format!("/api/v2/tenants/{}/databases/{}", tenant, database);
         let req = self.build_request(reqwest::Method::DELETE, &path)?;
-        let res = req.send().await?;
+        let res = self.send(req).await?;
         self.handle_response(res).await
     }
 
     /// GET /api/v2/tenants/{tenant}/databases/{database}/collections - Lists all collections in the specified database.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self), fields(endpoint, status, payload_bytes, latency_ms), err(level = "debug")))]
     pub async fn list_collections(&self, tenant: &str, database: &str, limit: Option<i32>, offset: Option<i32>) -> Result<Vec<Collection>, KhromaError> {
+        if let Some(api) = &self.backend {
+            return api.list_collections(tenant, database, limit, offset).await;
+        }
         let path = format!("/api/v2/tenants/{}/databases/{}/collections", tenant, database);
         let mut req = self.build_request(reqwest::Method::GET, &path)?;
         let mut query_params = Vec::new();
@@ -177,117 +827,294 @@ impl KhromaClient {
         if !query_params.is_empty() {
             req = req.query(&query_params);
         }
-        let res = req.send().await?;
+        let res = self.send(req).await?;
         self.handle_response(res).await
     }
 
     /// POST /api/v2/tenants/{tenant}/databases/{database}/collections - Creates a new collection.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, payload), fields(endpoint, status, payload_bytes, latency_ms), err(level = "debug")))]
     pub async fn create_collection(&self, tenant: &str, database: &str, payload: &CreateCollectionPayload) -> Result<Collection, KhromaError> {
+        if let Some(api) = &self.backend {
+            return api.create_collection(tenant, database, payload).await;
+        }
+        if let Some(configuration) = &payload.configuration {
+            configuration.index()?;
+        }
         let path = format!("/api/v2/tenants/{}/databases/{}/collections", tenant, database);
-        let req = self.build_request(reqwest::Method::POST, &path)?.json(payload);
-        let res = req.send().await?;
+        let req = self.with_json(self.build_request(reqwest::Method::POST, &path)?, payload)?;
+        let res = self.send(req).await?;
         self.handle_response(res).await
     }
 
     /// GET /api/v2/tenants/{tenant}/databases/{database}/collections/{collection_id} - Retrieves a collection.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self), fields(endpoint, status, payload_bytes, latency_ms), err(level = "debug")))]
     pub async fn get_collection(&self, tenant: &str, database: &str, collection_id: &str) -> Result<Collection, KhromaError> {
+        if let Some(api) = &self.backend {
+            return api.get_collection(tenant, database, collection_id).await;
+        }
         let path = format!("/api/v2/tenants/{}/databases/{}/collections/{}", tenant, database, collection_id);
         let req = self.build_request(reqwest::Method::GET, &path)?;
-        let res = req.send().await?;
+        let res = self.send(req).await?;
         self.handle_response(res).await
     }
 
     /// PUT /api/v2/tenants/{tenant}/databases/{database}/collections/{collection_id} - Updates a collection.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, payload), fields(endpoint, status, payload_bytes, latency_ms), err(level = "debug")))]
     pub async fn update_collection(&self, tenant: &str, database: &str, collection_id: &str, payload: &UpdateCollectionPayload) -> Result<UpdateCollectionResponse, KhromaError> {
-        let path = format!("/api/v2/tenants/{}/databases/{}/collections/{}", tenant, database, collection_id);
-        let req = self.build_request(reqwest::Method::PUT, &path)?.json(payload);
-        let res = req.send().await?;
-        self.handle_response(res).await
+        let response = match &self.backend {
+            Some(api) => api.update_collection(tenant, database, collection_id, payload).await?,
+            None => {
+                let path = format!("/api/v2/tenants/{}/databases/{}/collections/{}", tenant, database, collection_id);
+                let req = self.with_json(self.build_request(reqwest::Method::PUT, &path)?, payload)?;
+                let res = self.send(req).await?;
+                self.handle_response(res).await?
+            }
+        };
+        if payload.new_name.is_some() {
+            self.names.invalidate(tenant, database, collection_id);
+        }
+        Ok(response)
     }
 
     /// DELETE /api/v2/tenants/{tenant}/databases/{database}/collections/{collection_id} - Deletes a collection.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self), fields(endpoint, status, payload_bytes, latency_ms), err(level = "debug")))]
     pub async fn delete_collection(&self, tenant: &str, database: &str, collection_id: &str) -> Result<UpdateCollectionResponse, KhromaError> {
-        let path = format!("/api/v2/tenants/{}/databases/{}/collections/{}", tenant, database, collection_id);
-        let req = self.build_request(reqwest::Method::DELETE, &path)?;
-        let res = req.send().await?;
-        self.handle_response(res).await
+        let response = match &self.backend {
+            Some(api) => api.delete_collection(tenant, database, collection_id).await?,
+            None => {
+                let path = format!("/api/v2/tenants/{}/databases/{}/collections/{}", tenant, database, collection_id);
+                let req = self.build_request(reqwest::Method::DELETE, &path)?;
+                let res = self.send(req).await?;
+                self.handle_response(res).await?
+            }
+        };
+        self.names.invalidate(tenant, database, collection_id);
+        Ok(response)
     }
 
     /// POST /api/v2/tenants/{tenant}/databases/{database}/collections/{collection_id}/add - Adds records to a collection.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, payload), fields(endpoint, status, payload_bytes, latency_ms), err(level = "debug")))]
     pub async fn collection_add(&self, tenant: &str, database: &str, collection_id: &str, payload: &AddCollectionRecordsPayload) -> Result<AddCollectionRecordsResponse, KhromaError> {
+        if let Some(api) = &self.backend {
+            return api.collection_add(tenant, database, collection_id, payload).await;
+        }
+        let path = format!("/api/v2/tenants/{}/databases/{}/collections/{}/add", tenant, database, collection_id);
+        let req = self.build_request(reqwest::Method::POST, &path)?;
+        let req = match &payload.embeddings {
+            Some(EmbeddingsPayload::Float(_)) if self.sends_base64().await? => self.with_json(req, &RecordsRef::from(payload).to_base64())?,
+            _ => self.with_json(req, payload)?,
+        };
+        let req = self.with_idempotency_key(req);
+        let res = if self.idempotency_keys { self.send_idempotent(req).await? } else { self.send(req).await? };
+        self.handle_response(res).await
+    }
+
+    /// POST /api/v2/tenants/{tenant}/databases/{database}/collections/{collection_id}/add - Adds borrowed records to a collection without copying them.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, payload), fields(endpoint, status, payload_bytes, latency_ms), err(level = "debug")))]
+    pub async fn collection_add_ref(&self, tenant: &str, database: &str, collection_id: &str, payload: &RecordsRef<'_>) -> Result<AddCollectionRecordsResponse, KhromaError> {
+        if let Some(api) = &self.backend {
+            return api.collection_add(tenant, database, collection_id, &payload.to_add_payload()).await;
+        }
         let path = format!("/api/v2/tenants/{}/databases/{}/collections/{}/add", tenant, database, collection_id);
-        let req = self.build_request(reqwest::Method::POST, &path)?.json(payload);
-        let res = req.send().await?;
+        let encoded;
+        let payload = if payload.has_embeddings() && self.sends_base64().await? {
+            encoded = payload.to_base64();
+            &encoded
+        } else {
+            payload
+        };
+        let req = self.with_json(self.build_request(reqwest::Method::POST, &path)?, payload)?;
+        let req = self.with_idempotency_key(req);
+        let res = if self.idempotency_keys { self.send_idempotent(req).await? } else { self.send(req).await? };
         self.handle_response(res).await
     }
 
     /// GET /api/v2/tenants/{tenant}/databases/{database}/collections/{collection_id}/count - Retrieves the number of records in a collection.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self), fields(endpoint, status, payload_bytes, latency_ms), err(level = "debug")))]
     pub async fn collection_count(&self, tenant: &str, database: &str, collection_id: &str) -> Result<u32, KhromaError> {
+        if let Some(api) = &self.backend {
+            return api.collection_count(tenant, database, collection_id).await;
+        }
         let path = format!("/api/v2/tenants/{}/databases/{}/collections/{}/count", tenant, database, collection_id);
         let req = self.build_request(reqwest::Method::GET, &path)?;
-        let res = req.send().await?;
+        let res = self.send(req).await?;
         self.handle_response(res).await
     }
 
     /// POST /api/v2/tenants/{tenant}/databases/{database}/collections/{collection_id}/delete - Deletes records in a collection.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, payload), fields(endpoint, status, payload_bytes, latency_ms), err(level = "debug")))]
     pub async fn collection_delete(&self, tenant: &str, database: &str, collection_id: &str, payload: &DeleteCollectionRecordsPayload) -> Result<DeleteCollectionRecordsResponse, KhromaError> {
+        if let Some(api) = &self.backend {
+            return api.collection_delete(tenant, database, collection_id, payload).await;
+        }
         let path = format!("/api/v2/tenants/{}/databases/{}/collections/{}/delete", tenant, database, collection_id);
-        let req = self.build_request(reqwest::Method::POST, &path)?.json(payload);
-        let res = req.send().await?;
+        let req = self.with_json(self.build_request(reqwest::Method::POST, &path)?, payload)?;
+        let req = self.with_idempotency_key(req);
+        let res = self.send_idempotent(req).await?;
         self.handle_response(res).await
     }
 
     /// POST /api/v2/tenants/{tenant}/databases/{database}/collections/{collection_id}/fork - Forks an existing collection.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, payload), fields(endpoint, status, payload_bytes, latency_ms), err(level = "debug")))]
     pub async fn fork_collection(&self, tenant: &str, database: &str, collection_id: &str, payload: &ForkCollectionPayload) -> Result<Collection, KhromaError> {
+        if let Some(api) = &self.backend {
+            return api.fork_collection(tenant, database, collection_id, payload).await;
+        }
         let path = format!("/api/v2/tenants/{}/databases/{}/collections/{}/fork", tenant, database, collection_id);
-        let req = self.build_request(reqwest::Method::POST, &path)?.json(payload);
-        let res = req.send().await?;
+        let req = self.with_json(self.build_request(reqwest::Method::POST, &path)?, payload)?;
+        let res = self.send(req).await?;
         self.handle_response(res).await
     }
 
     /// POST /api/v2/tenants/{tenant}/databases/{database}/collections/{collection_id}/get - Retrieves records from a collection.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, payload), fields(endpoint, status, payload_bytes, latency_ms), err(level = "debug")))]
     pub async fn collection_get(&self, tenant: &str, database: &str, collection_id: &str, payload: &GetRequestPayload) -> Result<GetResponse, KhromaError> {
+        if let Some(api) = &self.backend {
+            return api.collection_get(tenant, database, collection_id, payload).await;
+        }
+        self.send_collection_get(tenant, database, collection_id, payload).await
+    }
+
+    /// Same as `collection_get`, but reads embeddings into a single `EmbeddingArena`.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, payload), fields(endpoint, status, payload_bytes, latency_ms), err(level = "debug")))]
+    pub async fn collection_get_arena(&self, tenant: &str, database: &str, collection_id: &str, payload: &GetRequestPayload) -> Result<ArenaGetResponse, KhromaError> {
+        if let Some(api) = &self.backend {
+            return api.collection_get_arena(tenant, database, collection_id, payload).await;
+        }
+        self.send_collection_get(tenant, database, collection_id, payload).await
+    }
+
+    async fn send_collection_get<R: serde::de::DeserializeOwned + serde::Serialize>(&self, tenant: &str, database: &str, collection_id: &str, payload: &GetRequestPayload) -> Result<R, KhromaError> {
         let path = format!("/api/v2/tenants/{}/databases/{}/collections/{}/get", tenant, database, collection_id);
-        let req = self.build_request(reqwest::Method::POST, &path)?.json(payload);
-        let res = req.send().await?;
+        let req = self.with_json(self.build_request(reqwest::Method::POST, &path)?, payload)?;
+        let res = self.send_idempotent(req).await?;
         self.handle_response(res).await
     }
 
     /// POST /api/v2/tenants/{tenant}/databases/{database}/collections/{collection_id}/query - Query a collection.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, payload), fields(endpoint, status, payload_bytes, latency_ms), err(level = "debug")))]
     pub async fn collection_query(&self, tenant: &str, database: &str, collection_id: &str, limit: Option<i32>, offset: Option<i32>, payload: &QueryRequestPayload) -> Result<QueryResponse, KhromaError> {
+        if let Some(api) = &self.backend {
+            return api.collection_query(tenant, database, collection_id, limit, offset, payload).await;
+        }
+        let Some(flights) = &self.query_flights else {
+            return self.send_collection_query(tenant, database, collection_id, limit, offset, payload).await;
+        };
+        let key = format!("{}/{}/{}?limit={:?}&offset={:?}#{}", tenant, database, collection_id, limit, offset, payload.digest()?);
+        // The shared call outlives this borrow, so it runs on an owned client without coalescing.
+        let mut this = self.clone();
+        this.query_flights = None;
+        let (tenant, database, collection_id, payload) = (tenant.to_string(), database.to_string(), collection_id.to_string(), payload.clone());
+        flights
+            .run(key, move || async move {
+                this.send_collection_query(&tenant, &database, &collection_id, limit, offset, &payload).await
+            })
+            .await
+    }
+
+    /// Same as `collection_query`, but reads each query's embeddings into an `EmbeddingArena`. Never coalesced.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, payload), fields(endpoint, status, payload_bytes, latency_ms), err(level = "debug")))]
+    pub async fn collection_query_arena(&self, tenant: &str, database: &str, collection_id: &str, limit: Option<i32>, offset: Option<i32>, payload: &QueryRequestPayload) -> Result<ArenaQueryResponse, KhromaError> {
+        if let Some(api) = &self.backend {
+            return api.collection_query_arena(tenant, database, collection_id, limit, offset, payload).await;
+        }
+        self.send_collection_query(tenant, database, collection_id, limit, offset, payload).await
+    }
+
+    async fn send_collection_query<R: serde::de::DeserializeOwned + serde::Serialize>(&self, tenant: &str, database: &str, collection_id: &str, limit: Option<i32>, offset: Option<i32>, payload: &QueryRequestPayload) -> Result<R, KhromaError> {
         let path = format!("/api/v2/tenants/{}/databases/{}/collections/{}/query", tenant, database, collection_id);
-        let mut req = self.build_request(reqwest::Method::POST, &path)?.json(payload);
+        let mut req = self.with_json(self.build_request(reqwest::Method::POST, &path)?, payload)?;
         let mut query_params = Vec::new();
         if let Some(l) = limit { query_params.push(("limit", l.to_string())); }
         if let Some(o) = offset { query_params.push(("offset", o.to_string())); }
         if !query_params.is_empty() {
             req = req.query(&query_params);
         }
-        let res = req.send().await?;
+        let res = self.send_idempotent(req).await?;
         self.handle_response(res).await
     }
 
     /// POST /api/v2/tenants/{tenant}/databases/{database}/collections/{collection_id}/update - Updates records in a collection.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, payload), fields(endpoint, status, payload_bytes, latency_ms), err(level = "debug")))]
     pub async fn collection_update(&self, tenant: &str, database: &str, collection_id: &str, payload: &UpdateCollectionRecordsPayload) -> Result<UpdateCollectionRecordsResponse, KhromaError> {
+        if let Some(api) = &self.backend {
+            return api.collection_update(tenant, database, collection_id, payload).await;
+        }
         let path = format!("/api/v2/tenants/{}/databases/{}/collections/{}/update", tenant, database, collection_id);
-        let req = self.build_request(reqwest::Method::POST, &path)?.json(payload);
-        let res = req.send().await?;
+        let req = self.build_request(reqwest::Method::POST, &path)?;
+        let req = match &payload.embeddings {
+            Some(UpdateEmbeddingsPayload::Float(_)) if self.sends_base64().await? => self.with_json(req, &payload.to_base64())?,
+            _ => self.with_json(req, payload)?,
+        };
+        let res = self.send_idempotent(req).await?;
         self.handle_response(res).await
     }
 
     /// POST /api/v2/tenants/{tenant}/databases/{database}/collections/{collection_id}/upsert - Upserts records in a collection.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, payload), fields(endpoint, status, payload_bytes, latency_ms), err(level = "debug")))]
     pub async fn collection_upsert(&self, tenant: &str, database: &str, collection_id: &str, payload: &UpsertCollectionRecordsPayload) -> Result<UpsertCollectionRecordsResponse, KhromaError> {
+        if let Some(api) = &self.backend {
+            return api.collection_upsert(tenant, database, collection_id, payload).await;
+        }
+        let path = format!("/api/v2/tenants/{}/databases/{}/collections/{}/upsert", tenant, database, collection_id);
+        let req = self.build_request(reqwest::Method::POST, &path)?;
+        let req = match &payload.embeddings {
+            Some(EmbeddingsPayload::Float(_)) if self.sends_base64().await? => self.with_json(req, &RecordsRef::from(payload).to_base64())?,
+            _ => self.with_json(req, payload)?,
+        };
+        let req = self.with_idempotency_key(req);
+        let res = self.send_idempotent(req).await?;
+        self.handle_response(res).await
+    }
+
+    /// POST /api/v2/tenants/{tenant}/databases/{database}/collections/{collection_id}/upsert - Upserts borrowed records in a collection without copying them.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, payload), fields(endpoint, status, payload_bytes, latency_ms), err(level = "debug")))]
+    pub async fn collection_upsert_ref(&self, tenant: &str, database: &str, collection_id: &str, payload: &RecordsRef<'_>) -> Result<UpsertCollectionRecordsResponse, KhromaError> {
+        if let Some(api) = &self.backend {
+            return api.collection_upsert(tenant, database, collection_id, &payload.to_upsert_payload()).await;
+        }
         let path = format!("/api/v2/tenants/{}/databases/{}/collections/{}/upsert", tenant, database, collection_id);
-        let req = self.build_request(reqwest::Method::POST, &path)?.json(payload);
-        let res = req.send().await?;
+        let encoded;
+        let payload = if payload.has_embeddings() && self.sends_base64().await? {
+            encoded = payload.to_base64();
+            &encoded
+        } else {
+            payload
+        };
+        let req = self.with_json(self.build_request(reqwest::Method::POST, &path)?, payload)?;
+        let req = self.with_idempotency_key(req);
+        let res = self.send_idempotent(req).await?;
         self.handle_response(res).await
     }
 
     /// GET /api/v2/tenants/{tenant}/databases/{database}/collections_count - Retrieves the total number of collections.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self), fields(endpoint, status, payload_bytes, latency_ms), err(level = "debug")))]
     pub async fn count_collections(&self, tenant: &str, database: &str) -> Result<u32, KhromaError> {
+        if let Some(api) = &self.backend {
+            return api.count_collections(tenant, database).await;
+        }
         let path = format!("/api/v2/tenants/{}/databases/{}/collections_count", tenant, database);
         let req = self.build_request(reqwest::Method::GET, &path)?;
-        let res = req.send().await?;
+        let res = self.send(req).await?;
         self.handle_response(res).await
     }
+
+    /// Resolves a collection name to its UUID, using the cache when possible.
+    ///
+    /// Entries are dropped when the collection is renamed or deleted through this client.
+    /// With the `tracing` feature, the span records whether the cache answered in `cache_hit`;
+    /// a miss is looked up in a nested `get_collection` span.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self), fields(cache_hit), err(level = "debug")))]
+    pub async fn resolve_collection_id(&self, tenant: &str, database: &str, name: &str) -> Result<CollectionUuid, KhromaError> {
+        let cached = self.names.get(tenant, database, name);
+        #[cfg(feature = "tracing")]
+        tracing::Span::current().record("cache_hit", cached.is_some());
+        if let Some(id) = cached {
+            return Ok(id);
+        }
+        // The collection GET endpoint resolves its path segment by name as well.
+        let collection = self.get_collection(tenant, database, name).await?;
+        self.names.insert(&collection);
+        Ok(collection.id)
+    }
 }
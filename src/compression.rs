@@ -0,0 +1,159 @@
+//! Compression of request bodies and snapshot files.
+
+use crate::error::KhromaError;
+use std::io::{self, BufRead, Write};
+
+/// Bodies smaller than this are sent uncompressed, as compressing them saves too little.
+#[cfg(any(feature = "gzip", feature = "zstd"))]
+pub(crate) const MIN_COMPRESSED_BYTES: usize = 1024;
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// How [`KhromaClientBuilder::request_compression`](crate::KhromaClientBuilder::request_compression)
+/// compresses request bodies, and [`Collection::export`](crate::Collection::export)
+/// snapshot files.
+///
+/// Without the `gzip` and `zstd` features there are no variants, and only uncompressed
+/// snapshots can be written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Compression {
+    /// With the `gzip` feature.
+    #[cfg(feature = "gzip")]
+    Gzip,
+    /// With the `zstd` feature. Compresses faster and smaller than gzip, but fewer proxies
+    /// accept it.
+    #[cfg(feature = "zstd")]
+    Zstd,
+}
+
+#[cfg(any(feature = "gzip", feature = "zstd"))]
+impl Compression {
+    /// The `Content-Encoding` header value.
+    pub(crate) fn encoding(self) -> &'static str {
+        match self {
+            #[cfg(feature = "gzip")]
+            Compression::Gzip => "gzip",
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => "zstd",
+        }
+    }
+
+    pub(crate) fn compress(self, body: &[u8]) -> Result<Vec<u8>, KhromaError> {
+        // Sized for incompressible input so the buffer never grows, which would leave
+        // copies of the body in freed memory when request bodies are zeroized.
+        let mut out = Vec::with_capacity(body.len() + body.len() / 128 + 64);
+        match self {
+            #[cfg(feature = "gzip")]
+            Compression::Gzip => {
+                let mut encoder =
+                    flate2::write::GzEncoder::new(&mut out, flate2::Compression::fast());
+                encoder.write_all(body)?;
+                encoder.finish()?;
+            }
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => {
+                let mut encoder = zstd::Encoder::new(&mut out, 3)?;
+                encoder.write_all(body)?;
+                encoder.finish()?;
+            }
+        }
+        Ok(out)
+    }
+}
+
+/// A snapshot file being written, compressed or not.
+pub(crate) enum Encoder<W: Write> {
+    Plain(W),
+    #[cfg(feature = "gzip")]
+    Gzip(flate2::write::GzEncoder<W>),
+    #[cfg(feature = "zstd")]
+    Zstd(zstd::Encoder<'static, W>),
+}
+
+impl<W: Write> Encoder<W> {
+    pub(crate) fn new(writer: W, compression: Option<Compression>) -> io::Result<Self> {
+        Ok(match compression {
+            None => Encoder::Plain(writer),
+            #[cfg(feature = "gzip")]
+            Some(Compression::Gzip) => Encoder::Gzip(flate2::write::GzEncoder::new(
+                writer,
+                flate2::Compression::default(),
+            )),
+            #[cfg(feature = "zstd")]
+            Some(Compression::Zstd) => Encoder::Zstd(zstd::Encoder::new(writer, 3)?),
+        })
+    }
+
+    /// Writes the end of the compressed stream and flushes the writer.
+    #[cfg_attr(
+        not(any(feature = "gzip", feature = "zstd")),
+        allow(clippy::infallible_destructuring_match)
+    )]
+    pub(crate) fn finish(self) -> io::Result<W> {
+        let mut writer = match self {
+            Encoder::Plain(writer) => writer,
+            #[cfg(feature = "gzip")]
+            Encoder::Gzip(encoder) => encoder.finish()?,
+            #[cfg(feature = "zstd")]
+            Encoder::Zstd(encoder) => encoder.finish()?,
+        };
+        writer.flush()?;
+        Ok(writer)
+    }
+}
+
+impl<W: Write> Write for Encoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Encoder::Plain(writer) => writer.write(buf),
+            #[cfg(feature = "gzip")]
+            Encoder::Gzip(encoder) => encoder.write(buf),
+            #[cfg(feature = "zstd")]
+            Encoder::Zstd(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Encoder::Plain(writer) => writer.flush(),
+            #[cfg(feature = "gzip")]
+            Encoder::Gzip(encoder) => encoder.flush(),
+            #[cfg(feature = "zstd")]
+            Encoder::Zstd(encoder) => encoder.flush(),
+        }
+    }
+}
+
+/// Reads a snapshot file, decompressing it if it starts with the gzip or zstd magic bytes.
+pub(crate) fn decoder(
+    mut reader: impl BufRead + Send + 'static,
+) -> Result<Box<dyn BufRead + Send>, KhromaError> {
+    let head = reader.fill_buf()?;
+    if head.starts_with(&GZIP_MAGIC) {
+        #[cfg(feature = "gzip")]
+        return Ok(Box::new(io::BufReader::new(
+            flate2::bufread::MultiGzDecoder::new(reader),
+        )));
+        #[cfg(not(feature = "gzip"))]
+        return Err(missing_codec("gzip"));
+    }
+    if head.starts_with(&ZSTD_MAGIC) {
+        #[cfg(feature = "zstd")]
+        return Ok(Box::new(io::BufReader::new(zstd::Decoder::with_buffer(
+            reader,
+        )?)));
+        #[cfg(not(feature = "zstd"))]
+        return Err(missing_codec("zstd"));
+    }
+    Ok(Box::new(reader))
+}
+
+#[cfg(not(all(feature = "gzip", feature = "zstd")))]
+fn missing_codec(codec: &str) -> KhromaError {
+    KhromaError::Validation(format!(
+        "The file is {}-compressed; enable the `{}` feature to read it",
+        codec, codec
+    ))
+}
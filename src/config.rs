@@ -0,0 +1,121 @@
+use crate::client::KhromaClient;
+use crate::error::KhromaError;
+use crate::retry::RetryPolicy;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+const DEFAULT_ENDPOINT: &str = "http://localhost:8000";
+
+/// Deployment settings for a `Khroma` client, loadable from TOML or YAML.
+///
+/// Values read from a file can be overridden by the `KHROMA_URL`, `KHROMA_TOKEN`,
+/// `KHROMA_TENANT`, `KHROMA_DATABASE`, `KHROMA_TIMEOUT_SECS`,
+/// `KHROMA_CONNECT_TIMEOUT_SECS` and `KHROMA_RETRY_ATTEMPTS` environment variables.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct KhromaConfig {
+    pub endpoint: String,
+    pub token: Option<String>,
+    pub tenant: Option<String>,
+    pub database: Option<String>,
+    pub timeout_secs: Option<u64>,
+    pub connect_timeout_secs: Option<u64>,
+    /// Enables the default `RetryPolicy` with this many attempts per request.
+    pub retry_attempts: Option<u32>,
+}
+
+impl Default for KhromaConfig {
+    fn default() -> Self {
+        Self {
+            endpoint: DEFAULT_ENDPOINT.to_string(),
+            token: None,
+            tenant: None,
+            database: None,
+            timeout_secs: None,
+            connect_timeout_secs: None,
+            retry_attempts: None,
+        }
+    }
+}
+
+impl KhromaConfig {
+    /// Reads a `.toml`, `.yaml` or `.yml` file and applies environment overrides.
+    #[cfg(feature = "config")]
+    pub fn from_file(path: impl AsRef<std::path::Path>) -> Result<Self, KhromaError> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)?;
+        let config: Self = match path.extension().and_then(|e| e.to_str()) {
+            Some("toml") => toml::from_str(&text).map_err(|e| KhromaError::Config(e.to_string()))?,
+            Some("yaml" | "yml") => {
+                serde_yaml::from_str(&text).map_err(|e| KhromaError::Config(e.to_string()))?
+            }
+            _ => {
+                return Err(KhromaError::Config(format!(
+                    "Unsupported config format: {}",
+                    path.display()
+                )));
+            }
+        };
+        config.with_env_overrides()
+    }
+
+    /// Builds a configuration from defaults and environment variables only.
+    pub fn from_env() -> Result<Self, KhromaError> {
+        Self::default().with_env_overrides()
+    }
+
+    fn with_env_overrides(mut self) -> Result<Self, KhromaError> {
+        if let Ok(endpoint) = std::env::var("KHROMA_URL") {
+            self.endpoint = endpoint;
+        }
+        if let Ok(token) = std::env::var("KHROMA_TOKEN") {
+            self.token = Some(token);
+        }
+        if let Ok(tenant) = std::env::var("KHROMA_TENANT") {
+            self.tenant = Some(tenant);
+        }
+        if let Ok(database) = std::env::var("KHROMA_DATABASE") {
+            self.database = Some(database);
+        }
+        if let Some(secs) = env_parse("KHROMA_TIMEOUT_SECS")? {
+            self.timeout_secs = Some(secs);
+        }
+        if let Some(secs) = env_parse("KHROMA_CONNECT_TIMEOUT_SECS")? {
+            self.connect_timeout_secs = Some(secs);
+        }
+        if let Some(attempts) = env_parse("KHROMA_RETRY_ATTEMPTS")? {
+            self.retry_attempts = Some(attempts);
+        }
+        Ok(self)
+    }
+
+    pub(crate) fn build_client(&self) -> Result<KhromaClient, KhromaError> {
+        let mut builder = KhromaClient::builder(&self.endpoint);
+        if let Some(token) = &self.token {
+            builder = builder.token(token);
+        }
+        if let Some(secs) = self.timeout_secs {
+            builder = builder.timeout(Duration::from_secs(secs));
+        }
+        if let Some(secs) = self.connect_timeout_secs {
+            builder = builder.connect_timeout(Duration::from_secs(secs));
+        }
+        if let Some(attempts) = self.retry_attempts {
+            builder = builder.retry_policy(RetryPolicy::default().with_max_attempts(attempts));
+        }
+        builder.build()
+    }
+}
+
+fn env_parse<T: std::str::FromStr>(name: &str) -> Result<Option<T>, KhromaError>
+where
+    T::Err: std::fmt::Display,
+{
+    match std::env::var(name) {
+        Ok(value) => value
+            .parse()
+            .map(Some)
+            .map_err(|e| KhromaError::Config(format!("{}: {}", name, e))),
+        Err(_) => Ok(None),
+    }
+}
@@ -0,0 +1,164 @@
+//! Keeping reads available, in a reduced form, while the server is unreachable.
+
+use crate::error::KhromaError;
+use crate::models::QueryResponse;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+/// The result of [`Collection::query_resilient`](crate::Collection::query_resilient).
+#[derive(Debug)]
+pub enum QueryOutcome {
+    /// The server answered.
+    Fresh(QueryResponse),
+    /// The server failed and the last answer to the same query was served from the cache.
+    Cached(QueryResponse),
+    /// The server failed and nothing was cached; the application should carry on without
+    /// retrieval results.
+    Degraded(KhromaError),
+}
+
+impl QueryOutcome {
+    /// The response, fresh or cached.
+    pub fn response(&self) -> Option<&QueryResponse> {
+        match self {
+            QueryOutcome::Fresh(response) | QueryOutcome::Cached(response) => Some(response),
+            QueryOutcome::Degraded(_) => None,
+        }
+    }
+
+    pub fn is_fresh(&self) -> bool {
+        matches!(self, QueryOutcome::Fresh(_))
+    }
+}
+
+/// The result of [`Collection::query_within`](crate::Collection::query_within).
+#[derive(Debug)]
+pub enum BudgetedQuery {
+    /// Every query embedding was answered within the budget.
+    Complete(QueryResponse),
+    /// The budget ran out. `partial` holds the answers that arrived in time, with empty
+    /// result lists for the query embeddings still outstanding, or `None` if none arrived.
+    TimedOut { partial: Option<QueryResponse> },
+}
+
+impl BudgetedQuery {
+    /// The complete or partial response, if any answer arrived in time.
+    pub fn response(&self) -> Option<&QueryResponse> {
+        match self {
+            BudgetedQuery::Complete(response) => Some(response),
+            BudgetedQuery::TimedOut { partial } => partial.as_ref(),
+        }
+    }
+
+    pub fn is_complete(&self) -> bool {
+        matches!(self, BudgetedQuery::Complete(_))
+    }
+}
+
+/// How queries behave during a server outage: connection failures, timeouts, 429 and 5xx
+/// responses. Other errors, such as an invalid filter, are always returned as errors.
+///
+/// Clones share the same response cache.
+#[derive(Debug, Clone)]
+pub struct DegradationPolicy {
+    cache: Option<Arc<Mutex<ResponseCache>>>,
+    degraded_results: bool,
+}
+
+impl Default for DegradationPolicy {
+    /// Caches the last 256 responses and returns `Degraded` when no cached response exists.
+    fn default() -> Self {
+        Self::new().with_cache(256).with_degraded_results(true)
+    }
+}
+
+impl DegradationPolicy {
+    /// A policy that neither caches nor degrades: outages are returned as errors.
+    pub fn new() -> Self {
+        Self {
+            cache: None,
+            degraded_results: false,
+        }
+    }
+
+    /// Keeps the last successful response to up to `capacity` distinct queries, to serve
+    /// while the server is failing. A capacity of zero disables the cache.
+    pub fn with_cache(mut self, capacity: usize) -> Self {
+        self.cache = (capacity > 0).then(|| Arc::new(Mutex::new(ResponseCache::new(capacity))));
+        self
+    }
+
+    /// Returns [`QueryOutcome::Degraded`] instead of an error when no cached response exists.
+    pub fn with_degraded_results(mut self, enabled: bool) -> Self {
+        self.degraded_results = enabled;
+        self
+    }
+
+    pub(crate) fn resolve(
+        &self,
+        key: String,
+        result: Result<QueryResponse, KhromaError>,
+    ) -> Result<QueryOutcome, KhromaError> {
+        match result {
+            Ok(response) => {
+                if let Some(cache) = &self.cache {
+                    cache.lock().unwrap().insert(key, response.clone());
+                }
+                Ok(QueryOutcome::Fresh(response))
+            }
+            Err(error) if is_outage(&error) => {
+                let cached = self
+                    .cache
+                    .as_ref()
+                    .and_then(|cache| cache.lock().unwrap().get(&key));
+                match cached {
+                    Some(response) => Ok(QueryOutcome::Cached(response)),
+                    None if self.degraded_results => Ok(QueryOutcome::Degraded(error)),
+                    None => Err(error),
+                }
+            }
+            Err(error) => Err(error),
+        }
+    }
+}
+
+fn is_outage(error: &KhromaError) -> bool {
+    error.is_retryable()
+        || error
+            .status()
+            .is_some_and(|status| status.is_server_error())
+}
+
+/// Responses by query, evicting the oldest entry once full.
+#[derive(Debug)]
+struct ResponseCache {
+    capacity: usize,
+    entries: HashMap<String, QueryResponse>,
+    order: VecDeque<String>,
+}
+
+impl ResponseCache {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    fn get(&self, key: &str) -> Option<QueryResponse> {
+        self.entries.get(key).cloned()
+    }
+
+    fn insert(&mut self, key: String, response: QueryResponse) {
+        if self.entries.insert(key.clone(), response).is_some() {
+            return;
+        }
+        self.order.push_back(key);
+        if self.order.len() > self.capacity
+            && let Some(oldest) = self.order.pop_front()
+        {
+            self.entries.remove(&oldest);
+        }
+    }
+}
@@ -0,0 +1,130 @@
+//! Whole documents stored as chunks in a collection, searched and deleted as a unit.
+
+use crate::chunking::Chunker;
+use crate::error::KhromaError;
+use crate::high_level::Collection;
+use crate::models::{
+    DeleteCollectionRecordsPayload, Include, Metadata, QueryHit, QueryRequestPayload,
+    RawWhereFields,
+};
+use serde_json::json;
+
+/// The metadata key holding the id of the document a chunk belongs to.
+const SOURCE_KEY: &str = "source";
+
+/// How many chunks [`DocumentStore::search`] fetches per document it returns, since a
+/// document's chunks often rank next to each other.
+pub const SEARCH_OVERFETCH: u32 = 4;
+
+/// Keeps documents in a collection as chunks, each chunk a record with the document id
+/// in its `source` metadata.
+///
+/// The collection needs an embedding function. Chunk metadata is written by the
+/// [`Chunker`]: `source`, `offset`, `index` and `heading` are reserved and override
+/// document metadata under the same keys.
+#[derive(Debug, Clone)]
+pub struct DocumentStore {
+    collection: Collection,
+    chunker: Chunker,
+}
+
+/// A document found by [`DocumentStore::search`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct DocumentMatch {
+    pub id: String,
+    /// The distance of the closest chunk.
+    pub distance: Option<f32>,
+    /// The matching chunks of the document, closest first.
+    pub chunks: Vec<QueryHit>,
+}
+
+impl DocumentStore {
+    pub fn new(collection: Collection, chunker: Chunker) -> Self {
+        Self {
+            collection,
+            chunker,
+        }
+    }
+
+    pub fn collection(&self) -> &Collection {
+        &self.collection
+    }
+
+    /// Chunks, embeds and stores `text` under `id`, with `metadata` on every chunk, and
+    /// returns the number of chunks. Replaces the chunks of an earlier document with the
+    /// same id.
+    pub async fn add_document(
+        &self,
+        id: &str,
+        text: &str,
+        metadata: Metadata,
+    ) -> Result<usize, KhromaError> {
+        let mut records = self.chunker.records(id, text);
+        for record in &mut records {
+            let chunk_metadata = record.metadata.take().unwrap_or_default();
+            let mut merged = metadata.clone();
+            merged.extend(chunk_metadata);
+            record.metadata = Some(merged);
+        }
+        let count = records.len();
+        self.delete_document(id).await?;
+        if count > 0 {
+            self.collection.upsert_records(records).await?;
+        }
+        Ok(count)
+    }
+
+    /// Deletes every chunk of the document `id`.
+    pub async fn delete_document(&self, id: &str) -> Result<(), KhromaError> {
+        let filter = RawWhereFields::new(Some(json!({ SOURCE_KEY: id })), None);
+        self.collection
+            .delete(&DeleteCollectionRecordsPayload::matching(filter))
+            .await
+    }
+
+    /// The documents with chunks closest to `text`, up to `n_documents`, closest first.
+    pub async fn search(
+        &self,
+        text: &str,
+        n_documents: u32,
+    ) -> Result<Vec<DocumentMatch>, KhromaError> {
+        let mut embeddings = self
+            .collection
+            .embedder()?
+            .embed(&[text.to_string()])
+            .await?;
+        let payload = QueryRequestPayload::builder(vec![embeddings.remove(0)])?
+            .n_results(n_documents.saturating_mul(SEARCH_OVERFETCH))
+            .include(&[Include::Documents, Include::Metadatas, Include::Distances])
+            .build();
+        let hits = self
+            .collection
+            .query(&payload, None, None)
+            .await?
+            .into_results()?
+            .into_iter()
+            .next()
+            .unwrap_or_default();
+
+        let mut matches: Vec<DocumentMatch> = Vec::new();
+        for hit in hits {
+            let source = hit
+                .metadata
+                .as_ref()
+                .and_then(|metadata| metadata.get(SOURCE_KEY))
+                .and_then(|source| source.as_str())
+                .unwrap_or(&hit.id)
+                .to_string();
+            match matches.iter_mut().find(|m| m.id == source) {
+                Some(found) => found.chunks.push(hit),
+                None => matches.push(DocumentMatch {
+                    id: source,
+                    distance: hit.distance,
+                    chunks: vec![hit],
+                }),
+            }
+        }
+        matches.truncate(n_documents as usize);
+        Ok(matches)
+    }
+}
@@ -0,0 +1,113 @@
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+/// Responses checked per response type. Later ones are parsed as usual.
+const SAMPLES_PER_MODEL: u32 = 16;
+/// Bodies above this size, typically gets and queries carrying embeddings, aren't checked.
+const MAX_CHECKED_BYTES: u64 = 1 << 20;
+
+/// Compares raw response bodies against what khroma's models capture and logs the
+/// differences, once per response type and field.
+///
+/// A checked response is parsed into a JSON tree, then into its model, then serialized
+/// back for comparison, so only a sample of small responses is checked.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct DriftDetector {
+    reported: Arc<Mutex<HashSet<(&'static str, String)>>>,
+    sampled: Arc<Mutex<HashMap<&'static str, u32>>>,
+}
+
+impl DriftDetector {
+    /// Whether to check a response of type `T` whose body is `content_length` bytes, if
+    /// known. Counts the response towards the type's samples.
+    pub(crate) fn wants<T>(&self, content_length: Option<u64>) -> bool {
+        if content_length.is_some_and(|length| length > MAX_CHECKED_BYTES) {
+            return false;
+        }
+        let mut sampled = self.sampled.lock().unwrap();
+        let count = sampled.entry(std::any::type_name::<T>()).or_default();
+        if *count >= SAMPLES_PER_MODEL {
+            return false;
+        }
+        *count += 1;
+        true
+    }
+
+    /// Logs fields present in `raw` but not in `modeled` (unknown to khroma), and fields
+    /// khroma models that the server did not send (missing).
+    pub(crate) fn check<T>(&self, endpoint: &str, raw: &Value, modeled: &Value) {
+        let model = std::any::type_name::<T>();
+        let mut diffs = Vec::new();
+        diff("", raw, modeled, &mut diffs);
+        let mut reported = self.reported.lock().unwrap();
+        for (field, kind) in diffs {
+            if reported.insert((model, field.clone())) {
+                log::warn!(
+                    "Schema drift on {}: {} field `{}` in {}",
+                    endpoint,
+                    kind,
+                    field,
+                    model
+                );
+            }
+        }
+    }
+}
+
+/// The fields of `raw` that `modeled` lacks. Null fields are ignored, since models drop
+/// unset fields when serializing.
+pub(crate) fn unknown_fields(raw: &Value, modeled: &Value) -> Vec<String> {
+    let mut raw = raw.clone();
+    strip_nulls(&mut raw);
+    let mut diffs = Vec::new();
+    diff("", &raw, modeled, &mut diffs);
+    diffs
+        .into_iter()
+        .filter(|(_, kind)| *kind == "unknown")
+        .map(|(field, _)| field)
+        .collect()
+}
+
+fn strip_nulls(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            map.retain(|_, value| !value.is_null());
+            map.values_mut().for_each(strip_nulls);
+        }
+        Value::Array(items) => items.iter_mut().for_each(strip_nulls),
+        _ => {}
+    }
+}
+
+fn diff(path: &str, raw: &Value, modeled: &Value, out: &mut Vec<(String, &'static str)>) {
+    match (raw, modeled) {
+        (Value::Object(raw), Value::Object(modeled)) => {
+            for (key, raw_value) in raw {
+                let field = join(path, key);
+                match modeled.get(key) {
+                    Some(modeled_value) => diff(&field, raw_value, modeled_value, out),
+                    None => out.push((field, "unknown")),
+                }
+            }
+            for key in modeled.keys().filter(|key| !raw.contains_key(*key)) {
+                out.push((join(path, key), "missing"));
+            }
+        }
+        (Value::Array(raw), Value::Array(modeled)) => {
+            let field = format!("{}[]", path);
+            for (raw_value, modeled_value) in raw.iter().zip(modeled) {
+                diff(&field, raw_value, modeled_value, out);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn join(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
@@ -0,0 +1,132 @@
+#[cfg(feature = "onnx")]
+mod onnx;
+#[cfg(feature = "openai")]
+mod openai;
+
+#[cfg(feature = "onnx")]
+pub use onnx::OnnxEmbedder;
+#[cfg(feature = "openai")]
+pub use openai::OpenAiEmbedder;
+
+use crate::error::KhromaError;
+use async_trait::async_trait;
+use std::fmt;
+use std::sync::{Arc, OnceLock};
+
+/// Computes embeddings on the client side, so records and queries can be given as text.
+#[async_trait]
+pub trait EmbeddingFunction: Send + Sync {
+    /// Returns one embedding per text, in the same order.
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, KhromaError>;
+}
+
+#[async_trait]
+impl<E: EmbeddingFunction + ?Sized> EmbeddingFunction for Arc<E> {
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, KhromaError> {
+        (**self).embed(texts).await
+    }
+}
+
+/// An embedding function attached to a collection handle.
+#[derive(Clone)]
+pub(crate) struct SharedEmbedder(Arc<dyn EmbeddingFunction>);
+
+impl SharedEmbedder {
+    pub(crate) fn new(embedder: impl EmbeddingFunction + 'static) -> Self {
+        Self(Arc::new(embedder))
+    }
+
+    /// Embeds `texts`, checking that the function returned one embedding for each.
+    pub(crate) async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, KhromaError> {
+        let embeddings = self.0.embed(texts).await?;
+        if embeddings.len() != texts.len() {
+            return Err(KhromaError::Validation(format!(
+                "Embedding function returned {} embeddings for {} texts",
+                embeddings.len(),
+                texts.len()
+            )));
+        }
+        Ok(embeddings)
+    }
+}
+
+impl fmt::Debug for SharedEmbedder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SharedEmbedder(..)")
+    }
+}
+
+/// Tries a chain of embedding functions in order, falling through to the next when one
+/// fails.
+///
+/// Every embedding returned must have the same dimension: the one given to
+/// [`with_dimension`](Self::with_dimension), or else that of the first embeddings
+/// computed. A function whose embeddings don't match counts as failed. The check can't
+/// tell apart two models of the same dimension, so only chain functions computing the
+/// same embeddings, such as one model served by two providers.
+#[derive(Debug)]
+pub struct FailoverEmbedder {
+    embedders: Vec<SharedEmbedder>,
+    dimension: OnceLock<usize>,
+}
+
+impl FailoverEmbedder {
+    pub fn new(primary: impl EmbeddingFunction + 'static) -> Self {
+        Self {
+            embedders: vec![SharedEmbedder::new(primary)],
+            dimension: OnceLock::new(),
+        }
+    }
+
+    /// Falls back to `embedder` when every function before it has failed.
+    pub fn or_else(mut self, embedder: impl EmbeddingFunction + 'static) -> Self {
+        self.embedders.push(SharedEmbedder::new(embedder));
+        self
+    }
+
+    /// Requires embeddings of `dimension`, instead of learning it from the first ones.
+    pub fn with_dimension(self, dimension: usize) -> Self {
+        Self {
+            dimension: OnceLock::from(dimension),
+            ..self
+        }
+    }
+
+    fn check_dimension(&self, embeddings: Vec<Vec<f32>>) -> Result<Vec<Vec<f32>>, KhromaError> {
+        let Some(first) = embeddings.first() else {
+            return Ok(embeddings);
+        };
+        let found = first.len();
+        if embeddings.iter().any(|embedding| embedding.len() != found) {
+            return Err(KhromaError::Validation(
+                "Embedding function returned embeddings of different dimensions".to_string(),
+            ));
+        }
+        let expected = *self.dimension.get_or_init(|| found);
+        if found != expected {
+            return Err(KhromaError::Validation(format!(
+                "Embedding function returned embeddings of dimension {}, expected {}",
+                found, expected
+            )));
+        }
+        Ok(embeddings)
+    }
+}
+
+#[async_trait]
+impl EmbeddingFunction for FailoverEmbedder {
+    /// Returns the first embeddings computed, or the last function's error if all fail.
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, KhromaError> {
+        let mut last_error = None;
+        for embedder in &self.embedders {
+            match embedder.embed(texts).await {
+                Ok(embeddings) => match self.check_dimension(embeddings) {
+                    Ok(embeddings) => return Ok(embeddings),
+                    Err(error) => last_error = Some(error),
+                },
+                Err(error) => last_error = Some(error),
+            }
+        }
+        Err(last_error.expect("a failover chain has at least one embedding function"))
+    }
+}
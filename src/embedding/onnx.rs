@@ -0,0 +1,177 @@
+use super::EmbeddingFunction;
+use crate::error::KhromaError;
+use async_trait::async_trait;
+use ort::session::Session;
+use ort::value::Tensor;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokenizers::{PaddingParams, Tokenizer, TruncationParams};
+
+/// Tokens per text; longer texts are truncated, as in Chroma's default embedding function.
+const MAX_LENGTH: usize = 256;
+/// Texts per inference run.
+const BATCH_SIZE: usize = 32;
+
+/// Runs a sentence-transformer ONNX model locally.
+///
+/// With the `all-MiniLM-L6-v2` model this reproduces Chroma's default embedding function
+/// (mean pooling over the attention mask, then L2 normalization), so its embeddings are
+/// compatible with those computed by the Python client.
+///
+/// The ONNX Runtime shared library is loaded at runtime: install it and point
+/// `ORT_DYLIB_PATH` at it if it is not on the library search path.
+#[derive(Clone)]
+pub struct OnnxEmbedder {
+    session: Arc<Mutex<Session>>,
+    tokenizer: Arc<Tokenizer>,
+    uses_token_type_ids: bool,
+}
+
+impl std::fmt::Debug for OnnxEmbedder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OnnxEmbedder").finish_non_exhaustive()
+    }
+}
+
+impl OnnxEmbedder {
+    /// Loads `model.onnx` and `tokenizer.json` from `dir`.
+    pub fn from_dir(dir: impl AsRef<Path>) -> Result<Self, KhromaError> {
+        let dir = dir.as_ref();
+        Self::from_files(dir.join("model.onnx"), dir.join("tokenizer.json"))
+    }
+
+    /// Loads the model Chroma's Python client downloads for its default embedding function,
+    /// from `~/.cache/chroma/onnx_models/all-MiniLM-L6-v2/onnx`.
+    pub fn chroma_default() -> Result<Self, KhromaError> {
+        let home = std::env::var_os("HOME")
+            .ok_or_else(|| KhromaError::Config("HOME is not set".to_string()))?;
+        let dir: PathBuf = [
+            home.as_os_str(),
+            ".cache/chroma/onnx_models/all-MiniLM-L6-v2/onnx".as_ref(),
+        ]
+        .iter()
+        .collect();
+        Self::from_dir(dir)
+    }
+
+    pub fn from_files(
+        model: impl AsRef<Path>,
+        tokenizer: impl AsRef<Path>,
+    ) -> Result<Self, KhromaError> {
+        let session = Session::builder()
+            .and_then(|builder| builder.commit_from_file(model))
+            .map_err(|e| KhromaError::Config(format!("Cannot load ONNX model: {}", e)))?;
+        let mut tokenizer = Tokenizer::from_file(tokenizer)
+            .map_err(|e| KhromaError::Config(format!("Cannot load tokenizer: {}", e)))?;
+        tokenizer
+            .with_truncation(Some(TruncationParams {
+                max_length: MAX_LENGTH,
+                ..Default::default()
+            }))
+            .map_err(|e| KhromaError::Config(e.to_string()))?;
+        tokenizer.with_padding(Some(PaddingParams::default()));
+        let uses_token_type_ids = session
+            .inputs
+            .iter()
+            .any(|input| input.name == "token_type_ids");
+        Ok(Self {
+            session: Arc::new(Mutex::new(session)),
+            tokenizer: Arc::new(tokenizer),
+            uses_token_type_ids,
+        })
+    }
+
+    fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, KhromaError> {
+        let encodings = self
+            .tokenizer
+            .encode_batch(texts.to_vec(), true)
+            .map_err(|e| KhromaError::Embedding(e.to_string()))?;
+        let batch = encodings.len();
+        let tokens = encodings.first().map_or(0, |e| e.get_ids().len());
+        let column = |get: fn(&tokenizers::Encoding) -> &[u32]| -> Vec<i64> {
+            encodings
+                .iter()
+                .flat_map(|e| get(e).iter().map(|&v| i64::from(v)))
+                .collect()
+        };
+        let ids = column(tokenizers::Encoding::get_ids);
+        let mask = column(tokenizers::Encoding::get_attention_mask);
+        let shape = [batch, tokens];
+
+        let mut inputs = vec![
+            ("input_ids", tensor(shape, ids)?),
+            ("attention_mask", tensor(shape, mask.clone())?),
+        ];
+        if self.uses_token_type_ids {
+            inputs.push((
+                "token_type_ids",
+                tensor(shape, column(tokenizers::Encoding::get_type_ids))?,
+            ));
+        }
+
+        let mut session = self.session.lock().unwrap();
+        let outputs = session.run(inputs).map_err(embedding_error)?;
+        let (hidden_shape, hidden) = outputs[0]
+            .try_extract_tensor::<f32>()
+            .map_err(embedding_error)?;
+        let dim = match **hidden_shape {
+            [b, t, d] if b as usize == batch && t as usize == tokens => d as usize,
+            _ => {
+                return Err(KhromaError::Embedding(format!(
+                    "Unexpected model output shape {:?}",
+                    hidden_shape
+                )));
+            }
+        };
+
+        Ok((0..batch)
+            .map(|b| {
+                let mut pooled = vec![0f32; dim];
+                let mut count = 0f32;
+                for t in 0..tokens {
+                    if mask[b * tokens + t] == 0 {
+                        continue;
+                    }
+                    count += 1.0;
+                    let start = (b * tokens + t) * dim;
+                    for (sum, v) in pooled.iter_mut().zip(&hidden[start..start + dim]) {
+                        *sum += v;
+                    }
+                }
+                let count = count.max(1e-9);
+                pooled.iter_mut().for_each(|v| *v /= count);
+                let norm = pooled.iter().map(|v| v * v).sum::<f32>().sqrt().max(1e-12);
+                pooled.iter_mut().for_each(|v| *v /= norm);
+                pooled
+            })
+            .collect())
+    }
+}
+
+fn tensor(shape: [usize; 2], data: Vec<i64>) -> Result<ort::value::DynValue, KhromaError> {
+    Tensor::from_array((shape, data))
+        .map(|t| t.into_dyn())
+        .map_err(embedding_error)
+}
+
+fn embedding_error(e: ort::Error) -> KhromaError {
+    KhromaError::Embedding(e.to_string())
+}
+
+#[async_trait]
+impl EmbeddingFunction for OnnxEmbedder {
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, KhromaError> {
+        let this = self.clone();
+        let texts = texts.to_vec();
+        // Inference is CPU-bound; keep it off the async worker threads.
+        tokio::task::spawn_blocking(move || {
+            let mut embeddings = Vec::with_capacity(texts.len());
+            for batch in texts.chunks(BATCH_SIZE) {
+                embeddings.extend(this.embed_batch(batch)?);
+            }
+            Ok(embeddings)
+        })
+        .await
+        .map_err(|e| KhromaError::Embedding(e.to_string()))?
+    }
+}
@@ -0,0 +1,150 @@
+use super::EmbeddingFunction;
+use crate::error::KhromaError;
+use crate::retry::{self, RetryPolicy};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+const DEFAULT_BASE_URL: &str = "https://api.openai.com/v1/";
+/// The most inputs the embeddings endpoint accepts in one request.
+const MAX_BATCH_SIZE: usize = 2048;
+
+/// Embeds texts with the OpenAI embeddings API.
+///
+/// Texts are sent in batches of up to 2048, one batch at a time. Rate limits and server
+/// errors are retried with the default [`RetryPolicy`].
+#[derive(Debug, Clone)]
+pub struct OpenAiEmbedder {
+    client: reqwest::Client,
+    base_url: Url,
+    api_key: String,
+    model: String,
+    dimensions: Option<u32>,
+    batch_size: usize,
+    retry: RetryPolicy,
+}
+
+#[derive(Serialize)]
+struct EmbeddingRequest<'a> {
+    model: &'a str,
+    input: &'a [String],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dimensions: Option<u32>,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingResponse {
+    data: Vec<EmbeddingData>,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingData {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+#[derive(Deserialize)]
+struct ErrorResponse {
+    error: ErrorBody,
+}
+
+#[derive(Deserialize)]
+struct ErrorBody {
+    message: String,
+    #[serde(default)]
+    r#type: Option<String>,
+}
+
+impl OpenAiEmbedder {
+    /// Uses `model`, e.g. `text-embedding-3-small`.
+    pub fn new(api_key: impl Into<String>, model: impl Into<String>) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            base_url: Url::parse(DEFAULT_BASE_URL).expect("default base URL is valid"),
+            api_key: api_key.into(),
+            model: model.into(),
+            dimensions: None,
+            batch_size: MAX_BATCH_SIZE,
+            retry: RetryPolicy::default(),
+        }
+    }
+
+    /// Reads the API key from `OPENAI_API_KEY`.
+    pub fn from_env(model: impl Into<String>) -> Result<Self, KhromaError> {
+        let api_key = std::env::var("OPENAI_API_KEY")
+            .map_err(|_| KhromaError::Config("OPENAI_API_KEY is not set".to_string()))?;
+        Ok(Self::new(api_key, model))
+    }
+
+    /// Targets an OpenAI-compatible server instead of `https://api.openai.com/v1/`.
+    pub fn with_base_url(mut self, base_url: &str) -> Result<Self, KhromaError> {
+        let mut base_url = Url::parse(base_url)?;
+        if !base_url.path().ends_with('/') {
+            base_url.set_path(&format!("{}/", base_url.path()));
+        }
+        self.base_url = base_url;
+        Ok(self)
+    }
+
+    /// Requests shortened embeddings, for models that support it.
+    pub fn with_dimensions(mut self, dimensions: u32) -> Self {
+        self.dimensions = Some(dimensions);
+        self
+    }
+
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.clamp(1, MAX_BATCH_SIZE);
+        self
+    }
+
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry = policy;
+        self
+    }
+
+    pub fn with_http_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, KhromaError> {
+        let request = self
+            .client
+            .post(self.base_url.join("embeddings")?)
+            .bearer_auth(&self.api_key)
+            .json(&EmbeddingRequest {
+                model: &self.model,
+                input: texts,
+                dimensions: self.dimensions,
+            })
+            .build()?;
+        let res = self.retry.execute(&self.client, request).await?;
+        let status = res.status();
+        if !status.is_success() {
+            let retry_after = retry::retry_after(&res);
+            let (kind, message) = match res.json::<ErrorResponse>().await {
+                Ok(err_res) => (err_res.error.r#type, err_res.error.message),
+                Err(_) => (None, format!("Failed to parse error response. Status: {}", status)),
+            };
+            return Err(KhromaError::from_status(status, kind.as_deref(), message, retry_after));
+        }
+        let mut data = res
+            .json::<EmbeddingResponse>()
+            .await
+            .map_err(|e| KhromaError::Parse(format!("Failed to deserialize embeddings: {}", e)))?
+            .data;
+        data.sort_by_key(|d| d.index);
+        Ok(data.into_iter().map(|d| d.embedding).collect())
+    }
+}
+
+#[async_trait]
+impl EmbeddingFunction for OpenAiEmbedder {
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, KhromaError> {
+        let mut embeddings = Vec::with_capacity(texts.len());
+        for batch in texts.chunks(self.batch_size) {
+            embeddings.extend(self.embed_batch(batch).await?);
+        }
+        Ok(embeddings)
+    }
+}
@@ -1,4 +1,6 @@
 use reqwest::StatusCode;
+use std::sync::Arc;
+use std::time::Duration;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -15,6 +17,218 @@ pub enum KhromaError {
         message: String,
     },
 
+    /// The server rejected the credentials (401) or denied access to the resource (403).
+    #[error("Authentication failed (status: {status}): {message}")]
+    Unauthorized {
+        status: StatusCode,
+        message: String,
+    },
+
+    #[error("Not found: {0}")]
+    NotFound(String),
+
+    /// Too many requests; `retry_after` is the wait the server asked for, if any.
+    #[error("Rate limited: {message}")]
+    RateLimited {
+        message: String,
+        retry_after: Option<Duration>,
+    },
+
+    /// A tenant quota, such as the number of collections or records, would be exceeded.
+    /// Retrying does not help until the quota is raised.
+    #[error("Quota exceeded (status: {status}): {message}")]
+    QuotaExceeded {
+        status: StatusCode,
+        message: String,
+    },
+
     #[error("Failed to parse response: {0}")]
     Parse(String),
+
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Configuration error: {0}")]
+    Config(String),
+
+    #[error("Invalid input: {0}")]
+    Validation(String),
+
+    /// A client-side embedding function failed.
+    #[error("Embedding error: {0}")]
+    Embedding(String),
+
+    #[error("Reset refused: {0}")]
+    ResetNotAllowed(String),
+
+    /// A conditional update found the collection changed since the handle last read it.
+    #[error("Conflicting update: {0}")]
+    Conflict(String),
+
+    /// The failure of a coalesced request, shared by every caller waiting on it.
+    #[error("{0}")]
+    Shared(Arc<KhromaError>),
+}
+
+impl From<std::convert::Infallible> for KhromaError {
+    fn from(never: std::convert::Infallible) -> Self {
+        match never {}
+    }
+}
+
+impl KhromaError {
+    /// Classifies an error response. `kind` is the server's error name, e.g. `"QuotaError"`.
+    ///
+    /// Quota errors are recognized by a 402 status or by `kind`, never by the message, whose
+    /// wording is free text and may mention quotas for other reasons.
+    pub(crate) fn from_status(
+        status: StatusCode,
+        kind: Option<&str>,
+        message: String,
+        retry_after: Option<Duration>,
+    ) -> Self {
+        let is_quota = status == StatusCode::PAYMENT_REQUIRED
+            || kind.is_some_and(|kind| kind.to_ascii_lowercase().contains("quota"));
+        match status {
+            _ if is_quota => KhromaError::QuotaExceeded { status, message },
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+                KhromaError::Unauthorized { status, message }
+            }
+            StatusCode::NOT_FOUND => KhromaError::NotFound(message),
+            StatusCode::TOO_MANY_REQUESTS => KhromaError::RateLimited {
+                message,
+                retry_after,
+            },
+            _ => KhromaError::Api { status, message },
+        }
+    }
+
+    /// The HTTP status associated with the error, if the server responded.
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            KhromaError::Api { status, .. }
+            | KhromaError::Unauthorized { status, .. }
+            | KhromaError::QuotaExceeded { status, .. } => Some(*status),
+            KhromaError::NotFound(_) => Some(StatusCode::NOT_FOUND),
+            KhromaError::RateLimited { .. } => Some(StatusCode::TOO_MANY_REQUESTS),
+            KhromaError::Reqwest(e) => e.status(),
+            KhromaError::Shared(e) => e.status(),
+            _ => None,
+        }
+    }
+
+    pub fn is_not_found(&self) -> bool {
+        self.status() == Some(StatusCode::NOT_FOUND)
+    }
+
+    /// Whether the server reported a conflict (409), or a conditional update found the
+    /// collection changed.
+    pub fn is_conflict(&self) -> bool {
+        match self {
+            KhromaError::Conflict(_) => true,
+            KhromaError::Shared(e) => e.is_conflict(),
+            _ => self.status() == Some(StatusCode::CONFLICT),
+        }
+    }
+
+    /// Whether the request timed out, either client-side or at a gateway.
+    pub fn is_timeout(&self) -> bool {
+        match self {
+            KhromaError::Reqwest(e) if e.is_timeout() => true,
+            KhromaError::Shared(e) => e.is_timeout(),
+            _ => matches!(
+                self.status(),
+                Some(StatusCode::REQUEST_TIMEOUT | StatusCode::GATEWAY_TIMEOUT)
+            ),
+        }
+    }
+
+    /// How long the server asked to wait before retrying, if it said.
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            KhromaError::RateLimited { retry_after, .. } => *retry_after,
+            KhromaError::Shared(e) => e.retry_after(),
+            _ => None,
+        }
+    }
+
+    /// Whether the failure is transient, so the same request may succeed if retried.
+    pub fn is_retryable(&self) -> bool {
+        if self.is_timeout() {
+            return true;
+        }
+        match self {
+            KhromaError::QuotaExceeded { .. } => false,
+            KhromaError::Reqwest(e) if e.is_connect() => true,
+            KhromaError::Shared(e) => e.is_retryable(),
+            _ => matches!(
+                self.status(),
+                Some(
+                    StatusCode::TOO_MANY_REQUESTS
+                        | StatusCode::BAD_GATEWAY
+                        | StatusCode::SERVICE_UNAVAILABLE
+                )
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn from_status(status: u16, kind: Option<&str>) -> KhromaError {
+        let status = StatusCode::from_u16(status).unwrap();
+        KhromaError::from_status(status, kind, "message".to_string(), None)
+    }
+
+    #[test]
+    fn transient_statuses_are_retryable() {
+        for status in [408, 429, 502, 503, 504] {
+            assert!(from_status(status, None).is_retryable(), "{}", status);
+        }
+    }
+
+    #[test]
+    fn client_errors_are_not_retryable() {
+        for status in [400, 401, 403, 404, 409, 422, 500] {
+            assert!(!from_status(status, None).is_retryable(), "{}", status);
+        }
+        let validation = KhromaError::Validation("bad".to_string());
+        assert!(!validation.is_retryable());
+    }
+
+    #[test]
+    fn quota_errors_are_not_retryable() {
+        let quota = from_status(429, Some("QuotaExceededError"));
+        assert!(matches!(quota, KhromaError::QuotaExceeded { .. }));
+        assert!(!quota.is_retryable());
+        assert!(!from_status(402, None).is_retryable());
+    }
+
+    #[test]
+    fn quota_is_not_read_from_the_message() {
+        let status = StatusCode::TOO_MANY_REQUESTS;
+        let message = "quota of requests per second exceeded".to_string();
+        let error = KhromaError::from_status(status, None, message, None);
+        assert!(matches!(error, KhromaError::RateLimited { .. }));
+        assert!(error.is_retryable());
+    }
+
+    #[test]
+    fn shared_errors_classify_like_their_source() {
+        let retry_after = Some(Duration::from_secs(2));
+        let rate_limited = KhromaError::from_status(
+            StatusCode::TOO_MANY_REQUESTS,
+            None,
+            "slow down".to_string(),
+            retry_after,
+        );
+        let shared = KhromaError::Shared(Arc::new(rate_limited));
+        assert!(shared.is_retryable());
+        assert_eq!(shared.retry_after(), retry_after);
+        let shared = KhromaError::Shared(Arc::new(from_status(404, None)));
+        assert!(!shared.is_retryable());
+        assert!(shared.is_not_found());
+    }
 }
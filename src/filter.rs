@@ -0,0 +1,240 @@
+//! Client-side evaluation of Chroma `where` filters.
+
+use crate::error::KhromaError;
+use crate::models::Metadata;
+use serde_json::Value;
+
+/// Whether `metadata` satisfies the `where` filter `filter`, using Chroma's semantics:
+/// `{"key": value}`, `{"key": {"$op": operand}}`, `$and` and `$or`.
+pub(crate) fn matches_where(filter: &Value, metadata: &Metadata) -> Result<bool, KhromaError> {
+    let clauses = filter
+        .as_object()
+        .ok_or_else(|| invalid(format!("Expected a where filter object, got {}", filter)))?;
+    for (key, condition) in clauses {
+        let matched = match key.as_str() {
+            "$and" => all_of(condition)?
+                .iter()
+                .map(|clause| matches_where(clause, metadata))
+                .collect::<Result<Vec<_>, _>>()?
+                .into_iter()
+                .all(|m| m),
+            "$or" => all_of(condition)?
+                .iter()
+                .map(|clause| matches_where(clause, metadata))
+                .collect::<Result<Vec<_>, _>>()?
+                .into_iter()
+                .any(|m| m),
+            _ => matches_condition(metadata.get(key), condition)?,
+        };
+        if !matched {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+/// Whether `document` satisfies the `where_document` filter `filter`: `$contains`,
+/// `$not_contains`, `$regex`, `$not_regex`, `$and` and `$or`. A missing document matches
+/// nothing but `$not_contains` and `$not_regex`.
+#[cfg_attr(not(feature = "mock"), allow(dead_code))]
+pub(crate) fn matches_where_document(
+    filter: &Value,
+    document: Option<&str>,
+) -> Result<bool, KhromaError> {
+    let clauses = filter.as_object().ok_or_else(|| {
+        invalid(format!(
+            "Expected a where_document filter object, got {}",
+            filter
+        ))
+    })?;
+    for (operator, operand) in clauses {
+        let matched = match operator.as_str() {
+            "$and" => all_of(operand)?
+                .iter()
+                .map(|clause| matches_where_document(clause, document))
+                .collect::<Result<Vec<_>, _>>()?
+                .into_iter()
+                .all(|m| m),
+            "$or" => all_of(operand)?
+                .iter()
+                .map(|clause| matches_where_document(clause, document))
+                .collect::<Result<Vec<_>, _>>()?
+                .into_iter()
+                .any(|m| m),
+            "$contains" => {
+                let needle = text(operand)?;
+                document.is_some_and(|document| document.contains(needle))
+            }
+            "$not_contains" => {
+                let needle = text(operand)?;
+                !document.is_some_and(|document| document.contains(needle))
+            }
+            #[cfg(feature = "mock")]
+            "$regex" => {
+                let pattern = regex(operand)?;
+                document.is_some_and(|document| pattern.is_match(document))
+            }
+            #[cfg(feature = "mock")]
+            "$not_regex" => {
+                let pattern = regex(operand)?;
+                !document.is_some_and(|document| pattern.is_match(document))
+            }
+            _ => {
+                return Err(invalid(format!(
+                    "Unsupported where_document operator: {}",
+                    operator
+                )));
+            }
+        };
+        if !matched {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+fn text(operand: &Value) -> Result<&str, KhromaError> {
+    operand
+        .as_str()
+        .ok_or_else(|| invalid(format!("Expected a string, got {}", operand)))
+}
+
+/// Compiles a `$regex` operand. The server also uses the `regex` crate's syntax.
+#[cfg(feature = "mock")]
+fn regex(operand: &Value) -> Result<regex::Regex, KhromaError> {
+    let pattern = text(operand)?;
+    regex::Regex::new(pattern).map_err(|e| invalid(format!("Invalid regex {:?}: {}", pattern, e)))
+}
+
+fn all_of(condition: &Value) -> Result<&Vec<Value>, KhromaError> {
+    condition
+        .as_array()
+        .ok_or_else(|| invalid(format!("Expected a list of filters, got {}", condition)))
+}
+
+fn matches_condition(value: Option<&Value>, condition: &Value) -> Result<bool, KhromaError> {
+    let Some(operators) = condition.as_object() else {
+        return Ok(value == Some(condition));
+    };
+    for (operator, operand) in operators {
+        let matched = match operator.as_str() {
+            "$eq" => value == Some(operand),
+            "$ne" => value != Some(operand),
+            "$gt" => compare(value, operand).is_some_and(|o| o.is_gt()),
+            "$gte" => compare(value, operand).is_some_and(|o| o.is_ge()),
+            "$lt" => compare(value, operand).is_some_and(|o| o.is_lt()),
+            "$lte" => compare(value, operand).is_some_and(|o| o.is_le()),
+            "$in" => all_of(operand)?
+                .iter()
+                .any(|candidate| value == Some(candidate)),
+            "$nin" => !all_of(operand)?
+                .iter()
+                .any(|candidate| value == Some(candidate)),
+            _ => return Err(invalid(format!("Unsupported where operator: {}", operator))),
+        };
+        if !matched {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+fn compare(value: Option<&Value>, operand: &Value) -> Option<std::cmp::Ordering> {
+    value?.as_f64()?.partial_cmp(&operand.as_f64()?)
+}
+
+fn invalid(message: String) -> KhromaError {
+    KhromaError::Validation(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn metadata() -> Metadata {
+        let Value::Object(map) = json!({"genre": "jazz", "year": 1959, "rating": 4.5}) else {
+            unreachable!()
+        };
+        map.into_iter().collect()
+    }
+
+    fn check(filter: Value) -> bool {
+        matches_where(&filter, &metadata()).unwrap()
+    }
+
+    #[test]
+    fn in_matches_any_listed_value() {
+        assert!(check(json!({"genre": {"$in": ["rock", "jazz"]}})));
+        assert!(check(json!({"year": {"$in": [1959, 1960]}})));
+        assert!(!check(json!({"genre": {"$in": ["rock", "pop"]}})));
+        assert!(!check(json!({"genre": {"$in": []}})));
+        assert!(!check(json!({"missing": {"$in": ["jazz"]}})));
+    }
+
+    #[test]
+    fn nin_matches_values_outside_the_list() {
+        assert!(check(json!({"genre": {"$nin": ["rock", "pop"]}})));
+        assert!(!check(json!({"genre": {"$nin": ["jazz"]}})));
+        assert!(check(json!({"genre": {"$nin": []}})));
+        assert!(check(json!({"missing": {"$nin": ["jazz"]}})));
+    }
+
+    #[test]
+    fn and_requires_every_clause() {
+        assert!(check(
+            json!({"$and": [{"genre": "jazz"}, {"year": {"$lt": 1960}}]})
+        ));
+        assert!(!check(
+            json!({"$and": [{"genre": "jazz"}, {"year": {"$gt": 1960}}]})
+        ));
+        assert!(check(json!({"$and": []})));
+    }
+
+    #[test]
+    fn or_requires_any_clause() {
+        assert!(check(
+            json!({"$or": [{"genre": "rock"}, {"rating": {"$gte": 4.5}}]})
+        ));
+        assert!(!check(
+            json!({"$or": [{"genre": "rock"}, {"rating": {"$gt": 4.5}}]})
+        ));
+        assert!(!check(json!({"$or": []})));
+    }
+
+    #[test]
+    fn logical_operators_nest() {
+        assert!(check(json!({
+            "$and": [
+                {"$or": [{"genre": "rock"}, {"genre": {"$in": ["jazz", "blues"]}}]},
+                {"$or": [{"year": {"$nin": [1959]}}, {"rating": {"$lte": 5}}]},
+            ]
+        })));
+        assert!(!check(json!({
+            "$or": [
+                {"$and": [{"genre": "jazz"}, {"year": {"$ne": 1959}}]},
+                {"$and": [{"genre": {"$nin": ["jazz"]}}, {"year": 1959}]},
+            ]
+        })));
+    }
+
+    #[test]
+    fn malformed_operands_are_rejected() {
+        let metadata = metadata();
+        assert!(matches_where(&json!({"genre": {"$in": "jazz"}}), &metadata).is_err());
+        assert!(matches_where(&json!({"$and": {"genre": "jazz"}}), &metadata).is_err());
+        assert!(matches_where(&json!({"genre": {"$like": "j%"}}), &metadata).is_err());
+        assert!(matches_where(&json!(["genre"]), &metadata).is_err());
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn regex_matches_documents() {
+        let filter = json!({"$regex": "^bl(ue|ack)"});
+        assert!(matches_where_document(&filter, Some("blue train")).unwrap());
+        assert!(!matches_where_document(&filter, Some("kind of blue")).unwrap());
+        assert!(!matches_where_document(&filter, None).unwrap());
+        assert!(matches_where_document(&json!({"$not_regex": "^bl"}), None).unwrap());
+        assert!(matches_where_document(&json!({"$regex": "("}), Some("x")).is_err());
+    }
+}
@@ -0,0 +1,35 @@
+//! A process-wide `Khroma` client for applications that don't want to pass handles around.
+
+use crate::config::KhromaConfig;
+use crate::error::KhromaError;
+use crate::high_level::Khroma;
+use std::sync::OnceLock;
+
+static CLIENT: OnceLock<Khroma> = OnceLock::new();
+
+/// Builds the global client from `config`. Fails if it was already initialized.
+pub fn init(config: &KhromaConfig) -> Result<&'static Khroma, KhromaError> {
+    set(Khroma::from_config(config)?)
+}
+
+/// Installs an already constructed client as the global client.
+pub fn set(khroma: Khroma) -> Result<&'static Khroma, KhromaError> {
+    CLIENT
+        .set(khroma)
+        .map_err(|_| KhromaError::Config("Global client is already initialized".to_string()))?;
+    Ok(client())
+}
+
+/// Returns the global client.
+///
+/// # Panics
+///
+/// Panics if neither [`init`] nor [`set`] has been called.
+pub fn client() -> &'static Khroma {
+    try_client().expect("khroma::global::init must be called before khroma::global::client")
+}
+
+/// Returns the global client, or `None` if it hasn't been initialized.
+pub fn try_client() -> Option<&'static Khroma> {
+    CLIENT.get()
+}
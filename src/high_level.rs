@@ -1,28 +1,293 @@
+use crate::api::ChromaApi;
+use crate::auth::Auth;
+use crate::blob::{BlobStore, DataLoader, LoadedBlobs};
+use crate::canonical;
 use crate::client::KhromaClient;
+use crate::compression::{Compression, Encoder};
+use crate::config::KhromaConfig;
+use crate::degradation::{BudgetedQuery, DegradationPolicy, QueryOutcome};
+use crate::embedding::{EmbeddingFunction, SharedEmbedder};
 use crate::error::KhromaError;
+use crate::filter::matches_where;
+use crate::hybrid::{self, Fusion, HybridHit};
+use crate::ingest::{self, ImportFormat, ImportReport, ProgressEvent};
 use crate::models;
+use crate::observer::{ChangeKind, ChangeObserver, ChangeObservers};
+use crate::page::{Page, PageToken};
+use crate::pipeline::Pipeline;
+use crate::priority::Priority;
+use crate::quality::QualityReport;
+use crate::read_cache::{CollectionVersion, ReadCache};
+use crate::read_only::{ReadOnlyCollection, ReadOnlyDatabase};
+use crate::rerank::{self, RERANK_OVERFETCH, RerankedHit, Reranker};
+use crate::retry::RetryPolicy;
+use futures::stream::FuturesUnordered;
+use futures::{Stream, StreamExt, TryStreamExt};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::borrow::Cow;
+use std::io::{BufWriter, Read, Seek, Write};
+use std::collections::HashMap;
 use std::sync::Arc;
-use uuid::Uuid;
+use std::time::{Duration, Instant};
 
+/// A handle on one collection. Clones share the HTTP client, the server's description of
+/// the collection, the embedding function and the caches. The names, include lists and
+/// observer list are copied, and so is the filter from [`Collection::with_base_filter`],
+/// which makes cloning costly when that filter is large.
 #[derive(Debug, Clone)]
 pub struct Collection {
-    pub id: Uuid,
-    pub name: String,
+    pub id: models::CollectionId,
+    pub name: models::CollectionName,
     pub tenant_name: String,
     pub database_name: String,
+    default_includes: models::DefaultIncludes,
+    embedder: Option<SharedEmbedder>,
+    degradation: DegradationPolicy,
+    base_filter: Option<serde_json::Value>,
+    observers: ChangeObservers,
+    two_phase: Option<TwoPhaseFetch>,
+    read_cache: Option<ReadCache>,
+    model: Arc<models::Collection>,
     client: Arc<KhromaClient>,
 }
 
 impl Collection {
     pub(crate) fn from(value: models::Collection, client: Arc<KhromaClient>) -> Self {
         Self {
-            id: value.id,
-            name: value.name,
-            tenant_name: value.tenant,
-            database_name: value.database,
+            id: value.id.into(),
+            name: value.name.clone().into(),
+            tenant_name: value.tenant.clone(),
+            database_name: value.database.clone(),
+            default_includes: client.default_includes().clone(),
+            embedder: None,
+            degradation: DegradationPolicy::default(),
+            base_filter: None,
+            observers: ChangeObservers::default(),
+            two_phase: None,
+            read_cache: None,
+            model: Arc::new(value),
             client,
         }
     }
+
+    /// Overrides the client-wide default include lists for requests made through this handle.
+    pub fn with_default_includes(mut self, includes: models::DefaultIncludes) -> Self {
+        self.default_includes = includes;
+        self
+    }
+
+    /// Embeds documents on the client side from now on: records added or upserted with
+    /// documents but without embeddings get them from `embedder`, and `query_text` works.
+    pub fn with_embedding_function(mut self, embedder: impl EmbeddingFunction + 'static) -> Self {
+        self.embedder = Some(SharedEmbedder::new(embedder));
+        self
+    }
+
+    /// Restricts every get, query and delete made through this handle to records matching
+    /// the `where` filter `filter`, e.g. `{"tenant_id": 42}`. Request filters are AND-ed
+    /// with it. Counts, updates by id and adds are not affected.
+    pub fn with_base_filter(mut self, filter: serde_json::Value) -> Self {
+        self.base_filter = Some(filter);
+        self
+    }
+
+    /// Calls `observer` after every successful add, upsert, update or delete made through
+    /// this handle and its clones. Observers already attached keep being called.
+    pub fn with_change_observer(mut self, observer: impl ChangeObserver + 'static) -> Self {
+        self.observers.push(observer);
+        self
+    }
+
+    /// Splits queries that include embeddings and would return more than the policy's size
+    /// estimate into two requests; see [`TwoPhaseFetch`].
+    pub fn with_two_phase_fetch(mut self, policy: TwoPhaseFetch) -> Self {
+        self.two_phase = Some(policy);
+        self
+    }
+
+    /// Queues requests made through this handle in the `priority` class of the client's
+    /// [priority limits](crate::KhromaClientBuilder::priority_limits), e.g.
+    /// [`Priority::Background`] for a handle used for bulk ingestion.
+    pub fn with_priority(mut self, priority: Priority) -> Self {
+        let mut client = (*self.client).clone();
+        client.set_priority(priority);
+        self.client = Arc::new(client);
+        self
+    }
+
+    /// Gives each attempt of a request made through this handle `timeout` to complete,
+    /// instead of the client's [`timeout`](crate::KhromaClientBuilder::timeout). Handles
+    /// are cheap to clone, so one call can get its own deadline:
+    /// `collection.clone().with_timeout(Duration::from_secs(120)).add(&payload)`.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        let mut client = (*self.client).clone();
+        client.set_request_timeout(timeout);
+        self.client = Arc::new(client);
+        self
+    }
+
+    /// Serves get and query responses from `cache` while the collection's version and log
+    /// position are unchanged. Writes through this handle, or any handle sharing `cache`,
+    /// drop the collection's entries; see [`ReadCache`] for writes by other clients. Every
+    /// read, cached or not, costs one more request for the collection's description.
+    pub fn with_read_cache(mut self, cache: ReadCache) -> Self {
+        self.read_cache = Some(cache);
+        self
+    }
+
+    /// Sets how [`Collection::query_resilient`] behaves while the server is failing.
+    pub fn with_degradation_policy(mut self, policy: DegradationPolicy) -> Self {
+        self.degradation = policy;
+        self
+    }
+
+    pub fn read_only(&self) -> ReadOnlyCollection {
+        self.clone().into()
+    }
+
+    /// The collection as the server last described it, when this handle was obtained or
+    /// last reloaded.
+    pub fn model(&self) -> &models::Collection {
+        &self.model
+    }
+
+    pub fn metadata(&self) -> Option<&models::Metadata> {
+        self.model.metadata.as_ref()
+    }
+
+    pub fn configuration(&self) -> &models::CollectionConfiguration {
+        &self.model.configuration_json
+    }
+
+    /// The dimension of the collection's embeddings, once the first record has been added.
+    pub fn dimension(&self) -> Option<i32> {
+        self.model.dimension
+    }
+
+    pub fn version(&self) -> i32 {
+        self.model.version
+    }
+
+    /// How far the server has compacted the collection's write-ahead log, as last seen by
+    /// this handle. It advances when a compaction applies logged record writes, which may
+    /// be well after the writes were acknowledged, and not at all on servers that don't
+    /// compact, such as a local single-node server.
+    pub fn log_position(&self) -> i64 {
+        self.model.log_position
+    }
+
+    pub(crate) fn embedder(&self) -> Result<&SharedEmbedder, KhromaError> {
+        self.embedder.as_ref().ok_or_else(|| {
+            KhromaError::Validation(
+                "No embedding function is attached to this collection".to_string(),
+            )
+        })
+    }
+
+    /// Embeddings for `documents`, when an embedder is attached and `embeddings` are missing.
+    async fn embed_missing(
+        &self,
+        embeddings: Option<&models::EmbeddingsPayload>,
+        documents: Option<&[Option<String>]>,
+    ) -> Result<Option<models::EmbeddingsPayload>, KhromaError> {
+        let (None, Some(documents), Some(embedder)) = (embeddings, documents, &self.embedder)
+        else {
+            return Ok(None);
+        };
+        let texts = documents
+            .iter()
+            .map(|document| {
+                document.clone().ok_or_else(|| {
+                    KhromaError::Validation("Cannot embed a record without a document".to_string())
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let embeddings = embedder.embed(&texts).await?;
+        Ok(Some(models::EmbeddingsPayload::Float(embeddings)))
+    }
+
+    async fn embed_missing_ref(
+        &self,
+        payload: &models::RecordsRef<'_>,
+    ) -> Result<Option<Vec<Vec<f32>>>, KhromaError> {
+        if payload.has_embeddings() {
+            return Ok(None);
+        }
+        Ok(
+            match self.embed_missing(None, payload.documents_column()).await? {
+                Some(models::EmbeddingsPayload::Float(embeddings)) => Some(embeddings),
+                _ => None,
+            },
+        )
+    }
+
+    pub(crate) fn get_payload<'a>(
+        &self,
+        payload: &'a models::GetRequestPayload,
+    ) -> Cow<'a, models::GetRequestPayload> {
+        let include = match (&payload.include, &self.default_includes.get) {
+            (None, Some(include)) => Some(include.clone()),
+            _ => None,
+        };
+        let filter = self.scoped_where(&payload.where_fields);
+        if include.is_none() && filter.is_none() {
+            return Cow::Borrowed(payload);
+        }
+        let mut payload = payload.clone();
+        if include.is_some() {
+            payload.include = include;
+        }
+        if filter.is_some() {
+            payload.where_fields.r#where = filter;
+        }
+        Cow::Owned(payload)
+    }
+
+    pub(crate) fn query_payload<'a>(
+        &self,
+        payload: &'a models::QueryRequestPayload,
+    ) -> Cow<'a, models::QueryRequestPayload> {
+        let include = match (&payload.include, &self.default_includes.query) {
+            (None, Some(include)) => Some(include.clone()),
+            _ => None,
+        };
+        let filter = self.scoped_where(&payload.where_fields);
+        if include.is_none() && filter.is_none() {
+            return Cow::Borrowed(payload);
+        }
+        let mut payload = payload.clone();
+        if include.is_some() {
+            payload.include = include;
+        }
+        if filter.is_some() {
+            payload.where_fields.r#where = filter;
+        }
+        Cow::Owned(payload)
+    }
+
+    fn delete_payload<'a>(
+        &self,
+        payload: &'a models::DeleteCollectionRecordsPayload,
+    ) -> Cow<'a, models::DeleteCollectionRecordsPayload> {
+        match self.scoped_where(&payload.where_fields) {
+            Some(filter) => {
+                let mut payload = payload.clone();
+                payload.where_fields.r#where = Some(filter);
+                Cow::Owned(payload)
+            }
+            None => Cow::Borrowed(payload),
+        }
+    }
+
+    /// The request's `where` filter combined with the handle's base filter, if one is set.
+    fn scoped_where(&self, fields: &models::RawWhereFields) -> Option<serde_json::Value> {
+        let base = self.base_filter.as_ref()?;
+        Some(match &fields.r#where {
+            Some(filter) => serde_json::json!({ "$and": [base, filter] }),
+            None => base.clone(),
+        })
+    }
 }
 
 impl Collection {
@@ -30,38 +295,521 @@ impl Collection {
         &self,
         payload: &models::AddCollectionRecordsPayload,
     ) -> Result<(), KhromaError> {
+        let embeddings = self
+            .embed_missing(payload.embeddings.as_ref(), payload.documents.as_deref())
+            .await?;
+        let payload = match embeddings {
+            Some(embeddings) => {
+                let mut payload = payload.clone();
+                payload.embeddings = Some(embeddings);
+                Cow::Owned(payload)
+            }
+            None => Cow::Borrowed(payload),
+        };
         self.client
             .collection_add(
                 &self.tenant_name,
                 &self.database_name,
                 &self.id.to_string(),
-                payload,
+                &payload,
+            )
+            .await?;
+        self.record_write(ChangeKind::Add, &payload.ids, None);
+        Ok(())
+    }
+
+    /// Like [`Collection::add`], but sends borrowed columns without copying them.
+    pub async fn add_ref(&self, payload: &models::RecordsRef<'_>) -> Result<(), KhromaError> {
+        let embeddings = self.embed_missing_ref(payload).await?;
+        let payload = match &embeddings {
+            Some(embeddings) => payload.with_owned_embeddings(embeddings),
+            None => payload.clone(),
+        };
+        self.client
+            .collection_add_ref(
+                &self.tenant_name,
+                &self.database_name,
+                &self.id.to_string(),
+                &payload,
             )
             .await?;
+        self.record_write(ChangeKind::Add, payload.ids(), None);
         Ok(())
     }
 
+    pub async fn add_records(&self, records: Vec<models::Record>) -> Result<(), KhromaError> {
+        self.add(&models::AddCollectionRecordsPayload::from_records(records)?)
+            .await
+    }
+
+    /// Like [`Collection::upsert`], but sends borrowed columns without copying them.
+    pub async fn upsert_ref(&self, payload: &models::RecordsRef<'_>) -> Result<(), KhromaError> {
+        let embeddings = self.embed_missing_ref(payload).await?;
+        let payload = match &embeddings {
+            Some(embeddings) => payload.with_owned_embeddings(embeddings),
+            None => payload.clone(),
+        };
+        self.client
+            .collection_upsert_ref(
+                &self.tenant_name,
+                &self.database_name,
+                &self.id.to_string(),
+                &payload,
+            )
+            .await?;
+        self.record_write(ChangeKind::Upsert, payload.ids(), None);
+        Ok(())
+    }
+
+    pub async fn upsert_records(&self, records: Vec<models::Record>) -> Result<(), KhromaError> {
+        self.upsert(&models::UpsertCollectionRecordsPayload::from_records(records)?)
+            .await
+    }
+
+    /// Adds documents, embedding them with the attached embedding function.
+    pub async fn add_documents(&self, ids: &[&str], documents: &[&str]) -> Result<(), KhromaError> {
+        if ids.len() != documents.len() {
+            return Err(KhromaError::Validation(format!(
+                "Got {} ids for {} documents",
+                ids.len(),
+                documents.len()
+            )));
+        }
+        let texts: Vec<String> = documents.iter().map(|d| d.to_string()).collect();
+        let embeddings = self.embedder()?.embed(&texts).await?;
+        let mut payload =
+            models::AddCollectionRecordsPayload::new(ids.iter().map(|id| id.to_string()).collect());
+        payload.documents = Some(texts.into_iter().map(Some).collect());
+        payload.embeddings = Some(models::EmbeddingsPayload::Float(embeddings));
+        self.add(&payload).await
+    }
+
     pub async fn upsert(
         &self,
         payload: &models::UpsertCollectionRecordsPayload,
     ) -> Result<(), KhromaError> {
+        let embeddings = self
+            .embed_missing(payload.embeddings.as_ref(), payload.documents.as_deref())
+            .await?;
+        let payload = match embeddings {
+            Some(embeddings) => {
+                let mut payload = payload.clone();
+                payload.embeddings = Some(embeddings);
+                Cow::Owned(payload)
+            }
+            None => Cow::Borrowed(payload),
+        };
         self.client
             .collection_upsert(
                 &self.tenant_name,
                 &self.database_name,
                 &self.id.to_string(),
-                payload,
+                &payload,
             )
             .await?;
+        self.record_write(ChangeKind::Upsert, &payload.ids, None);
+        Ok(())
+    }
+
+    /// Adds records in chunks no larger than the server's `max_batch_size`, sending at most
+    /// `concurrency` chunks at a time. Chunks already sent stay added if a later one fails.
+    pub async fn add_batched(
+        &self,
+        payload: &models::AddCollectionRecordsPayload,
+        concurrency: usize,
+    ) -> Result<(), KhromaError> {
+        self.add_batched_with_progress(payload, concurrency, |_| {})
+            .await
+    }
+
+    /// Like [`Collection::add_batched`], calling `on_progress` after each chunk is added.
+    pub async fn add_batched_with_progress(
+        &self,
+        payload: &models::AddCollectionRecordsPayload,
+        concurrency: usize,
+        on_progress: impl FnMut(&ProgressEvent),
+    ) -> Result<(), KhromaError> {
+        let chunks = payload.chunks(self.max_batch_size().await?)?;
+        let batches = chunks
+            .iter()
+            .map(|chunk| async move { self.add(chunk).await.map(|()| chunk.ids.len()) });
+        send_batches(batches, payload.ids.len(), concurrency, on_progress).await
+    }
+
+    /// Upserts records in chunks no larger than the server's `max_batch_size`, sending at
+    /// most `concurrency` chunks at a time.
+    pub async fn upsert_batched(
+        &self,
+        payload: &models::UpsertCollectionRecordsPayload,
+        concurrency: usize,
+    ) -> Result<(), KhromaError> {
+        self.upsert_batched_with_progress(payload, concurrency, |_| {})
+            .await
+    }
+
+    /// Like [`Collection::upsert_batched`], calling `on_progress` after each chunk is upserted.
+    pub async fn upsert_batched_with_progress(
+        &self,
+        payload: &models::UpsertCollectionRecordsPayload,
+        concurrency: usize,
+        on_progress: impl FnMut(&ProgressEvent),
+    ) -> Result<(), KhromaError> {
+        let chunks = payload.chunks(self.max_batch_size().await?)?;
+        let batches = chunks
+            .iter()
+            .map(|chunk| async move { self.upsert(chunk).await.map(|()| chunk.ids.len()) });
+        send_batches(batches, payload.ids.len(), concurrency, on_progress).await
+    }
+
+    /// Upserts the records in `reader`, validating each and sending them in batches of the
+    /// server's `max_batch_size`, one batch at a time.
+    ///
+    /// Stops at the first invalid record or failed batch; records before it are imported,
+    /// and [`ImportReport::next_offset`] tells [`Collection::import_from`] where to resume.
+    /// The file is read one batch at a time on the runtime's blocking threads.
+    pub async fn import(
+        &self,
+        reader: impl Read + Seek + Send + 'static,
+        format: ImportFormat,
+    ) -> ImportReport {
+        self.import_from(reader, format, 0).await
+    }
+
+    /// Like [`Collection::import`], skipping the first `offset` records.
+    pub async fn import_from(
+        &self,
+        reader: impl Read + Seek + Send + 'static,
+        format: ImportFormat,
+        offset: u64,
+    ) -> ImportReport {
+        let started = Instant::now();
+        let mut report = ImportReport {
+            imported: 0,
+            next_offset: offset,
+            error: None,
+            elapsed: Duration::ZERO,
+        };
+        if let Err(error) = self.import_records(reader, format, &mut report).await {
+            report.error = Some(error);
+        }
+        report.elapsed = started.elapsed();
+        report
+    }
+
+    async fn import_records(
+        &self,
+        reader: impl Read + Seek + Send + 'static,
+        format: ImportFormat,
+        report: &mut ImportReport,
+    ) -> Result<(), KhromaError> {
+        let batch_size = self.max_batch_size().await?;
+        let mut dimension = self.dimension().map(|dimension| dimension as usize);
+        let offset = report.next_offset;
+        let mut records =
+            ingest::unblock(move || ingest::read_records(reader, format, offset)).await??;
+        loop {
+            let (rest, read) =
+                ingest::unblock(move || ingest::read_batch(records, batch_size)).await?;
+            records = rest;
+            let done = read.len() < batch_size;
+            let mut batch = Vec::with_capacity(read.len());
+            for record in read {
+                let record = record.and_then(|record| {
+                    ingest::validate(&record, &mut dimension)?;
+                    Ok(record)
+                });
+                match record {
+                    Ok(record) => batch.push(record),
+                    Err(error) => {
+                        self.import_batch(&mut batch, report).await?;
+                        return Err(error);
+                    }
+                }
+            }
+            self.import_batch(&mut batch, report).await?;
+            if done {
+                return Ok(());
+            }
+        }
+    }
+
+    async fn import_batch(
+        &self,
+        batch: &mut Vec<models::Record>,
+        report: &mut ImportReport,
+    ) -> Result<(), KhromaError> {
+        if batch.is_empty() {
+            return Ok(());
+        }
+        let count = batch.len() as u64;
+        self.upsert_records(std::mem::take(batch)).await?;
+        report.imported += count;
+        report.next_offset += count;
+        Ok(())
+    }
+
+    /// Writes every record, embeddings included, to `writer` as a snapshot in the
+    /// [`ImportFormat::JsonLines`] format, compressed with `compression` if given.
+    /// [`Collection::import`] reads it back, whichever codec was used. Returns how many
+    /// records were written.
+    ///
+    /// Records are read in pages of [`EXPORT_PAGE_SIZE`] and written on the runtime's
+    /// blocking threads. Writes to the collection during the export may shift its pages and
+    /// cause records to be skipped or written twice.
+    pub async fn export(
+        &self,
+        writer: impl Write + Send + 'static,
+        compression: Option<Compression>,
+    ) -> Result<u64, KhromaError> {
+        let payload = models::GetRequestPayload::default().with_include(models::Include::ALL);
+        let mut pages = std::pin::pin!(self.get_paginated(&payload, EXPORT_PAGE_SIZE));
+        let mut encoder = Encoder::new(BufWriter::new(writer), compression)?;
+        let mut exported = 0;
+        while let Some(page) = pages.try_next().await? {
+            let records = page.into_records()?;
+            exported += records.len() as u64;
+            encoder = ingest::unblock(move || ingest::write_records(encoder, records)).await??;
+        }
+        ingest::unblock(move || encoder.finish()).await??;
+        Ok(exported)
+    }
+
+    pub(crate) async fn max_batch_size(&self) -> Result<usize, KhromaError> {
+        let checks = self.client.cached_pre_flight_checks().await?;
+        Ok(checks.max_batch_size.max(1) as usize)
+    }
+
+    /// Upserts only the records whose content differs from what is stored.
+    ///
+    /// A hash of each record's embedding, document, metadata and URI is kept in the
+    /// [`CONTENT_HASH_KEY`] metadata entry; records whose stored hash matches are skipped.
+    pub async fn upsert_if_changed(
+        &self,
+        payload: &models::UpsertCollectionRecordsPayload,
+    ) -> Result<UpsertIfChangedResult, KhromaError> {
+        let hashes = payload
+            .ids
+            .iter()
+            .enumerate()
+            .map(|(i, _)| record_hash(payload, i))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let stored = self
+            .get(&models::GetRequestPayload {
+                ids: Some(payload.ids.clone()),
+                include: Some(vec![models::Include::Metadatas]),
+                ..Default::default()
+            })
+            .await?;
+        let stored: std::collections::HashMap<String, String> = stored
+            .ids
+            .into_iter()
+            .zip(stored.metadatas.unwrap_or_default())
+            .filter_map(|(id, metadata)| {
+                let hash = metadata?.get(CONTENT_HASH_KEY)?.as_str()?.to_string();
+                Some((id, hash))
+            })
+            .collect();
+
+        let mut changed = models::UpsertCollectionRecordsPayload::default();
+        let mut embeddings_f32 = Vec::new();
+        let mut embeddings_str = Vec::new();
+        let mut metadatas = Vec::new();
+        let mut documents = Vec::new();
+        let mut uris = Vec::new();
+        let mut result = UpsertIfChangedResult::default();
+        for (i, (id, hash)) in payload.ids.iter().zip(hashes).enumerate() {
+            if stored.get(id) == Some(&hash) {
+                result.unchanged.push(id.clone());
+                continue;
+            }
+            match &payload.embeddings {
+                Some(models::EmbeddingsPayload::Float(e)) => embeddings_f32.push(e[i].clone()),
+                Some(models::EmbeddingsPayload::String(e)) => embeddings_str.push(e[i].clone()),
+                None => {}
+            }
+            let mut metadata = payload
+                .metadatas
+                .as_ref()
+                .and_then(|m| m[i].clone())
+                .unwrap_or_default();
+            metadata.insert(CONTENT_HASH_KEY.to_string(), hash.into());
+            metadatas.push(Some(metadata));
+            if let Some(d) = &payload.documents {
+                documents.push(d[i].clone());
+            }
+            if let Some(u) = &payload.uris {
+                uris.push(u[i].clone());
+            }
+            changed.ids.push(id.clone());
+            result.upserted.push(id.clone());
+        }
+        if changed.ids.is_empty() {
+            return Ok(result);
+        }
+
+        changed.embeddings = match &payload.embeddings {
+            Some(models::EmbeddingsPayload::Float(_)) => {
+                Some(models::EmbeddingsPayload::Float(embeddings_f32))
+            }
+            Some(models::EmbeddingsPayload::String(_)) => {
+                Some(models::EmbeddingsPayload::String(embeddings_str))
+            }
+            None => None,
+        };
+        changed.metadatas = Some(metadatas);
+        changed.documents = payload.documents.as_ref().map(|_| documents);
+        changed.uris = payload.uris.as_ref().map(|_| uris);
+        self.upsert(&changed).await?;
+        Ok(result)
+    }
+
+    /// Fetches the collection's current name, metadata and configuration from the server.
+    pub async fn reload(&mut self) -> Result<(), KhromaError> {
+        let model = self.fetch_model().await?;
+        self.set_model(model);
         Ok(())
     }
 
+    async fn fetch_model(&self) -> Result<models::Collection, KhromaError> {
+        self.client
+            .get_collection(&self.tenant_name, &self.database_name, &self.id.to_string())
+            .await
+    }
+
+    fn set_model(&mut self, model: models::Collection) {
+        self.name = model.name.clone().into();
+        self.model = Arc::new(model);
+    }
+
+    /// Reloads the collection and returns whether it changed since this handle last saw
+    /// it.
+    ///
+    /// Changes to the name, metadata or configuration are detected at once. Record writes
+    /// are only detected once compacted, through [`Collection::log_position`], so a `false`
+    /// doesn't mean no records were written since.
+    pub async fn refresh_if_stale(&mut self) -> Result<bool, KhromaError> {
+        let model = self.fetch_model().await?;
+        let stale = model.log_position != self.model.log_position
+            || definition_changed(&self.model, &model);
+        self.set_model(model);
+        Ok(stale)
+    }
+
+    /// Applies `payload` only if the collection's version, name, metadata and configuration
+    /// are still what this handle last saw, then reloads the handle. Record writes don't
+    /// count as changes.
+    ///
+    /// Otherwise fails with [`KhromaError::Conflict`] and reloads the handle, so the caller
+    /// can rebuild its change from the current state, e.g. re-read [`Collection::metadata`],
+    /// and try again.
+    ///
+    /// Chroma has no conditional update, so the check and the update are separate requests
+    /// and a change landing between them goes unnoticed. The check catches changes made
+    /// while the caller worked from its copy, which is where most of the window lies.
+    pub async fn update_if_unchanged(
+        &mut self,
+        payload: &models::UpdateCollectionPayload,
+    ) -> Result<(), KhromaError> {
+        let current = self.fetch_model().await?;
+        if definition_changed(&self.model, &current) {
+            let message = format!(
+                "Collection {} changed since version {} (now version {})",
+                self.id, self.model.version, current.version
+            );
+            self.set_model(current);
+            return Err(KhromaError::Conflict(message));
+        }
+        self.update(payload).await?;
+        self.reload().await
+    }
+
+    /// Forks the collection and returns a handle to the fork, configured like this one.
+    ///
+    /// Takes a new name, or a [`ForkCollectionPayload::builder`](models::ForkCollectionPayload::builder)
+    /// request that also changes the fork's metadata or index parameters. If applying those
+    /// changes fails, the fork is deleted again and the error returned.
+    pub async fn fork(
+        &self,
+        request: impl Into<models::ForkCollectionRequest>,
+    ) -> Result<Collection, KhromaError> {
+        let request = request.into();
+        let model = self
+            .client
+            .fork_collection(
+                &self.tenant_name,
+                &self.database_name,
+                &self.id.to_string(),
+                &request.fork,
+            )
+            .await?;
+        let mut fork = Collection {
+            id: model.id.into(),
+            name: model.name.clone().into(),
+            tenant_name: model.tenant.clone(),
+            database_name: model.database.clone(),
+            model: Arc::new(model),
+            ..self.clone()
+        };
+        let Some(update) = &request.update else {
+            return Ok(fork);
+        };
+        if let Err(error) = fork.update(update).await {
+            if let Err(cleanup) = self
+                .client
+                .delete_collection(&fork.tenant_name, &fork.database_name, &fork.id.to_string())
+                .await
+            {
+                log::warn!(
+                    "Failed to delete fork '{}' after its update failed: {}",
+                    fork.name,
+                    cleanup
+                );
+            }
+            return Err(error);
+        }
+        fork.reload().await?;
+        Ok(fork)
+    }
+
     pub async fn query(
         &self,
         payload: &models::QueryRequestPayload,
         limit: Option<i32>,
         offset: Option<i32>,
     ) -> Result<models::QueryResponse, KhromaError> {
+        let payload = self.query_payload(payload);
+        let two_phase = self.two_phase.filter(|policy| policy.applies_to(&payload));
+        let Some(cache) = &self.read_cache else {
+            return self.send_query(&payload, limit, offset, two_phase).await;
+        };
+        let key = format!(
+            "query/{}?limit={:?}&offset={:?}&two_phase={:?}#{}",
+            self.id,
+            limit,
+            offset,
+            two_phase.map(|policy| policy.top_k),
+            payload.digest()?
+        );
+        let version = self.current_version().await?;
+        if let Some(response) = cache.query(&key, version) {
+            return Ok(response);
+        }
+        let response = self.send_query(&payload, limit, offset, two_phase).await?;
+        cache.insert_query(key, version, response.clone());
+        Ok(response)
+    }
+
+    async fn send_query(
+        &self,
+        payload: &models::QueryRequestPayload,
+        limit: Option<i32>,
+        offset: Option<i32>,
+        two_phase: Option<TwoPhaseFetch>,
+    ) -> Result<models::QueryResponse, KhromaError> {
+        if let Some(policy) = &two_phase {
+            return self.query_two_phase(payload, limit, offset, policy).await;
+        }
         self.client
             .collection_query(
                 &self.tenant_name,
@@ -74,33 +822,487 @@ impl Collection {
             .await
     }
 
-    pub async fn get(
+    /// Queries without embeddings, then fetches the embeddings of each query's first
+    /// `top_k` hits by id.
+    async fn query_two_phase(
+        &self,
+        payload: &models::QueryRequestPayload,
+        limit: Option<i32>,
+        offset: Option<i32>,
+        policy: &TwoPhaseFetch,
+    ) -> Result<models::QueryResponse, KhromaError> {
+        let mut trimmed = payload.clone();
+        if let Some(include) = &mut trimmed.include {
+            include.retain(|i| *i != models::Include::Embeddings);
+        }
+        let mut response = self
+            .client
+            .collection_query(
+                &self.tenant_name,
+                &self.database_name,
+                &self.id.to_string(),
+                limit,
+                offset,
+                &trimmed,
+            )
+            .await?;
+
+        let mut ids: Vec<String> = response
+            .ids
+            .iter()
+            .flat_map(|hits| hits.iter().take(policy.top_k).cloned())
+            .collect();
+        ids.sort_unstable();
+        ids.dedup();
+        let get = models::GetRequestPayload {
+            ids: Some(ids),
+            include: Some(vec![models::Include::Embeddings]),
+            ..Default::default()
+        };
+        let fetched = self.get(&get).await?;
+        let embeddings: HashMap<String, Vec<f32>> = fetched
+            .ids
+            .into_iter()
+            .zip(fetched.embeddings.unwrap_or_default())
+            .collect();
+
+        response.embeddings = Some(
+            response
+                .ids
+                .iter()
+                .map(|hits| {
+                    hits.iter()
+                        .enumerate()
+                        .map(|(rank, id)| match embeddings.get(id) {
+                            Some(embedding) if rank < policy.top_k => {
+                                embedding.iter().copied().map(Some).collect()
+                            }
+                            _ => Vec::new(),
+                        })
+                        .collect()
+                })
+                .collect(),
+        );
+        response.include.push(models::Include::Embeddings);
+        Ok(response)
+    }
+
+    pub async fn get(
+        &self,
+        payload: &models::GetRequestPayload,
+    ) -> Result<models::GetResponse, KhromaError> {
+        let payload = self.get_payload(payload);
+        let Some(cache) = &self.read_cache else {
+            return self.send_get(&payload).await;
+        };
+        let key = format!("get/{}#{}", self.id, payload.digest()?);
+        let version = self.current_version().await?;
+        if let Some(response) = cache.get(&key, version) {
+            return Ok(response);
+        }
+        let response = self.send_get(&payload).await?;
+        cache.insert_get(key, version, response.clone());
+        Ok(response)
+    }
+
+    async fn send_get(
+        &self,
+        payload: &models::GetRequestPayload,
+    ) -> Result<models::GetResponse, KhromaError> {
+        self.client
+            .collection_get(
+                &self.tenant_name,
+                &self.database_name,
+                &self.id.to_string(),
+                payload,
+            )
+            .await
+    }
+
+    /// The collection's version and log position as the server reports them now, which
+    /// costs a `get_collection` round-trip. They only advance as the server compacts the
+    /// write-ahead log, not on every write.
+    async fn current_version(&self) -> Result<CollectionVersion, KhromaError> {
+        let model = self
+            .client
+            .get_collection(&self.tenant_name, &self.database_name, &self.id.to_string())
+            .await?;
+        Ok(CollectionVersion::from(&model))
+    }
+
+    /// The first `n` records, with their documents, embeddings and metadata.
+    pub async fn peek(&self, n: u32) -> Result<models::GetResponse, KhromaError> {
+        self.get(&models::GetRequestPayload {
+            include: Some(vec![
+                models::Include::Documents,
+                models::Include::Embeddings,
+                models::Include::Metadatas,
+            ]),
+            limit: Some(n.min(i32::MAX as u32) as i32),
+            ..Default::default()
+        })
+        .await
+    }
+
+    /// Like [`Collection::query`], returning one list of records per query embedding.
+    pub async fn query_records(
+        &self,
+        payload: &models::QueryRequestPayload,
+        limit: Option<i32>,
+        offset: Option<i32>,
+    ) -> Result<Vec<Vec<models::Record>>, KhromaError> {
+        self.query(payload, limit, offset).await?.into_records()
+    }
+
+    pub async fn get_records(
+        &self,
+        payload: &models::GetRequestPayload,
+    ) -> Result<Vec<models::Record>, KhromaError> {
+        self.get(payload).await?.into_records()
+    }
+
+    /// The records with the given ids. With `include` unset, the collection's default
+    /// includes apply.
+    pub async fn get_by_ids(
+        &self,
+        ids: &[&str],
+        include: Option<models::IncludeList>,
+    ) -> Result<models::GetResponse, KhromaError> {
+        self.get(&models::GetRequestPayload {
+            ids: Some(ids.iter().map(|id| id.to_string()).collect()),
+            include,
+            ..Default::default()
+        })
+        .await
+    }
+
+    /// Fetches `page_size` records matching `payload`, starting where `token` left off,
+    /// or at the beginning without one. `payload.limit` and `payload.offset` are ignored.
+    pub async fn get_page(
+        &self,
+        payload: &models::GetRequestPayload,
+        page_size: u32,
+        token: Option<&PageToken>,
+    ) -> Result<Page<models::Record>, KhromaError> {
+        let page_size = page_size.max(1);
+        let mut page = payload.clone();
+        page.limit = Some(page_size.min(i32::MAX as u32) as i32);
+        page.offset = token.map(PageToken::offset);
+        let records = self.get(&page).await?.into_records()?;
+        Ok(Page::new(records, token, page_size))
+    }
+
+    /// Like [`Collection::query`], but during a server outage answers from the cache or
+    /// with [`QueryOutcome::Degraded`], as allowed by the handle's degradation policy
+    /// (the default [`DegradationPolicy`] unless another was set).
+    pub async fn query_resilient(
+        &self,
+        payload: &models::QueryRequestPayload,
+        limit: Option<i32>,
+        offset: Option<i32>,
+    ) -> Result<QueryOutcome, KhromaError> {
+        let key = format!(
+            "{}?limit={:?}&offset={:?}#{}",
+            self.id,
+            limit,
+            offset,
+            payload.digest()?
+        );
+        self.degradation
+            .resolve(key, self.query(payload, limit, offset).await)
+    }
+
+    /// Like [`Collection::query`], but returns within `budget` with whatever has been
+    /// answered so far. Each query embedding is sent as its own request, so a slow one does
+    /// not hold back the others; requests still running when the budget expires are dropped.
+    ///
+    /// The call is all-or-nothing on failure: if any of the requests fails before the budget
+    /// expires, its error is returned, the requests still running are dropped, and the answers
+    /// already received are discarded.
+    pub async fn query_within(
+        &self,
+        payload: &models::QueryRequestPayload,
+        budget: Duration,
+        limit: Option<i32>,
+        offset: Option<i32>,
+    ) -> Result<BudgetedQuery, KhromaError> {
+        if payload.query_embeddings.is_empty() {
+            return self
+                .query(payload, limit, offset)
+                .await
+                .map(BudgetedQuery::Complete);
+        }
+        let deadline = tokio::time::Instant::now() + budget;
+        let mut pending: FuturesUnordered<_> = payload
+            .query_embeddings
+            .iter()
+            .enumerate()
+            .map(|(i, embedding)| {
+                let mut single = payload.clone();
+                single.query_embeddings = vec![embedding.clone()];
+                async move { (i, self.query(&single, limit, offset).await) }
+            })
+            .collect();
+
+        let mut parts = vec![None; payload.query_embeddings.len()];
+        loop {
+            match tokio::time::timeout_at(deadline, pending.next()).await {
+                Ok(Some((i, response))) => parts[i] = Some(response?),
+                Ok(None) => {
+                    let response = models::QueryResponse::concat(parts)
+                        .expect("every query embedding was answered");
+                    return Ok(BudgetedQuery::Complete(response));
+                }
+                Err(_) => {
+                    return Ok(BudgetedQuery::TimedOut {
+                        partial: models::QueryResponse::concat(parts),
+                    });
+                }
+            }
+        }
+    }
+
+    /// Queries with `text`, embedded by the attached embedding function.
+    pub async fn query_text(
+        &self,
+        text: &str,
+        n_results: Option<i32>,
+    ) -> Result<models::QueryResponse, KhromaError> {
+        let mut embeddings = self.embedder()?.embed(&[text.to_string()]).await?;
+        let mut payload = models::QueryRequestPayload::new(vec![embeddings.remove(0)]);
+        payload.n_results = n_results;
+        self.query(&payload, None, None).await
+    }
+
+    /// Queries with each of `texts`, embedded by the attached embedding function.
+    pub async fn query_by_texts(
+        &self,
+        texts: &[&str],
+        n_results: Option<i32>,
+    ) -> Result<models::QueryResponse, KhromaError> {
+        let texts: Vec<String> = texts.iter().map(|t| t.to_string()).collect();
+        let embeddings = self.embedder()?.embed(&texts).await?;
+        let mut payload = models::QueryRequestPayload::new(embeddings);
+        payload.n_results = n_results;
+        self.query(&payload, None, None).await
+    }
+
+    /// Queries for [`RERANK_OVERFETCH`] times `top_k` candidates per query embedding, scores
+    /// them with `reranker` and returns the best `top_k` of each, highest score first.
+    ///
+    /// Hits carry the fields in `payload`'s include list, so rerankers that read the text
+    /// need [`Include::Documents`](models::Include::Documents). `payload.n_results` is ignored.
+    pub async fn query_reranked(
+        &self,
+        payload: &models::QueryRequestPayload,
+        reranker: &dyn Reranker,
+        top_k: u32,
+    ) -> Result<Vec<Vec<RerankedHit>>, KhromaError> {
+        let mut payload = payload.clone();
+        let candidates = top_k.saturating_mul(RERANK_OVERFETCH);
+        payload.n_results = Some(candidates.min(i32::MAX as u32) as i32);
+        let results = self.query(&payload, None, None).await?.into_results()?;
+        rerank::rerank(reranker, results, top_k as usize).await
+    }
+
+    /// Ranks records by both vector similarity and the text filter `text_filter`.
+    ///
+    /// Sends two queries with `query_embeddings`, one plain and one restricted to documents
+    /// matching `text_filter`, and merges every result list into the best `n_results`
+    /// records according to `fusion`. Hits carry documents, metadata and distances.
+    pub async fn hybrid_query(
+        &self,
+        text_filter: models::WhereDocument,
+        query_embeddings: impl Into<models::EmbeddingsPayload>,
+        n_results: u32,
+        fusion: Fusion,
+    ) -> Result<Vec<HybridHit>, KhromaError> {
+        const INCLUDE: &[models::Include] = &[
+            models::Include::Documents,
+            models::Include::Metadatas,
+            models::Include::Distances,
+        ];
+        let vector = models::QueryRequestPayload::builder(query_embeddings)?
+            .n_results(n_results)
+            .include(INCLUDE)
+            .build();
+        let mut text = vector.clone();
+        text.where_fields.where_document = Some(text_filter.into());
+        let (vector, text) = futures::try_join!(
+            self.query(&vector, None, None),
+            self.query(&text, None, None)
+        )?;
+        Ok(hybrid::fuse(
+            vector.into_results()?,
+            text.into_results()?,
+            n_results as usize,
+            fusion,
+        ))
+    }
+
+    /// Like [`Collection::get`], with all embeddings read into one contiguous buffer.
+    pub async fn get_arena(
+        &self,
+        payload: &models::GetRequestPayload,
+    ) -> Result<models::ArenaGetResponse, KhromaError> {
+        self.client
+            .collection_get_arena(
+                &self.tenant_name,
+                &self.database_name,
+                &self.id.to_string(),
+                &self.get_payload(payload),
+            )
+            .await
+    }
+
+    /// Like [`Collection::query`], with each query's embeddings read into one contiguous buffer.
+    pub async fn query_arena(
+        &self,
+        payload: &models::QueryRequestPayload,
+        limit: Option<i32>,
+        offset: Option<i32>,
+    ) -> Result<models::ArenaQueryResponse, KhromaError> {
+        self.client
+            .collection_query_arena(
+                &self.tenant_name,
+                &self.database_name,
+                &self.id.to_string(),
+                limit,
+                offset,
+                &self.query_payload(payload),
+            )
+            .await
+    }
+
+    /// Streams the records matching `payload` in pages of `page_size`, starting at
+    /// `payload.offset` and stopping after `payload.limit` records when it is set.
+    pub fn get_paginated(
+        &self,
+        payload: &models::GetRequestPayload,
+        page_size: u32,
+    ) -> impl Stream<Item = Result<models::GetResponse, KhromaError>> + Send + 'static {
+        self.get_paginated_with_prefetch(payload, page_size, 0)
+    }
+
+    /// Like [`Collection::get_paginated`], requesting up to `prefetch` pages past the one
+    /// awaited, so their round-trips overlap.
+    ///
+    /// Like any future, the requests only progress while the stream is polled, so time
+    /// spent on a page between polls isn't overlapped. When the collection runs out, up to
+    /// `prefetch` requests past its end are wasted.
+    pub fn get_paginated_with_prefetch(
+        &self,
+        payload: &models::GetRequestPayload,
+        page_size: u32,
+        prefetch: usize,
+    ) -> impl Stream<Item = Result<models::GetResponse, KhromaError>> + Send + 'static {
+        let collection = self.clone();
+        let payload = payload.clone();
+        let page_size = page_size.clamp(1, i32::MAX as u32) as i32;
+        let start = payload.offset.unwrap_or(0);
+        let end = payload.limit.map(|limit| start.saturating_add(limit.max(0)));
+        let offsets = std::iter::successors(Some(start), move |o| o.checked_add(page_size))
+            .map_while(move |offset| {
+                let limit = end.map_or(page_size, |end| page_size.min(end - offset));
+                (limit > 0).then_some((offset, limit))
+            });
+        futures::stream::iter(offsets)
+            .map(move |(offset, limit)| {
+                let collection = collection.clone();
+                let mut page = payload.clone();
+                page.offset = Some(offset);
+                page.limit = Some(limit);
+                async move { (limit, collection.get(&page).await) }
+            })
+            .buffered(prefetch.saturating_add(1))
+            .scan(false, |done, (limit, result)| {
+                let item = match result {
+                    _ if *done => None,
+                    Err(e) => {
+                        *done = true;
+                        Some(Err(e))
+                    }
+                    Ok(response) if response.ids.is_empty() => None,
+                    Ok(response) => {
+                        // A short page means the collection is exhausted.
+                        *done = (response.ids.len() as i32) < limit;
+                        Some(Ok(response))
+                    }
+                };
+                futures::future::ready(item)
+            })
+    }
+
+    /// Drops this collection's cached reads and tells change observers about a write.
+    fn record_write(
         &self,
-        payload: &models::GetRequestPayload,
-    ) -> Result<models::GetResponse, KhromaError> {
+        kind: ChangeKind,
+        ids: &[String],
+        filter: Option<&models::RawWhereFields>,
+    ) {
+        if let Some(cache) = &self.read_cache {
+            cache.invalidate(&self.id.to_string());
+        }
+        let filter = filter.filter(|f| f.r#where.is_some() || f.where_document.is_some());
+        self.observers.notify(self.id, kind, ids, filter);
+    }
+
+    /// Deletes the records named by `payload`, or matching its filters. Change observers
+    /// receive the ids named and the filters sent, the handle's base filter included; see
+    /// [`ChangeEvent`](crate::observer::ChangeEvent).
+    pub async fn delete(
+        &self,
+        payload: &models::DeleteCollectionRecordsPayload,
+    ) -> Result<(), KhromaError> {
+        let payload = self.delete_payload(payload);
         self.client
-            .collection_get(
+            .collection_delete(
                 &self.tenant_name,
                 &self.database_name,
                 &self.id.to_string(),
-                payload,
+                &payload,
             )
-            .await
+            .await?;
+        let ids = payload.ids.as_deref().unwrap_or_default();
+        self.record_write(ChangeKind::Delete, ids, Some(&payload.where_fields));
+        Ok(())
     }
 
-    pub async fn delete(
+    /// Deletes the records matching `filter`. With `count`, first fetches the ids of the
+    /// matching records, which the report returns. Change observers receive the filter
+    /// sent and, with `count`, the matched ids.
+    ///
+    /// Counting and deleting are separate requests, so writes racing the delete can make
+    /// the count differ from what was removed.
+    pub async fn delete_where(
         &self,
-        payload: &models::DeleteCollectionRecordsPayload,
-    ) -> Result<(), KhromaError> {
+        filter: impl Into<models::RawWhereFields>,
+        count: bool,
+    ) -> Result<DeleteReport, KhromaError> {
+        let where_fields = filter.into();
+        let matched_ids = if count {
+            let payload = models::GetRequestPayload {
+                where_fields: where_fields.clone(),
+                include: Some(Vec::new()),
+                ..Default::default()
+            };
+            Some(self.get(&payload).await?.ids)
+        } else {
+            None
+        };
+        let payload = models::DeleteCollectionRecordsPayload::matching(where_fields);
+        let payload = self.delete_payload(&payload);
         self.client
             .collection_delete(
                 &self.tenant_name,
                 &self.database_name,
                 &self.id.to_string(),
-                payload,
+                &payload,
             )
             .await?;
-        Ok(())
+        let ids = matched_ids.as_deref().unwrap_or_default();
+        self.record_write(ChangeKind::Delete, ids, Some(&payload.where_fields));
+        Ok(DeleteReport { matched_ids })
     }
 
     pub async fn update_records(
@@ -115,6 +1317,7 @@ impl Collection {
                 payload,
             )
             .await?;
+        self.record_write(ChangeKind::Update, &payload.ids, None);
         Ok(())
     }
 
@@ -133,13 +1336,285 @@ impl Collection {
         Ok(())
     }
 
+    /// Renames the collection and this handle with it.
+    pub async fn rename(&mut self, new_name: impl Into<String>) -> Result<(), KhromaError> {
+        let new_name = new_name.into();
+        self.update(
+            &models::UpdateCollectionPayload::builder()
+                .rename(new_name.clone())
+                .build(),
+        )
+        .await?;
+        self.name = new_name.clone().into();
+        Arc::make_mut(&mut self.model).name = new_name;
+        Ok(())
+    }
+
+    /// Replaces the collection metadata as a whole and updates this handle's copy.
+    pub async fn set_metadata(&mut self, metadata: models::Metadata) -> Result<(), KhromaError> {
+        self.update(
+            &models::UpdateCollectionPayload::builder()
+                .replace_metadata(metadata.clone())
+                .build(),
+        )
+        .await?;
+        Arc::make_mut(&mut self.model).metadata = Some(metadata);
+        Ok(())
+    }
+
+    /// Uploads each blob to `store` under `{collection id}/{record id}` and adds the records
+    /// with the returned URIs. Ids that are absolute paths or have `.` or `..` parts are
+    /// rejected before anything is uploaded.
+    pub async fn add_with_blobs<S: BlobStore + ?Sized>(
+        &self,
+        store: &S,
+        mut payload: models::AddCollectionRecordsPayload,
+        blobs: Vec<Vec<u8>>,
+    ) -> Result<(), KhromaError> {
+        if blobs.len() != payload.ids.len() {
+            return Err(KhromaError::Validation(format!(
+                "Expected {} blobs, got {}",
+                payload.ids.len(),
+                blobs.len()
+            )));
+        }
+        let keys: Vec<String> = payload
+            .ids
+            .iter()
+            .map(|id| format!("{}/{}", self.id, id))
+            .collect();
+        for key in &keys {
+            crate::blob::validate_key(key)?;
+        }
+        let mut uris = Vec::with_capacity(blobs.len());
+        for (key, blob) in keys.iter().zip(blobs) {
+            uris.push(Some(store.put(key, blob).await?));
+        }
+        payload.uris = Some(uris);
+        self.add(&payload).await
+    }
+
+    pub async fn get_with_blobs<L: DataLoader + ?Sized>(
+        &self,
+        loader: &L,
+        payload: &models::GetRequestPayload,
+    ) -> Result<(models::GetResponse, LoadedBlobs), KhromaError> {
+        let mut payload = payload.clone();
+        let include = payload.include.get_or_insert_with(|| {
+            self.default_includes.get.clone().unwrap_or_else(|| {
+                vec![models::Include::Documents, models::Include::Metadatas]
+            })
+        });
+        if !include.contains(&models::Include::Uris) {
+            include.push(models::Include::Uris);
+        }
+
+        let response = self.get(&payload).await?;
+        let uris = response.uris.clone().unwrap_or_default();
+        let present: Vec<String> = uris.iter().flatten().cloned().collect();
+        let mut loaded = loader.load(&present).await?.into_iter();
+        let blobs = uris
+            .iter()
+            .map(|uri| uri.as_ref().and_then(|_| loaded.next()))
+            .collect();
+        Ok((response, blobs))
+    }
+
+    /// Runs `sample_queries` one at a time to populate server-side caches after a deploy.
+    ///
+    /// Only distances are requested, so responses stay small; results are discarded.
+    pub async fn warmup(
+        &self,
+        sample_queries: &[models::QueryRequestPayload],
+    ) -> Result<(), KhromaError> {
+        for query in sample_queries {
+            let mut query = query.clone();
+            query.include = Some(vec![models::Include::Distances]);
+            self.query(&query, None, None).await?;
+        }
+        Ok(())
+    }
+
     pub async fn count(&self) -> Result<u32, KhromaError> {
         self.client
             .collection_count(&self.tenant_name, &self.database_name, &self.id.to_string())
             .await
     }
+
+    /// Checks a window of up to `sample_size` consecutive records, starting at a random
+    /// offset, for zero, non-finite and wrongly sized embeddings, duplicate documents and
+    /// gaps in metadata.
+    pub async fn quality_report(&self, sample_size: u32) -> Result<QualityReport, KhromaError> {
+        let total = self.count().await?;
+        let offset = fastrand::u32(0..=total.saturating_sub(sample_size));
+        let sample = self
+            .get(&models::GetRequestPayload {
+                include: Some(vec![
+                    models::Include::Documents,
+                    models::Include::Embeddings,
+                    models::Include::Metadatas,
+                ]),
+                limit: Some(sample_size.min(i32::MAX as u32) as i32),
+                offset: Some(offset.min(i32::MAX as u32) as i32),
+                ..Default::default()
+            })
+            .await?;
+        let dimension = self.dimension().map(|dimension| dimension as usize);
+        Ok(QualityReport::assess(&sample, total, dimension))
+    }
+}
+
+/// The tenant and database a Chroma server creates on first start.
+const CHROMA_DEFAULT_TENANT: &str = "default_tenant";
+const CHROMA_DEFAULT_DATABASE: &str = "default_database";
+
+/// Runs one [`Khroma::self_test`] step and records its outcome, returning whether it passed.
+async fn record_step(
+    report: &mut models::SelfTestReport,
+    step: models::SelfTestStep,
+    run: impl Future<Output = Result<(), KhromaError>>,
+) -> bool {
+    let started = Instant::now();
+    let error = run.await.err().map(|e| e.to_string());
+    let passed = error.is_none();
+    report.steps.push(models::SelfTestStepResult {
+        step,
+        duration: started.elapsed(),
+        error,
+    });
+    passed
+}
+
+/// Metadata key holding the content hash written by [`Collection::upsert_if_changed`].
+pub const CONTENT_HASH_KEY: &str = "khroma_content_hash";
+
+/// Records [`Collection::export`] reads per request.
+pub const EXPORT_PAGE_SIZE: u32 = 1000;
+
+/// Runs `batches`, each resolving to the number of records it wrote, `concurrency` at a
+/// time, reporting progress after each one.
+async fn send_batches<F>(
+    batches: impl ExactSizeIterator<Item = F>,
+    records_total: usize,
+    concurrency: usize,
+    mut on_progress: impl FnMut(&ProgressEvent),
+) -> Result<(), KhromaError>
+where
+    F: Future<Output = Result<usize, KhromaError>>,
+{
+    let started = Instant::now();
+    let mut event = ProgressEvent {
+        records_sent: 0,
+        records_total,
+        batches_sent: 0,
+        batches_remaining: batches.len(),
+        elapsed: Duration::ZERO,
+    };
+    let mut sent = futures::stream::iter(batches).buffer_unordered(concurrency.max(1));
+    while let Some(records) = sent.try_next().await? {
+        event.records_sent += records;
+        event.batches_sent += 1;
+        event.batches_remaining -= 1;
+        event.elapsed = started.elapsed();
+        on_progress(&event);
+    }
+    Ok(())
+}
+
+/// Bytes a vector component takes in a JSON response, such as `-0.012345678,`.
+const JSON_BYTES_PER_COMPONENT: usize = 12;
+
+/// The number of results a query returns when `n_results` is unset.
+const DEFAULT_N_RESULTS: usize = 10;
+
+/// Keeps queries that include embeddings fast on high-dimension collections.
+///
+/// When the embeddings in a query's response are estimated to exceed `max_response_bytes`,
+/// the query is sent without them and the embeddings of each query's first `top_k` hits
+/// are then fetched by id. Hits past `top_k` get an empty embedding.
+#[derive(Debug, Clone, Copy)]
+pub struct TwoPhaseFetch {
+    pub max_response_bytes: usize,
+    pub top_k: usize,
+}
+
+impl TwoPhaseFetch {
+    pub fn new(max_response_bytes: usize, top_k: usize) -> Self {
+        Self {
+            max_response_bytes,
+            top_k,
+        }
+    }
+
+    fn applies_to(&self, payload: &models::QueryRequestPayload) -> bool {
+        let includes_embeddings = payload
+            .include
+            .as_ref()
+            .is_some_and(|include| include.contains(&models::Include::Embeddings));
+        let dimension = payload.query_embeddings.first().map_or(0, Vec::len);
+        let n_results = payload
+            .n_results
+            .map_or(DEFAULT_N_RESULTS, |n| n.max(0) as usize);
+        let estimated_bytes =
+            payload.query_embeddings.len() * n_results * dimension * JSON_BYTES_PER_COMPONENT;
+        includes_embeddings && estimated_bytes > self.max_response_bytes
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct UpsertIfChangedResult {
+    pub upserted: Vec<String>,
+    pub unchanged: Vec<String>,
+}
+
+fn record_hash(
+    payload: &models::UpsertCollectionRecordsPayload,
+    i: usize,
+) -> Result<String, KhromaError> {
+    fn column<'a, T>(
+        column: Option<&'a Vec<T>>,
+        len: usize,
+        name: &str,
+    ) -> Result<Option<&'a Vec<T>>, KhromaError> {
+        match column {
+            Some(values) if values.len() != len => Err(KhromaError::Validation(format!(
+                "{} has {} entries for {} ids",
+                name,
+                values.len(),
+                len
+            ))),
+            column => Ok(column),
+        }
+    }
+
+    let len = payload.ids.len();
+    let embedding = match &payload.embeddings {
+        Some(models::EmbeddingsPayload::Float(e)) => {
+            serde_json::json!(column(Some(e), len, "embeddings")?.map(|e| &e[i]))
+        }
+        Some(models::EmbeddingsPayload::String(e)) => {
+            serde_json::json!(column(Some(e), len, "embeddings")?.map(|e| &e[i]))
+        }
+        None => serde_json::Value::Null,
+    };
+    let mut metadata = column(payload.metadatas.as_ref(), len, "metadatas")?
+        .and_then(|m| m[i].clone())
+        .unwrap_or_default();
+    metadata.remove(CONTENT_HASH_KEY);
+    let document =
+        column(payload.documents.as_ref(), len, "documents")?.and_then(|d| d[i].as_ref());
+    let uri = column(payload.uris.as_ref(), len, "uris")?.and_then(|u| u[i].as_ref());
+
+    let record = serde_json::json!({
+        "embedding": embedding,
+        "metadata": metadata,
+        "document": document,
+        "uri": uri,
+    });
+    canonical::digest(&record)
 }
 
+/// A handle on one database. Clones share the HTTP client and copy the two names.
 #[derive(Debug, Clone)]
 pub struct Database {
     pub name: String,
@@ -158,6 +1633,10 @@ impl Database {
 }
 
 impl Database {
+    pub fn read_only(&self) -> ReadOnlyDatabase {
+        self.clone().into()
+    }
+
     pub async fn create_collection(
         &self,
         payload: &models::CreateCollectionPayload,
@@ -169,14 +1648,48 @@ impl Database {
         Ok(Collection::from(collection_model, self.client.clone()))
     }
 
-    pub async fn get_collection(&self, collection_id: &str) -> Result<Collection, KhromaError> {
+    /// Gets a collection by id: a [`models::CollectionId`], a `Uuid`, or a UUID string.
+    /// A string that isn't a UUID fails with [`KhromaError::Validation`].
+    pub async fn get_collection(
+        &self,
+        collection_id: impl TryInto<models::CollectionId, Error: Into<KhromaError>>,
+    ) -> Result<Collection, KhromaError> {
+        let collection_id = collection_id.try_into().map_err(Into::into)?;
+        let collection_model = self
+            .client
+            .get_collection(&self.tenant_name, &self.name, &collection_id.to_string())
+            .await?;
+        Ok(Collection::from(collection_model, self.client.clone()))
+    }
+
+    pub async fn get_collection_by_name(&self, name: &str) -> Result<Collection, KhromaError> {
+        // The collection GET endpoint resolves its path segment by name as well.
         let collection_model = self
             .client
-            .get_collection(&self.tenant_name, &self.name, collection_id)
+            .get_collection(&self.tenant_name, &self.name, name)
             .await?;
         Ok(Collection::from(collection_model, self.client.clone()))
     }
 
+    /// Whether a collection named `name` exists in this database.
+    pub async fn collection_exists(&self, name: &str) -> Result<bool, KhromaError> {
+        found(
+            self.client
+                .get_collection(&self.tenant_name, &self.name, name)
+                .await,
+        )
+    }
+
+    pub async fn resolve_collection_id(
+        &self,
+        name: impl Into<models::CollectionName>,
+    ) -> Result<models::CollectionId, KhromaError> {
+        self.client
+            .resolve_collection_id(&self.tenant_name, &self.name, &name.into())
+            .await
+            .map(models::CollectionId::from)
+    }
+
     pub async fn get_or_create_collection(
         &self,
         payload: models::CreateCollectionPayload,
@@ -186,6 +1699,30 @@ impl Database {
         self.create_collection(&create_payload).await
     }
 
+    /// Lists the collections whose metadata matches the `where` filter `where_metadata`,
+    /// e.g. `{"project": "atlas"}` or `{"$and": [{"owner": "alice"}, {"tier": {"$gte": 2}}]}`.
+    ///
+    /// The server cannot filter collections, so every collection is listed and the filter
+    /// is evaluated here. Collections without metadata match only filters on absent keys.
+    pub async fn find_collections(
+        &self,
+        where_metadata: &serde_json::Value,
+    ) -> Result<Vec<Collection>, KhromaError> {
+        let empty = models::Metadata::new();
+        let mut found = Vec::new();
+        for collection in self
+            .client
+            .list_collections(&self.tenant_name, &self.name, None, None)
+            .await?
+        {
+            let metadata = collection.metadata.as_ref().unwrap_or(&empty);
+            if matches_where(where_metadata, metadata)? {
+                found.push(Collection::from(collection, self.client.clone()));
+            }
+        }
+        Ok(found)
+    }
+
     pub async fn list_collections(
         &self,
         limit: Option<i32>,
@@ -200,9 +1737,127 @@ impl Database {
             .collect())
     }
 
-    pub async fn delete_collection(&self, collection_id: &str) -> Result<(), KhromaError> {
+    /// Lists `page_size` collections, starting where `token` left off.
+    pub async fn list_collections_page(
+        &self,
+        page_size: u32,
+        token: Option<&PageToken>,
+    ) -> Result<Page<Collection>, KhromaError> {
+        let page_size = page_size.max(1);
+        let collections = self
+            .list_collections(
+                Some(page_size.min(i32::MAX as u32) as i32),
+                token.map(PageToken::offset),
+            )
+            .await?;
+        Ok(Page::new(collections, token, page_size))
+    }
+
+    /// Lists collections together with their record counts, counting at most
+    /// `concurrency` collections at a time.
+    pub async fn list_collections_detailed(
+        &self,
+        limit: Option<i32>,
+        offset: Option<i32>,
+        concurrency: usize,
+    ) -> Result<Vec<CollectionDetails>, KhromaError> {
+        let collections = self
+            .client
+            .list_collections(&self.tenant_name, &self.name, limit, offset)
+            .await?;
+        futures::stream::iter(collections)
+            .map(|collection| async move {
+                let record_count = self
+                    .client
+                    .collection_count(&self.tenant_name, &self.name, &collection.id.to_string())
+                    .await?;
+                Ok::<_, KhromaError>(CollectionDetails {
+                    collection,
+                    record_count,
+                })
+            })
+            .buffered(concurrency.max(1))
+            .try_collect()
+            .await
+    }
+
+    /// Sums the record counts of every collection, counting at most `concurrency` at a time.
+    pub async fn total_records(&self, concurrency: usize) -> Result<u64, KhromaError> {
+        Ok(self
+            .list_collections_detailed(None, None, concurrency)
+            .await?
+            .iter()
+            .map(|details| u64::from(details.record_count))
+            .sum())
+    }
+
+    /// Brings the database's collections in line with `spec`: missing collections are
+    /// created and existing ones get their metadata and mutable index parameters updated.
+    ///
+    /// Every change is planned before the first request is sent, so a spec asking for an
+    /// impossible change, such as a different distance space, fails without touching the
+    /// database. Collections not named in the spec are left alone.
+    pub async fn apply_config(
+        &self,
+        spec: &models::DatabaseSpec,
+    ) -> Result<ApplyReport, KhromaError> {
+        let existing: HashMap<String, models::Collection> = self
+            .client
+            .list_collections(&self.tenant_name, &self.name, None, None)
+            .await?
+            .into_iter()
+            .map(|collection| (collection.name.clone(), collection))
+            .collect();
+
+        let mut report = ApplyReport::default();
+        let mut creates = Vec::new();
+        let mut updates = Vec::new();
+        for desired in &spec.collections {
+            match existing.get(&desired.name) {
+                None => {
+                    if let Some(configuration) = &desired.configuration {
+                        configuration.index()?;
+                    }
+                    creates.push(models::CreateCollectionPayload::from(desired.clone()));
+                }
+                Some(actual) => match plan_update(desired, actual)? {
+                    Some(update) => updates.push((actual, update)),
+                    None => report.unchanged.push(desired.name.clone()),
+                },
+            }
+        }
+
+        for payload in creates {
+            self.client
+                .create_collection(&self.tenant_name, &self.name, &payload)
+                .await?;
+            report.created.push(payload.name);
+        }
+        for (actual, update) in updates {
+            self.client
+                .update_collection(
+                    &self.tenant_name,
+                    &self.name,
+                    &actual.id.to_string(),
+                    &update,
+                )
+                .await?;
+            report.updated.push(actual.name.clone());
+        }
+        Ok(report)
+    }
+
+    /// Deletes a collection, given its id or its name.
+    pub async fn delete_collection(
+        &self,
+        collection: impl Into<models::CollectionRef>,
+    ) -> Result<(), KhromaError> {
+        let collection_id = match collection.into() {
+            models::CollectionRef::Id(id) => id,
+            models::CollectionRef::Name(name) => self.resolve_collection_id(name).await?,
+        };
         self.client
-            .delete_collection(&self.tenant_name, &self.name, collection_id)
+            .delete_collection(&self.tenant_name, &self.name, &collection_id.to_string())
             .await?;
         Ok(())
     }
@@ -214,6 +1869,145 @@ impl Database {
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct CollectionDetails {
+    pub collection: models::Collection,
+    pub record_count: u32,
+}
+
+/// What [`Collection::delete_where`] deleted.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DeleteReport {
+    /// The ids of the records matching the filter just before the delete, when counted.
+    pub matched_ids: Option<Vec<String>>,
+}
+
+impl DeleteReport {
+    /// How many records matched the filter, when counted.
+    pub fn deleted(&self) -> Option<usize> {
+        self.matched_ids.as_ref().map(Vec::len)
+    }
+}
+
+/// What `Database::apply_config` changed, by collection name.
+#[derive(Debug, Clone, Default)]
+pub struct ApplyReport {
+    pub created: Vec<String>,
+    pub updated: Vec<String>,
+    pub unchanged: Vec<String>,
+}
+
+/// Whether a lookup found its resource, with not found (404) as `false` and other errors
+/// passed on.
+fn found<T>(lookup: Result<T, KhromaError>) -> Result<bool, KhromaError> {
+    match lookup {
+        Ok(_) => Ok(true),
+        Err(e) if e.is_not_found() => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+/// Whether the collection's version, name, metadata or configuration differ between two
+/// descriptions of it.
+fn definition_changed(seen: &models::Collection, current: &models::Collection) -> bool {
+    seen.version != current.version
+        || seen.name != current.name
+        || seen.metadata != current.metadata
+        || serde_json::to_value(&seen.configuration_json).ok()
+            != serde_json::to_value(&current.configuration_json).ok()
+}
+
+/// Index parameters the server fixes when a collection is created.
+const IMMUTABLE_INDEX_FIELDS: [&str; 2] = ["space", "ef_construction"];
+
+/// The update turning `actual` into `desired`, or `None` if they already agree.
+fn plan_update(
+    desired: &models::CollectionSpec,
+    actual: &models::Collection,
+) -> Result<Option<models::UpdateCollectionPayload>, KhromaError> {
+    let mut update = models::UpdateCollectionPayload::default();
+    if let Some(metadata) = &desired.metadata
+        && actual.metadata.as_ref() != Some(metadata)
+    {
+        update.new_metadata = Some(metadata.clone());
+    }
+
+    if let Some(configuration) = &desired.configuration {
+        let current = &actual.configuration_json;
+        let mut new_configuration = models::UpdateCollectionConfiguration::default();
+        let index_mismatch = (configuration.hnsw.is_some() && current.spann.is_some())
+            || (configuration.spann.is_some() && current.hnsw.is_some());
+        if index_mismatch {
+            return Err(KhromaError::Validation(format!(
+                "Cannot change the index type of collection '{}'; recreate it instead",
+                desired.name
+            )));
+        }
+        if let Some(hnsw) = &configuration.hnsw {
+            new_configuration.hnsw = changed_index_fields(&desired.name, hnsw, &current.hnsw)?;
+        }
+        if let Some(spann) = &configuration.spann {
+            new_configuration.spann = changed_index_fields(&desired.name, spann, &current.spann)?;
+        }
+        if let Some(embedding_function) = &configuration.embedding_function
+            && serde_json::to_value(embedding_function).ok()
+                != serde_json::to_value(&current.embedding_function).ok()
+        {
+            new_configuration.embedding_function = Some(embedding_function.clone());
+        }
+        if new_configuration.hnsw.is_some()
+            || new_configuration.spann.is_some()
+            || new_configuration.embedding_function.is_some()
+        {
+            update.new_configuration = Some(new_configuration);
+        }
+    }
+
+    let changed = update.new_metadata.is_some() || update.new_configuration.is_some();
+    Ok(changed.then_some(update))
+}
+
+/// The parameters set in `desired` that differ from `actual`, as an update of type `U`.
+fn changed_index_fields<T: Serialize, U: DeserializeOwned>(
+    collection: &str,
+    desired: &T,
+    actual: &Option<T>,
+) -> Result<Option<U>, KhromaError> {
+    let to_map = |value: serde_json::Value| match value {
+        serde_json::Value::Object(map) => map,
+        _ => serde_json::Map::new(),
+    };
+    let desired = serde_json::to_value(desired).map_err(|e| KhromaError::Parse(e.to_string()))?;
+    let actual = serde_json::to_value(actual).map_err(|e| KhromaError::Parse(e.to_string()))?;
+    let (desired, actual) = (to_map(desired), to_map(actual));
+
+    let mut changed = serde_json::Map::new();
+    for (field, value) in desired {
+        if actual.get(&field) == Some(&value) {
+            continue;
+        }
+        if IMMUTABLE_INDEX_FIELDS.contains(&field.as_str()) {
+            return Err(KhromaError::Validation(format!(
+                "Cannot change '{}' of collection '{}' after creation; recreate it instead",
+                field, collection
+            )));
+        }
+        changed.insert(field, value);
+    }
+    if changed.is_empty() {
+        return Ok(None);
+    }
+    serde_json::from_value(serde_json::Value::Object(changed))
+        .map(Some)
+        .map_err(|e| {
+            KhromaError::Validation(format!(
+                "Unsupported change to collection '{}': {}",
+                collection, e
+            ))
+        })
+}
+
+/// A handle on one tenant. Clones share the HTTP client and copy the name.
 #[derive(Debug, Clone)]
 pub struct Tenant {
     pub name: String,
@@ -236,6 +2030,11 @@ impl Tenant {
             .map(|d| Database::from(d, self.client.clone()))
     }
 
+    /// Whether a database named `name` exists in this tenant.
+    pub async fn database_exists(&self, name: &str) -> Result<bool, KhromaError> {
+        found(self.client.get_database(&self.name, name).await)
+    }
+
     pub async fn create_database(&self, name: &str) -> Result<Database, KhromaError> {
         let payload = models::CreateDatabasePayload {
             name: name.to_string(),
@@ -243,9 +2042,21 @@ impl Tenant {
         self.client.create_database(&self.name, &payload).await?;
         Ok(self.database(name))
     }
-    
+
+    /// Gets the database, creating it if it does not exist. A database created concurrently
+    /// by another client is returned as well.
+    pub async fn get_or_create_database(&self, name: &str) -> Result<Database, KhromaError> {
+        match self.get_database(name).await {
+            Err(e) if e.is_not_found() => match self.create_database(name).await {
+                Err(e) if e.is_conflict() => Ok(self.database(name)),
+                result => result,
+            },
+            result => result,
+        }
+    }
+
     pub async fn delete_database(&self, name: &str) -> Result<(), KhromaError> {
-        self.client.delete_database(&self.name, &name).await?;
+        self.client.delete_database(&self.name, name).await?;
         Ok(())
     }
 
@@ -262,20 +2073,156 @@ impl Tenant {
             .map(|i| Database::from(i, self.client.clone()))
             .collect())
     }
+
+    /// Lists `page_size` databases, starting where `token` left off.
+    pub async fn list_databases_page(
+        &self,
+        page_size: u32,
+        token: Option<&PageToken>,
+    ) -> Result<Page<Database>, KhromaError> {
+        let page_size = page_size.max(1);
+        let databases = self
+            .list_databases(
+                Some(page_size.min(i32::MAX as u32) as i32),
+                token.map(PageToken::offset),
+            )
+            .await?;
+        Ok(Page::new(databases, token, page_size))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CollectionGetResult {
+    pub collection: Collection,
+    pub response: models::GetResponse,
 }
 
+/// The hosted Chroma Cloud API.
+pub const CHROMA_CLOUD_URL: &str = "https://api.trychroma.com";
+
+/// The entry point to a Chroma server. Clones share the HTTP client and its connection
+/// pool, and copy the default tenant and database names.
 #[derive(Debug, Clone)]
 pub struct Khroma {
     client: Arc<KhromaClient>,
+    default_tenant: Option<String>,
+    default_database: Option<String>,
 }
 
 impl Khroma {
     pub fn new(base_url: &str, token: Option<String>) -> Result<Self, KhromaError> {
+        Ok(Self::from_client(KhromaClient::new(base_url, token)?))
+    }
+
+    /// Wraps a client configured through [`KhromaClient::builder`].
+    pub fn from_client(client: KhromaClient) -> Self {
+        Self {
+            client: Arc::new(client),
+            default_tenant: None,
+            default_database: None,
+        }
+    }
+
+    /// Runs the high-level API against `api` instead of a server, e.g. a
+    /// [`MockKhromaClient`](crate::mock::MockKhromaClient); see [`KhromaClient::from_api`].
+    pub fn from_api(api: impl ChromaApi + 'static) -> Self {
+        Self::from_client(KhromaClient::from_api(api))
+    }
+
+    /// A handle to `database` of `tenant` on Chroma Cloud, authenticated with `api_key`.
+    /// No request is made until the handle is used.
+    pub fn cloud(
+        api_key: impl Into<String>,
+        tenant: &str,
+        database: &str,
+    ) -> Result<Database, KhromaError> {
+        let client = KhromaClient::builder(CHROMA_CLOUD_URL)
+            .auth(Auth::TokenHeader(api_key.into()))
+            .build()?;
+        Ok(Self::from_client(client).tenant(tenant).database(database))
+    }
+
+    pub fn from_config(config: &KhromaConfig) -> Result<Self, KhromaError> {
         Ok(Self {
-            client: Arc::new(KhromaClient::new(base_url, token)?),
+            default_tenant: config.tenant.clone(),
+            default_database: config.database.clone(),
+            ..Self::from_client(config.build_client()?)
         })
     }
 
+    /// Include lists used by every collection handle obtained from this client,
+    /// for get and query requests that leave `include` unset.
+    pub fn with_default_includes(mut self, includes: models::DefaultIncludes) -> Self {
+        let mut client = (*self.client).clone();
+        client.set_default_includes(includes);
+        self.client = Arc::new(client);
+        self
+    }
+
+    /// Coalesces concurrent identical queries (same collection and payload) made through
+    /// this client into a single HTTP call whose response is shared.
+    pub fn with_query_coalescing(mut self, enabled: bool) -> Self {
+        let mut client = (*self.client).clone();
+        client.set_query_coalescing(enabled);
+        self.client = Arc::new(client);
+        self
+    }
+
+    /// Retries transient failures of idempotent requests according to `policy`.
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        let mut client = (*self.client).clone();
+        client.set_retry_policy(policy);
+        self.client = Arc::new(client);
+        self
+    }
+
+    /// Gives each attempt of a request `timeout` to complete, replacing the client's
+    /// [`timeout`](crate::KhromaClientBuilder::timeout) for this handle and those it creates.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        let mut client = (*self.client).clone();
+        client.set_request_timeout(timeout);
+        self.client = Arc::new(client);
+        self
+    }
+
+    /// Logs response fields that khroma's models do not match; see
+    /// [`KhromaClientBuilder::detect_schema_drift`](crate::KhromaClientBuilder::detect_schema_drift).
+    pub fn with_schema_drift_detection(mut self, enabled: bool) -> Self {
+        let mut client = (*self.client).clone();
+        client.set_schema_drift_detection(enabled);
+        self.client = Arc::new(client);
+        self
+    }
+
+    /// Sends idempotency keys with writes; see
+    /// [`KhromaClientBuilder::idempotency_keys`](crate::KhromaClientBuilder::idempotency_keys).
+    pub fn with_idempotency_keys(mut self, enabled: bool) -> Self {
+        let mut client = (*self.client).clone();
+        client.set_idempotency_keys(enabled);
+        self.client = Arc::new(client);
+        self
+    }
+
+    /// Zeroes request bodies after use; see
+    /// [`KhromaClientBuilder::zeroize_request_bodies`](crate::KhromaClientBuilder::zeroize_request_bodies).
+    pub fn with_zeroized_request_bodies(mut self, enabled: bool) -> Self {
+        let mut client = (*self.client).clone();
+        client.set_zeroize_request_bodies(enabled);
+        self.client = Arc::new(client);
+        self
+    }
+
+    /// The tenant named in the configuration, without checking that it exists.
+    pub fn default_tenant(&self) -> Option<Tenant> {
+        self.default_tenant.as_deref().map(|name| self.tenant(name))
+    }
+
+    /// The database named in the configuration, without checking that it exists.
+    pub fn default_database(&self) -> Option<Database> {
+        let database = self.default_database.as_deref()?;
+        self.default_tenant().map(|tenant| tenant.database(database))
+    }
+
     fn tenant(&self, name: &str) -> Tenant {
         Tenant {
             name: name.to_string(),
@@ -296,6 +2243,76 @@ impl Khroma {
         Ok(self.tenant(name))
     }
 
+    /// Whether a tenant named `name` exists.
+    pub async fn tenant_exists(&self, name: &str) -> Result<bool, KhromaError> {
+        found(self.client.get_tenant(name).await)
+    }
+
+    /// Gets or creates `tenant`, `database` inside it, and every collection in `collections`,
+    /// returning handles to the database and collections in the order given.
+    ///
+    /// Safe to call on every startup: existing resources are reused, and the configuration
+    /// and metadata of each payload only apply when that collection is created.
+    pub async fn ensure(
+        &self,
+        tenant: &str,
+        database: &str,
+        collections: &[models::CreateCollectionPayload],
+    ) -> Result<(Database, Vec<Collection>), KhromaError> {
+        let tenant = self.get_or_create_tenant(tenant).await?;
+        let database = tenant.get_or_create_database(database).await?;
+        let collections = futures::future::try_join_all(
+            collections
+                .iter()
+                .map(|payload| database.get_or_create_collection(payload.clone())),
+        )
+        .await?;
+        Ok((database, collections))
+    }
+
+    /// Gets the tenant, creating it if it does not exist. A tenant created concurrently by
+    /// another client is returned as well.
+    pub async fn get_or_create_tenant(&self, name: &str) -> Result<Tenant, KhromaError> {
+        match self.get_tenant(name).await {
+            Err(e) if e.is_not_found() => match self.create_tenant(name).await {
+                // Created concurrently by someone else.
+                Err(e) if e.is_conflict() => Ok(self.tenant(name)),
+                result => result,
+            },
+            result => result,
+        }
+    }
+
+    /// Starts a batch of gets, queries and counts that run concurrently.
+    pub fn pipeline(&self) -> Pipeline<'_> {
+        self.client.pipeline()
+    }
+
+    /// Gets the same ids from several collections concurrently, one result per collection.
+    pub async fn multi_get(
+        &self,
+        collections: &[Collection],
+        ids: &[String],
+        include: Option<models::IncludeList>,
+    ) -> Result<Vec<CollectionGetResult>, KhromaError> {
+        let payload = models::GetRequestPayload {
+            ids: Some(ids.to_vec()),
+            include,
+            ..Default::default()
+        };
+        futures::future::try_join_all(collections.iter().map(|collection| {
+            let payload = &payload;
+            async move {
+                let response = collection.get(payload).await?;
+                Ok::<_, KhromaError>(CollectionGetResult {
+                    collection: collection.clone(),
+                    response,
+                })
+            }
+        }))
+        .await
+    }
+
     pub async fn version(&self) -> Result<String, KhromaError> {
         self.client.version().await
     }
@@ -308,7 +2325,166 @@ impl Khroma {
         self.client.healthcheck().await
     }
 
+    /// Runs healthcheck and heartbeat concurrently. Failures are reported in the status
+    /// rather than as an error.
+    pub async fn health(&self) -> models::HealthStatus {
+        let started = std::time::Instant::now();
+        let (healthcheck, heartbeat) =
+            futures::join!(self.client.healthcheck(), self.client.heartbeat());
+        let error = match (&healthcheck, &heartbeat) {
+            (Err(e), _) | (_, Err(e)) => Some(e.to_string()),
+            _ => None,
+        };
+        models::HealthStatus {
+            healthy: healthcheck.is_ok(),
+            heartbeat: heartbeat.ok(),
+            error,
+            round_trip: started.elapsed(),
+        }
+    }
+
+    /// Waits for the server to come up, for startup races such as a service starting
+    /// alongside Chroma under docker-compose. Polls healthcheck and heartbeat, waiting
+    /// 100ms after the first failure and twice as long after each one after that, up to 2s.
+    ///
+    /// Returns the first successful heartbeat, or the last error once `timeout` has passed.
+    /// A probe still pending at that point is abandoned, failing with a
+    /// [`std::io::ErrorKind::TimedOut`] I/O error if no probe failed before it.
+    /// Authentication failures are returned right away, since waiting will not fix them.
+    pub async fn wait_until_ready(
+        &self,
+        timeout: Duration,
+    ) -> Result<models::HeartbeatResponse, KhromaError> {
+        let deadline = tokio::time::Instant::now() + timeout;
+        let mut delay = Duration::from_millis(100);
+        let mut last_error = None;
+        loop {
+            let probe = async {
+                self.client.healthcheck().await?;
+                self.client.heartbeat().await
+            };
+            let error = match tokio::time::timeout_at(deadline, probe).await {
+                Ok(Ok(heartbeat)) => return Ok(heartbeat),
+                Ok(Err(error @ KhromaError::Unauthorized { .. })) => return Err(error),
+                Ok(Err(error)) => error,
+                Err(_) => {
+                    return Err(last_error.unwrap_or_else(|| {
+                        KhromaError::Io(std::io::Error::new(
+                            std::io::ErrorKind::TimedOut,
+                            format!("Chroma did not answer within {:?}", timeout),
+                        ))
+                    }));
+                }
+            };
+            let remaining = deadline.saturating_duration_since(tokio::time::Instant::now());
+            if remaining.is_zero() {
+                return Err(error);
+            }
+            log::debug!("Chroma is not ready yet, retrying in {:?}: {}", delay, error);
+            last_error = Some(error);
+            tokio::time::sleep(delay.min(remaining)).await;
+            delay = (delay * 2).min(Duration::from_secs(2));
+        }
+    }
+
+    /// Checks connectivity, authentication and write permissions end to end: creates a
+    /// temporary collection in the default database, adds a record, queries it back and
+    /// deletes the collection. Failures are reported in the result rather than as an error.
+    ///
+    /// Uses the tenant and database from the configuration, or Chroma's `default_tenant`
+    /// and `default_database`.
+    pub async fn self_test(&self) -> models::SelfTestReport {
+        let tenant = self.default_tenant.as_deref().unwrap_or(CHROMA_DEFAULT_TENANT);
+        let database = self
+            .default_database
+            .as_deref()
+            .unwrap_or(CHROMA_DEFAULT_DATABASE);
+        let mut report = models::SelfTestReport {
+            tenant: tenant.to_string(),
+            database: database.to_string(),
+            steps: Vec::new(),
+        };
+        let database = self.tenant(tenant).database(database);
+
+        if !record_step(&mut report, models::SelfTestStep::Heartbeat, async {
+            self.client.heartbeat().await.map(|_| ())
+        })
+        .await
+        {
+            return report;
+        }
+
+        let name = format!("khroma-self-test-{}", uuid::Uuid::new_v4());
+        let mut collection = None;
+        record_step(&mut report, models::SelfTestStep::CreateCollection, async {
+            let payload = models::CreateCollectionPayload::new(name);
+            collection = Some(database.create_collection(&payload).await?);
+            Ok(())
+        })
+        .await;
+        let Some(collection) = collection else {
+            return report;
+        };
+
+        let record = models::Record::new("self-test")
+            .with_embedding(vec![1.0, 0.0, 0.0])
+            .with_document("khroma self-test");
+        let added = record_step(&mut report, models::SelfTestStep::Add, async {
+            collection.add_records(vec![record]).await
+        })
+        .await;
+        if added {
+            record_step(&mut report, models::SelfTestStep::Query, async {
+                let mut payload = models::QueryRequestPayload::new(vec![vec![1.0, 0.0, 0.0]]);
+                payload.n_results = Some(1);
+                let response = collection.query(&payload, None, None).await?;
+                match response.ids.first().and_then(|ids| ids.first()) {
+                    Some(id) if id == "self-test" => Ok(()),
+                    _ => Err(KhromaError::Parse(
+                        "the query did not return the record just added".to_string(),
+                    )),
+                }
+            })
+            .await;
+        }
+
+        record_step(&mut report, models::SelfTestStep::DeleteCollection, async {
+            database.delete_collection(collection.id).await
+        })
+        .await;
+        report
+    }
+
+    /// Wipes all data on the server. Fails unless the client was built with
+    /// [`KhromaClientBuilder::allow_reset`](crate::KhromaClientBuilder::allow_reset).
     pub async fn reset(&self) -> Result<bool, KhromaError> {
         self.client.reset().await
     }
+
+    /// Like [`Khroma::reset`], also checking that `confirmation` names the server being
+    /// reset as `host:port` (e.g. `"localhost:8000"`), so code copied from a test setup
+    /// cannot silently wipe a different deployment.
+    pub async fn reset_confirmed(&self, confirmation: &str) -> Result<bool, KhromaError> {
+        let url = self.client.base_url();
+        let expected = match (url.host_str(), url.port_or_known_default()) {
+            (Some(host), Some(port)) => format!("{}:{}", host, port),
+            (Some(host), None) => host.to_string(),
+            _ => url.to_string(),
+        };
+        if confirmation != expected {
+            return Err(KhromaError::ResetNotAllowed(format!(
+                "confirmation '{}' does not match server '{}'",
+                confirmation, expected
+            )));
+        }
+        self.client.reset().await
+    }
 }
+
+const _: () = {
+    const fn assert_send_sync_clone<T: Send + Sync + Clone>() {}
+    assert_send_sync_clone::<Khroma>();
+    assert_send_sync_clone::<Tenant>();
+    assert_send_sync_clone::<Database>();
+    assert_send_sync_clone::<Collection>();
+};
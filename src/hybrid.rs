@@ -0,0 +1,198 @@
+//! Merging a text-filtered query with a plain vector query into one ranking.
+
+use crate::models::QueryHit;
+use std::collections::HashMap;
+
+/// How [`Collection::hybrid_query`](crate::Collection::hybrid_query) merges the ranked
+/// lists of its vector and text-filtered queries.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub enum Fusion {
+    /// Reciprocal rank fusion: each record scores `weight / (k + rank)` summed over the
+    /// lists it appears in, with ranks starting at 1. Vector lists weigh 1 and
+    /// text-filtered lists `text_weight`. A larger `k` flattens the gap between ranks.
+    ReciprocalRank { k: f32, text_weight: f32 },
+    /// Records matching the text filter first, then the rest, each group ordered by
+    /// distance. Scores are 1 for text matches and 0 otherwise.
+    TextFirst,
+}
+
+impl Default for Fusion {
+    /// Reciprocal rank fusion with the customary `k` of 60 and equal weights.
+    fn default() -> Self {
+        Fusion::ReciprocalRank {
+            k: 60.0,
+            text_weight: 1.0,
+        }
+    }
+}
+
+/// One record of a hybrid query.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HybridHit {
+    /// The record, with its smallest distance to any of the query embeddings.
+    pub hit: QueryHit,
+    /// The fusion score. Higher ranks first.
+    pub score: f32,
+    /// Whether the record matched the text filter.
+    pub matched_text: bool,
+}
+
+/// Merges the per-embedding result lists of the vector and text-filtered queries into the
+/// best `n_results` records.
+pub(crate) fn fuse(
+    vector: Vec<Vec<QueryHit>>,
+    text: Vec<Vec<QueryHit>>,
+    n_results: usize,
+    fusion: Fusion,
+) -> Vec<HybridHit> {
+    let mut merged: HashMap<String, HybridHit> = HashMap::new();
+    let lists = vector
+        .into_iter()
+        .map(|list| (list, false))
+        .chain(text.into_iter().map(|list| (list, true)));
+    for (list, matched_text) in lists {
+        for (rank, hit) in list.into_iter().enumerate() {
+            let score = match fusion {
+                Fusion::ReciprocalRank { k, text_weight } => {
+                    let weight = if matched_text { text_weight } else { 1.0 };
+                    weight / (k + rank as f32 + 1.0)
+                }
+                Fusion::TextFirst => 0.0,
+            };
+            match merged.get_mut(&hit.id) {
+                Some(entry) => {
+                    entry.score += score;
+                    entry.matched_text |= matched_text;
+                    if closer(&hit, &entry.hit) {
+                        entry.hit = hit;
+                    }
+                }
+                None => {
+                    let entry = HybridHit {
+                        hit,
+                        score,
+                        matched_text,
+                    };
+                    merged.insert(entry.hit.id.clone(), entry);
+                }
+            }
+        }
+    }
+
+    let mut hits: Vec<HybridHit> = merged.into_values().collect();
+    if fusion == Fusion::TextFirst {
+        for hit in &mut hits {
+            hit.score = if hit.matched_text { 1.0 } else { 0.0 };
+        }
+    }
+    hits.sort_by(|a, b| {
+        b.score
+            .total_cmp(&a.score)
+            .then_with(|| distance(a).total_cmp(&distance(b)))
+            .then_with(|| a.hit.id.cmp(&b.hit.id))
+    });
+    hits.truncate(n_results);
+    hits
+}
+
+fn closer(candidate: &QueryHit, current: &QueryHit) -> bool {
+    match (candidate.distance, current.distance) {
+        (Some(candidate), Some(current)) => candidate < current,
+        (Some(_), None) => true,
+        _ => false,
+    }
+}
+
+fn distance(hit: &HybridHit) -> f32 {
+    hit.hit.distance.unwrap_or(f32::INFINITY)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hit(id: &str, distance: f32) -> QueryHit {
+        QueryHit {
+            id: id.to_string(),
+            distance: Some(distance),
+            document: None,
+            metadata: None,
+            embedding: None,
+            uri: None,
+        }
+    }
+
+    fn ids(hits: &[HybridHit]) -> Vec<&str> {
+        hits.iter().map(|hit| hit.hit.id.as_str()).collect()
+    }
+
+    #[test]
+    fn rrf_sums_weighted_reciprocal_ranks() {
+        let fusion = Fusion::ReciprocalRank {
+            k: 60.0,
+            text_weight: 2.0,
+        };
+        let vector = vec![vec![hit("a", 0.1), hit("b", 0.2)]];
+        let text = vec![vec![hit("b", 0.2), hit("c", 0.3)]];
+        let hits = fuse(vector, text, 10, fusion);
+
+        assert_eq!(ids(&hits), ["b", "c", "a"]);
+        assert_eq!(hits[0].score, 1.0 / 62.0 + 2.0 / 61.0);
+        assert_eq!(hits[1].score, 2.0 / 62.0);
+        assert_eq!(hits[2].score, 1.0 / 61.0);
+        assert!(hits[0].matched_text && hits[1].matched_text && !hits[2].matched_text);
+    }
+
+    #[test]
+    fn rrf_keeps_smallest_distance_across_lists() {
+        let vector = vec![vec![hit("a", 0.4)], vec![hit("a", 0.1)]];
+        let hits = fuse(vector, Vec::new(), 10, Fusion::default());
+
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].hit.distance, Some(0.1));
+        assert_eq!(hits[0].score, 2.0 / 61.0);
+    }
+
+    #[test]
+    fn ties_break_by_distance_then_id() {
+        let vector = vec![
+            vec![hit("b", 0.5)],
+            vec![hit("a", 0.5)],
+            vec![hit("c", 0.2)],
+        ];
+        let hits = fuse(vector, Vec::new(), 10, Fusion::default());
+
+        assert!(hits.iter().all(|hit| hit.score == hits[0].score));
+        assert_eq!(ids(&hits), ["c", "a", "b"]);
+    }
+
+    #[test]
+    fn missing_distance_ranks_after_known_ones() {
+        let mut unknown = hit("a", 0.0);
+        unknown.distance = None;
+        let vector = vec![vec![unknown], vec![hit("b", 0.9)]];
+        let hits = fuse(vector, Vec::new(), 10, Fusion::default());
+
+        assert_eq!(ids(&hits), ["b", "a"]);
+    }
+
+    #[test]
+    fn text_first_orders_matches_before_distance() {
+        let vector = vec![vec![hit("near", 0.1), hit("far", 0.9)]];
+        let text = vec![vec![hit("far", 0.9)]];
+        let hits = fuse(vector, text, 10, Fusion::TextFirst);
+
+        assert_eq!(ids(&hits), ["far", "near"]);
+        assert_eq!(hits[0].score, 1.0);
+        assert_eq!(hits[1].score, 0.0);
+    }
+
+    #[test]
+    fn truncates_to_n_results() {
+        let vector = vec![vec![hit("a", 0.1), hit("b", 0.2), hit("c", 0.3)]];
+        let hits = fuse(vector, Vec::new(), 2, Fusion::default());
+
+        assert_eq!(ids(&hits), ["a", "b"]);
+    }
+}
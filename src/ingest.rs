@@ -0,0 +1,638 @@
+//! Loading large numbers of records into a collection.
+
+#[cfg(feature = "parquet")]
+mod parquet;
+
+use crate::compression;
+use crate::error::KhromaError;
+use crate::high_level::Collection;
+use crate::models::{Metadata, Record};
+use futures::future::Either;
+use futures::stream::FuturesUnordered;
+use futures::{Stream, StreamExt};
+use serde::ser::SerializeStruct;
+use serde::{Deserialize, Serialize, Serializer};
+use serde_json::Value;
+use std::io::{BufRead, BufReader, Read, Seek, Write};
+use std::time::{Duration, Instant};
+
+/// Progress of [`Collection::add_batched_with_progress`] or
+/// [`Collection::upsert_batched_with_progress`], reported after each batch is written.
+#[derive(Debug, Clone, Copy)]
+pub struct ProgressEvent {
+    pub records_sent: usize,
+    pub records_total: usize,
+    pub batches_sent: usize,
+    pub batches_remaining: usize,
+    pub elapsed: Duration,
+}
+
+/// A batch that could not be written. Serializes `error` as its message.
+#[derive(Debug, Serialize)]
+pub struct BatchFailure {
+    pub ids: Vec<String>,
+    #[serde(serialize_with = "serialize_display")]
+    pub error: KhromaError,
+}
+
+/// Progress of a [`BulkLoader::load`], reported after every batch and returned at the end.
+///
+/// Serializes with `elapsed` as `elapsed_secs` and with `records_per_second`, for run logs
+/// and dashboards.
+#[derive(Debug, Default)]
+pub struct IngestStats {
+    pub records_written: u64,
+    pub batches_written: u64,
+    pub records_failed: u64,
+    pub failures: Vec<BatchFailure>,
+    pub elapsed: Duration,
+    /// How many batches may be in flight, after adapting to the server with
+    /// [`BulkLoader::adaptive`].
+    pub concurrency: usize,
+}
+
+impl IngestStats {
+    /// Records written per second so far.
+    pub fn records_per_second(&self) -> f64 {
+        per_second(self.records_written, self.elapsed)
+    }
+}
+
+impl Serialize for IngestStats {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("IngestStats", 7)?;
+        state.serialize_field("records_written", &self.records_written)?;
+        state.serialize_field("batches_written", &self.batches_written)?;
+        state.serialize_field("records_failed", &self.records_failed)?;
+        state.serialize_field("failures", &self.failures)?;
+        state.serialize_field("elapsed_secs", &self.elapsed.as_secs_f64())?;
+        state.serialize_field("records_per_second", &self.records_per_second())?;
+        state.serialize_field("concurrency", &self.concurrency)?;
+        state.end()
+    }
+}
+
+/// `records` over `elapsed`, or zero before any time has passed.
+pub(crate) fn per_second(records: u64, elapsed: Duration) -> f64 {
+    let secs = elapsed.as_secs_f64();
+    if secs > 0.0 {
+        records as f64 / secs
+    } else {
+        0.0
+    }
+}
+
+fn serialize_display<T: std::fmt::Display, S: Serializer>(
+    value: &T,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(value)
+}
+
+/// Writes a stream of records to a collection in batches, with several batches in flight.
+///
+/// A failed batch is recorded in [`IngestStats::failures`] and loading carries on; batches
+/// already written stay written.
+#[derive(Debug, Clone)]
+pub struct BulkLoader {
+    collection: Collection,
+    batch_size: Option<usize>,
+    concurrency: usize,
+    latency_target: Option<Duration>,
+    upsert: bool,
+}
+
+impl BulkLoader {
+    pub fn new(collection: &Collection) -> Self {
+        Self {
+            collection: collection.clone(),
+            batch_size: None,
+            concurrency: 4,
+            latency_target: None,
+            upsert: false,
+        }
+    }
+
+    /// Records per request. The server's `max_batch_size` by default.
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = Some(batch_size.max(1));
+        self
+    }
+
+    /// Limits how many batches are in flight at once. Four by default.
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Adapts how many batches are in flight to the server's feedback, the way TCP does:
+    /// the limit is halved when a batch fails with a retryable error, such as 429 or 503, or
+    /// takes longer than `latency_target`, and grows by one after as many batches as the
+    /// limit succeed in time, up to [`concurrency`](Self::concurrency).
+    pub fn adaptive(mut self, latency_target: Duration) -> Self {
+        self.latency_target = Some(latency_target);
+        self
+    }
+
+    /// Upserts instead of adding, so a load can be rerun after a partial failure.
+    pub fn upsert(mut self, enabled: bool) -> Self {
+        self.upsert = enabled;
+        self
+    }
+
+    pub async fn load(
+        &self,
+        records: impl Stream<Item = Record>,
+    ) -> Result<IngestStats, KhromaError> {
+        self.load_with_progress(records, |_| {}).await
+    }
+
+    /// Like [`load`](Self::load), calling `on_progress` after each batch completes.
+    pub async fn load_with_progress(
+        &self,
+        records: impl Stream<Item = Record>,
+        mut on_progress: impl FnMut(&IngestStats),
+    ) -> Result<IngestStats, KhromaError> {
+        let batch_size = match self.batch_size {
+            Some(batch_size) => batch_size,
+            None => self.collection.max_batch_size().await?,
+        };
+        let started = Instant::now();
+        let mut throttle = Throttle::new(self.concurrency, self.latency_target);
+        let mut stats = IngestStats {
+            concurrency: throttle.limit,
+            ..Default::default()
+        };
+        let mut chunks = std::pin::pin!(records.chunks(batch_size));
+        let mut in_flight = FuturesUnordered::new();
+        let mut exhausted = false;
+        loop {
+            let step = if !exhausted && in_flight.len() < throttle.limit {
+                if in_flight.is_empty() {
+                    Either::Left(chunks.next().await)
+                } else {
+                    match futures::future::select(chunks.next(), in_flight.next()).await {
+                        Either::Left((batch, _)) => Either::Left(batch),
+                        Either::Right((written, _)) => Either::Right(written),
+                    }
+                }
+            } else {
+                Either::Right(in_flight.next().await)
+            };
+            let (epoch, latency, ids, result) = match step {
+                Either::Left(Some(batch)) => {
+                    let epoch = throttle.epoch;
+                    let write = self.write(batch);
+                    in_flight.push(async move {
+                        let (latency, ids, result) = write.await;
+                        (epoch, latency, ids, result)
+                    });
+                    continue;
+                }
+                Either::Left(None) => {
+                    exhausted = true;
+                    continue;
+                }
+                Either::Right(Some(written)) => written,
+                Either::Right(None) => break,
+            };
+            throttle.record(epoch, latency, &result);
+            match result {
+                Ok(()) => {
+                    stats.records_written += ids.len() as u64;
+                    stats.batches_written += 1;
+                }
+                Err(error) => {
+                    stats.records_failed += ids.len() as u64;
+                    stats.failures.push(BatchFailure { ids, error });
+                }
+            }
+            stats.elapsed = started.elapsed();
+            stats.concurrency = throttle.limit;
+            on_progress(&stats);
+        }
+        stats.elapsed = started.elapsed();
+        Ok(stats)
+    }
+
+    async fn write(&self, batch: Vec<Record>) -> (Duration, Vec<String>, Result<(), KhromaError>) {
+        let started = Instant::now();
+        let ids = batch.iter().map(|record| record.id.clone()).collect();
+        let result = if self.upsert {
+            self.collection.upsert_records(batch).await
+        } else {
+            self.collection.add_records(batch).await
+        };
+        (started.elapsed(), ids, result)
+    }
+}
+
+/// The additive-increase, multiplicative-decrease limit on batches in flight.
+#[derive(Debug)]
+struct Throttle {
+    limit: usize,
+    max: usize,
+    latency_target: Option<Duration>,
+    /// Batches finished in time since the limit last changed.
+    on_time: usize,
+    /// Bumped on every decrease, so the batches already in flight when the limit was cut
+    /// don't cut it again.
+    epoch: u64,
+}
+
+impl Throttle {
+    fn new(max: usize, latency_target: Option<Duration>) -> Self {
+        Self {
+            limit: max,
+            max,
+            latency_target,
+            on_time: 0,
+            epoch: 0,
+        }
+    }
+
+    fn record(&mut self, epoch: u64, latency: Duration, result: &Result<(), KhromaError>) {
+        let Some(latency_target) = self.latency_target else {
+            return;
+        };
+        let congested = match result {
+            Ok(()) => latency > latency_target,
+            Err(e) => e.is_retryable(),
+        };
+        if congested {
+            if epoch == self.epoch {
+                self.limit = (self.limit / 2).max(1);
+                self.on_time = 0;
+                self.epoch += 1;
+            }
+        } else if result.is_ok() {
+            self.on_time += 1;
+            if self.on_time >= self.limit {
+                self.limit = (self.limit + 1).min(self.max);
+                self.on_time = 0;
+            }
+        }
+    }
+}
+
+/// The file formats [`Collection::import`] reads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportFormat {
+    /// One JSON object per line with `id` and optionally `embedding`, `document`,
+    /// `metadata` and `uri`. Blank lines are skipped. Files compressed with gzip or zstd,
+    /// such as those written by [`Collection::export`], are recognized by their first
+    /// bytes and decompressed with the matching feature.
+    JsonLines,
+    /// A Parquet file with an `id` string column and optionally an `embedding` list of
+    /// floats, `document` and `uri` strings, and `metadata` holding a JSON object string.
+    #[cfg(feature = "parquet")]
+    Parquet,
+}
+
+/// The outcome of [`Collection::import`].
+///
+/// Serializes with `error` as its message, `elapsed` as `elapsed_secs` and with
+/// `records_per_second`.
+#[derive(Debug)]
+pub struct ImportReport {
+    /// Records upserted by this call.
+    pub imported: u64,
+    /// The offset of the first record not yet imported, to resume from with
+    /// [`Collection::import_from`] after a failure.
+    pub next_offset: u64,
+    /// Why the import stopped early, if it did.
+    pub error: Option<KhromaError>,
+    pub elapsed: Duration,
+}
+
+impl ImportReport {
+    pub fn is_complete(&self) -> bool {
+        self.error.is_none()
+    }
+
+    /// Records imported per second.
+    pub fn records_per_second(&self) -> f64 {
+        per_second(self.imported, self.elapsed)
+    }
+}
+
+impl Serialize for ImportReport {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("ImportReport", 5)?;
+        state.serialize_field("imported", &self.imported)?;
+        state.serialize_field("next_offset", &self.next_offset)?;
+        state.serialize_field("error", &self.error.as_ref().map(ToString::to_string))?;
+        state.serialize_field("elapsed_secs", &self.elapsed.as_secs_f64())?;
+        state.serialize_field("records_per_second", &self.records_per_second())?;
+        state.end()
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct JsonRecord {
+    id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    embedding: Option<Vec<f32>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    document: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    metadata: Option<Metadata>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    uri: Option<String>,
+}
+
+impl From<Record> for JsonRecord {
+    fn from(record: Record) -> Self {
+        JsonRecord {
+            id: record.id,
+            embedding: record.embedding,
+            document: record.document,
+            metadata: record.metadata,
+            uri: record.uri,
+        }
+    }
+}
+
+impl From<JsonRecord> for Record {
+    fn from(record: JsonRecord) -> Self {
+        Record {
+            id: record.id,
+            embedding: record.embedding,
+            document: record.document,
+            metadata: record.metadata,
+            uri: record.uri,
+        }
+    }
+}
+
+/// Records read from an import file, in order.
+pub(crate) type Records = Box<dyn Iterator<Item = Result<Record, KhromaError>> + Send>;
+
+/// The records in `reader`, starting at record `offset`. Reads lazily, so the iterator
+/// blocks on file IO and belongs on a blocking thread, like this call.
+pub(crate) fn read_records(
+    reader: impl Read + Seek + Send + 'static,
+    format: ImportFormat,
+    offset: u64,
+) -> Result<Records, KhromaError> {
+    match format {
+        ImportFormat::JsonLines => {
+            let lines = compression::decoder(BufReader::new(reader))?
+                .lines()
+                .enumerate()
+                .filter(|(_, line)| !line.as_ref().is_ok_and(|line| line.trim().is_empty()))
+                .skip(offset as usize)
+                .map(|(number, line)| {
+                    let line = line.map_err(|e| KhromaError::Parse(e.to_string()))?;
+                    serde_json::from_str::<JsonRecord>(&line)
+                        .map(Record::from)
+                        .map_err(|e| KhromaError::Parse(format!("Line {}: {}", number + 1, e)))
+                });
+            Ok(Box::new(lines))
+        }
+        #[cfg(feature = "parquet")]
+        ImportFormat::Parquet => Ok(Box::new(parquet::read(reader)?.skip(offset as usize))),
+    }
+}
+
+/// Writes `records` as [`ImportFormat::JsonLines`], returning the writer so it can be
+/// moved to a blocking thread and back.
+pub(crate) fn write_records<W: Write>(
+    mut writer: W,
+    records: Vec<Record>,
+) -> Result<W, KhromaError> {
+    for record in records {
+        serde_json::to_writer(&mut writer, &JsonRecord::from(record))
+            .map_err(std::io::Error::from)?;
+        writer.write_all(b"\n")?;
+    }
+    Ok(writer)
+}
+
+/// Reads up to `count` records, stopping after the first error. Returns the iterator with
+/// the records so it can be moved to a blocking thread and back.
+pub(crate) fn read_batch(
+    mut records: Records,
+    count: usize,
+) -> (Records, Vec<Result<Record, KhromaError>>) {
+    let mut batch = Vec::with_capacity(count);
+    for record in records.by_ref() {
+        let failed = record.is_err();
+        batch.push(record);
+        if failed || batch.len() == count {
+            break;
+        }
+    }
+    (records, batch)
+}
+
+/// Runs blocking file IO on the runtime's blocking threads.
+pub(crate) async fn unblock<T: Send + 'static>(
+    f: impl FnOnce() -> T + Send + 'static,
+) -> Result<T, KhromaError> {
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(|e| KhromaError::Io(std::io::Error::other(e)))
+}
+
+/// Checks what the server would reject, so a bad record stops the import at its offset
+/// instead of failing a whole batch.
+pub(crate) fn validate(record: &Record, dimension: &mut Option<usize>) -> Result<(), KhromaError> {
+    if record.id.is_empty() {
+        return Err(KhromaError::Validation("Record has an empty id".to_string()));
+    }
+    if let Some(embedding) = &record.embedding {
+        if embedding.iter().any(|x| !x.is_finite()) {
+            return Err(KhromaError::Validation(format!(
+                "Record '{}' has a non-finite embedding component",
+                record.id
+            )));
+        }
+        let expected = *dimension.get_or_insert(embedding.len());
+        if embedding.len() != expected {
+            return Err(KhromaError::Validation(format!(
+                "Record '{}' has {} embedding components, expected {}",
+                record.id,
+                embedding.len(),
+                expected
+            )));
+        }
+    }
+    for (key, value) in record.metadata.iter().flatten() {
+        if !matches!(value, Value::String(_) | Value::Number(_) | Value::Bool(_)) {
+            return Err(KhromaError::Validation(format!(
+                "Record '{}' has metadata '{}' that is not a string, number or boolean",
+                record.id, key
+            )));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::StatusCode;
+
+    const TARGET: Duration = Duration::from_millis(100);
+
+    fn unavailable() -> Result<(), KhromaError> {
+        Err(KhromaError::from_status(
+            StatusCode::SERVICE_UNAVAILABLE,
+            None,
+            "busy".to_string(),
+            None,
+        ))
+    }
+
+    #[test]
+    fn throttle_halves_on_retryable_errors_and_slow_batches() {
+        let mut throttle = Throttle::new(8, Some(TARGET));
+        throttle.record(0, Duration::from_millis(10), &unavailable());
+        assert_eq!(throttle.limit, 4);
+        throttle.record(1, TARGET * 2, &Ok(()));
+        assert_eq!(throttle.limit, 2);
+        throttle.record(2, TARGET * 2, &Ok(()));
+        throttle.record(3, TARGET * 2, &Ok(()));
+        assert_eq!(throttle.limit, 1);
+    }
+
+    #[test]
+    fn throttle_ignores_batches_sent_before_a_decrease() {
+        let mut throttle = Throttle::new(8, Some(TARGET));
+        throttle.record(0, Duration::ZERO, &unavailable());
+        throttle.record(0, Duration::ZERO, &unavailable());
+        throttle.record(0, TARGET * 2, &Ok(()));
+        assert_eq!(throttle.limit, 4);
+        assert_eq!(throttle.epoch, 1);
+    }
+
+    #[test]
+    fn throttle_keeps_the_limit_on_other_errors() {
+        let mut throttle = Throttle::new(8, Some(TARGET));
+        let invalid = Err(KhromaError::Validation("bad record".to_string()));
+        throttle.record(0, Duration::ZERO, &invalid);
+        assert_eq!(throttle.limit, 8);
+        assert_eq!(throttle.on_time, 0);
+    }
+
+    #[test]
+    fn throttle_grows_by_one_per_limit_batches_up_to_the_max() {
+        let mut throttle = Throttle::new(4, Some(TARGET));
+        throttle.record(0, TARGET * 2, &Ok(()));
+        assert_eq!(throttle.limit, 2);
+        throttle.record(1, Duration::ZERO, &Ok(()));
+        assert_eq!(throttle.limit, 2);
+        throttle.record(1, Duration::ZERO, &Ok(()));
+        assert_eq!(throttle.limit, 3);
+        for _ in 0..3 {
+            throttle.record(1, Duration::ZERO, &Ok(()));
+        }
+        assert_eq!(throttle.limit, 4);
+        for _ in 0..8 {
+            throttle.record(1, Duration::ZERO, &Ok(()));
+        }
+        assert_eq!(throttle.limit, 4);
+    }
+
+    #[test]
+    fn throttle_without_a_target_is_fixed() {
+        let mut throttle = Throttle::new(4, None);
+        throttle.record(0, Duration::from_secs(60), &unavailable());
+        assert_eq!(throttle.limit, 4);
+    }
+
+    #[cfg(feature = "mock")]
+    mod loader {
+        use super::*;
+        use crate::high_level::Khroma;
+        use crate::mock::MockKhromaClient;
+        use crate::models::CreateCollectionPayload;
+
+        fn block_on<F: std::future::Future>(future: F) -> F::Output {
+            tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .unwrap()
+                .block_on(future)
+        }
+
+        async fn collection() -> Collection {
+            Khroma::from_api(MockKhromaClient::new())
+                .get_tenant("default_tenant")
+                .await
+                .unwrap()
+                .get_database("default_database")
+                .await
+                .unwrap()
+                .create_collection(&CreateCollectionPayload::new("docs"))
+                .await
+                .unwrap()
+        }
+
+        fn records(range: std::ops::Range<usize>, dimension: usize) -> Vec<Record> {
+            range
+                .map(|i| Record::new(format!("r{}", i)).with_embedding(vec![i as f32; dimension]))
+                .collect()
+        }
+
+        #[test]
+        fn loads_every_record_in_batches() {
+            block_on(async {
+                let collection = collection().await;
+                let mut reported = Vec::new();
+                let stats = BulkLoader::new(&collection)
+                    .batch_size(10)
+                    .concurrency(3)
+                    .load_with_progress(futures::stream::iter(records(0..95, 2)), |stats| {
+                        reported.push(stats.records_written)
+                    })
+                    .await
+                    .unwrap();
+                assert_eq!(stats.records_written, 95);
+                assert_eq!(stats.batches_written, 10);
+                assert_eq!(stats.concurrency, 3);
+                assert_eq!(reported.len(), 10);
+                assert_eq!(collection.count().await.unwrap(), 95);
+            });
+        }
+
+        #[test]
+        fn failed_batches_are_reported_and_loading_continues() {
+            block_on(async {
+                let collection = collection().await;
+                let mut batch = records(0..10, 2);
+                batch.extend(records(10..20, 3));
+                batch.extend(records(20..30, 2));
+                let stats = BulkLoader::new(&collection)
+                    .batch_size(10)
+                    .concurrency(1)
+                    .load(futures::stream::iter(batch))
+                    .await
+                    .unwrap();
+                assert_eq!(stats.records_written, 20);
+                assert_eq!(stats.records_failed, 10);
+                assert_eq!(stats.failures.len(), 1);
+                assert_eq!(stats.failures[0].ids[0], "r10");
+                assert_eq!(collection.count().await.unwrap(), 20);
+            });
+        }
+
+        #[test]
+        fn adaptive_loading_backs_off_from_slow_batches() {
+            block_on(async {
+                let collection = collection().await;
+                let stats = BulkLoader::new(&collection)
+                    .batch_size(5)
+                    .concurrency(8)
+                    .adaptive(Duration::ZERO)
+                    .load(futures::stream::iter(records(0..100, 2)))
+                    .await
+                    .unwrap();
+                assert_eq!(stats.records_written, 100);
+                assert_eq!(stats.concurrency, 1);
+            });
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use crate::error::KhromaError;
+use crate::models::Record;
+use bytes::Bytes;
+use parquet::file::reader::{ChunkReader, Length, SerializedFileReader};
+use parquet::record::reader::RowIter;
+use parquet::record::{Field, Row};
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::sync::{Arc, Mutex};
+
+/// The records of a Parquet file, one per row. Row groups are read as the iterator reaches
+/// them, so only the footer and the current row group are held in memory.
+pub(super) fn read(
+    reader: impl Read + Seek + Send + 'static,
+) -> Result<impl Iterator<Item = Result<Record, KhromaError>>, KhromaError> {
+    let reader = SerializedFileReader::new(Source::new(reader)?).map_err(parse_error)?;
+    let rows = RowIter::from_file_into(Box::new(reader));
+    Ok(rows
+        .enumerate()
+        .map(|(number, row)| record(&row.map_err(parse_error)?).map_err(|e| at_row(number, e))))
+}
+
+/// A seekable reader shared by the readers Parquet opens at different offsets, the way
+/// `File::try_clone` shares a file descriptor.
+struct Source<R> {
+    inner: Arc<Mutex<R>>,
+    len: u64,
+}
+
+impl<R: Seek> Source<R> {
+    fn new(mut reader: R) -> Result<Self, KhromaError> {
+        let len = reader.seek(SeekFrom::End(0))?;
+        Ok(Self {
+            inner: Arc::new(Mutex::new(reader)),
+            len,
+        })
+    }
+}
+
+impl<R> Length for Source<R> {
+    fn len(&self) -> u64 {
+        self.len
+    }
+}
+
+impl<R: Read + Seek + Send> ChunkReader for Source<R> {
+    type T = BufReader<SourceReader<R>>;
+
+    fn get_read(&self, start: u64) -> parquet::errors::Result<Self::T> {
+        Ok(BufReader::new(SourceReader {
+            inner: self.inner.clone(),
+            position: start,
+        }))
+    }
+
+    fn get_bytes(&self, start: u64, length: usize) -> parquet::errors::Result<Bytes> {
+        let mut buffer = vec![0; length];
+        let mut inner = lock(&self.inner)?;
+        inner.seek(SeekFrom::Start(start))?;
+        inner.read_exact(&mut buffer)?;
+        Ok(buffer.into())
+    }
+}
+
+struct SourceReader<R> {
+    inner: Arc<Mutex<R>>,
+    position: u64,
+}
+
+impl<R: Read + Seek> Read for SourceReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut inner = lock(&self.inner)?;
+        inner.seek(SeekFrom::Start(self.position))?;
+        let read = inner.read(buf)?;
+        self.position += read as u64;
+        Ok(read)
+    }
+}
+
+fn lock<R>(inner: &Mutex<R>) -> io::Result<std::sync::MutexGuard<'_, R>> {
+    inner
+        .lock()
+        .map_err(|_| io::Error::other("Parquet reader poisoned by a panic"))
+}
+
+fn record(row: &Row) -> Result<Record, KhromaError> {
+    let mut record = Record::default();
+    for (name, field) in row.get_column_iter() {
+        match (name.as_str(), field) {
+            (_, Field::Null) => {}
+            ("id", Field::Str(id)) => record.id = id.clone(),
+            ("document", Field::Str(document)) => record.document = Some(document.clone()),
+            ("uri", Field::Str(uri)) => record.uri = Some(uri.clone()),
+            ("metadata", Field::Str(metadata)) => {
+                record.metadata = Some(
+                    serde_json::from_str(metadata)
+                        .map_err(|e| KhromaError::Parse(format!("metadata: {}", e)))?,
+                );
+            }
+            ("embedding", Field::ListInternal(list)) => {
+                let embedding = list
+                    .elements()
+                    .iter()
+                    .map(|element| match element {
+                        Field::Float(x) => Ok(*x),
+                        Field::Double(x) => Ok(*x as f32),
+                        other => Err(KhromaError::Parse(format!("embedding component {}", other))),
+                    })
+                    .collect::<Result<_, _>>()?;
+                record.embedding = Some(embedding);
+            }
+            ("id" | "document" | "uri" | "metadata" | "embedding", other) => {
+                return Err(KhromaError::Parse(format!(
+                    "unexpected value {} in column '{}'",
+                    other, name
+                )));
+            }
+            _ => {}
+        }
+    }
+    Ok(record)
+}
+
+fn parse_error(error: parquet::errors::ParquetError) -> KhromaError {
+    KhromaError::Parse(error.to_string())
+}
+
+fn at_row(number: usize, error: KhromaError) -> KhromaError {
+    KhromaError::Parse(format!("Row {}: {}", number + 1, error))
+}
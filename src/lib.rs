@@ -1,8 +1,51 @@
+mod api;
+mod auth;
 mod client;
+mod compression;
+mod config;
+mod drift;
 mod error;
+mod filter;
+mod retry;
+#[cfg(any(feature = "aws-secretsmanager", feature = "s3-store"))]
+mod sigv4;
+mod singleflight;
+mod zeroize;
 
+pub mod blob;
+pub mod canonical;
+pub mod chunking;
+#[cfg(feature = "blocking")]
+pub mod blocking;
+pub mod degradation;
+pub mod document_store;
+pub mod embedding;
+pub mod global;
 pub mod high_level;
+pub mod hybrid;
+pub mod ingest;
+pub mod metadata;
+pub mod middleware;
+pub mod migrate;
+#[cfg(feature = "mock")]
+pub mod mock;
+pub mod page;
+pub mod partition;
+pub mod pipeline;
+pub mod priority;
+pub mod quality;
+pub mod read_cache;
+pub mod read_only;
+pub mod rerank;
+pub mod secrets;
 pub mod models;
+pub mod observer;
 
+pub use api::ChromaApi;
+pub use auth::Auth;
+pub use client::{KhromaClient, KhromaClientBuilder};
+pub use compression::Compression;
+pub use config::KhromaConfig;
+pub use retry::RetryPolicy;
 pub use error::*;
 pub use high_level::*;
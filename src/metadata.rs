@@ -0,0 +1,334 @@
+use crate::error::KhromaError;
+use crate::models::Metadata;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+
+/// A single metadata value.
+///
+/// Chroma stores strings, integers, floats and booleans. Anything else, such as a nested
+/// object or an array, is kept unchanged in `Other`, so converting a server's metadata
+/// to `MetadataValue` and back never loses data. [`MetadataValue::is_valid`] and
+/// [`MetadataExt::validate`] flag the values Chroma would reject.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum MetadataValue {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Str(String),
+    Other(Value),
+}
+
+impl MetadataValue {
+    /// Whether Chroma accepts this value: everything but `Other`.
+    pub fn is_valid(&self) -> bool {
+        !matches!(self, MetadataValue::Other(_))
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            MetadataValue::Str(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            MetadataValue::Int(i) => Some(*i),
+            _ => None,
+        }
+    }
+
+    /// Integers convert too, as Chroma compares them with floats.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            MetadataValue::Float(f) => Some(*f),
+            MetadataValue::Int(i) => Some(*i as f64),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            MetadataValue::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+}
+
+impl From<Value> for MetadataValue {
+    fn from(value: Value) -> Self {
+        match value {
+            Value::Bool(b) => MetadataValue::Bool(b),
+            Value::String(s) => MetadataValue::Str(s),
+            Value::Number(n) => match (n.as_i64(), n.as_f64()) {
+                (Some(i), _) => MetadataValue::Int(i),
+                // u64 values above i64::MAX stay as they are rather than losing precision.
+                (None, Some(f)) if !n.is_u64() => MetadataValue::Float(f),
+                _ => MetadataValue::Other(Value::Number(n)),
+            },
+            other => MetadataValue::Other(other),
+        }
+    }
+}
+
+impl From<MetadataValue> for Value {
+    fn from(value: MetadataValue) -> Self {
+        match value {
+            MetadataValue::Bool(b) => Value::Bool(b),
+            MetadataValue::Int(i) => Value::from(i),
+            MetadataValue::Float(f) => Value::from(f),
+            MetadataValue::Str(s) => Value::String(s),
+            MetadataValue::Other(v) => v,
+        }
+    }
+}
+
+impl From<&str> for MetadataValue {
+    fn from(value: &str) -> Self {
+        MetadataValue::Str(value.to_string())
+    }
+}
+
+impl From<String> for MetadataValue {
+    fn from(value: String) -> Self {
+        MetadataValue::Str(value)
+    }
+}
+
+impl From<bool> for MetadataValue {
+    fn from(value: bool) -> Self {
+        MetadataValue::Bool(value)
+    }
+}
+
+impl From<i32> for MetadataValue {
+    fn from(value: i32) -> Self {
+        MetadataValue::Int(value.into())
+    }
+}
+
+impl From<i64> for MetadataValue {
+    fn from(value: i64) -> Self {
+        MetadataValue::Int(value)
+    }
+}
+
+impl From<f32> for MetadataValue {
+    fn from(value: f32) -> Self {
+        MetadataValue::Float(value.into())
+    }
+}
+
+impl From<f64> for MetadataValue {
+    fn from(value: f64) -> Self {
+        MetadataValue::Float(value)
+    }
+}
+
+impl fmt::Display for MetadataValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MetadataValue::Bool(b) => write!(f, "{}", b),
+            MetadataValue::Int(i) => write!(f, "{}", i),
+            MetadataValue::Float(x) => write!(f, "{}", x),
+            MetadataValue::Str(s) => f.write_str(s),
+            MetadataValue::Other(v) => write!(f, "{}", v),
+        }
+    }
+}
+
+/// Converts a Rust type to and from a Chroma-compatible metadata primitive
+/// (string, integer, float or boolean).
+pub trait MetadataCodec: Sized {
+    fn encode(&self) -> Value;
+
+    fn decode(value: &Value) -> Result<Self, KhromaError>;
+}
+
+fn mismatch(expected: &str, value: &Value) -> KhromaError {
+    KhromaError::Parse(format!("Expected {} metadata value, got {}", expected, value))
+}
+
+impl MetadataCodec for String {
+    fn encode(&self) -> Value {
+        Value::String(self.clone())
+    }
+
+    fn decode(value: &Value) -> Result<Self, KhromaError> {
+        value
+            .as_str()
+            .map(str::to_string)
+            .ok_or_else(|| mismatch("string", value))
+    }
+}
+
+impl MetadataCodec for bool {
+    fn encode(&self) -> Value {
+        Value::Bool(*self)
+    }
+
+    fn decode(value: &Value) -> Result<Self, KhromaError> {
+        value.as_bool().ok_or_else(|| mismatch("boolean", value))
+    }
+}
+
+impl MetadataCodec for i64 {
+    fn encode(&self) -> Value {
+        Value::from(*self)
+    }
+
+    fn decode(value: &Value) -> Result<Self, KhromaError> {
+        value.as_i64().ok_or_else(|| mismatch("integer", value))
+    }
+}
+
+impl MetadataCodec for f64 {
+    fn encode(&self) -> Value {
+        Value::from(*self)
+    }
+
+    fn decode(value: &Value) -> Result<Self, KhromaError> {
+        value.as_f64().ok_or_else(|| mismatch("float", value))
+    }
+}
+
+impl MetadataCodec for MetadataValue {
+    fn encode(&self) -> Value {
+        self.clone().into()
+    }
+
+    fn decode(value: &Value) -> Result<Self, KhromaError> {
+        Ok(value.clone().into())
+    }
+}
+
+/// Stored as the hyphenated string form.
+impl MetadataCodec for uuid::Uuid {
+    fn encode(&self) -> Value {
+        Value::String(self.to_string())
+    }
+
+    fn decode(value: &Value) -> Result<Self, KhromaError> {
+        let s = value.as_str().ok_or_else(|| mismatch("UUID string", value))?;
+        uuid::Uuid::parse_str(s).map_err(|e| KhromaError::Parse(e.to_string()))
+    }
+}
+
+/// Stored as integer milliseconds since the Unix epoch, so range filters work.
+#[cfg(feature = "chrono")]
+impl MetadataCodec for chrono::DateTime<chrono::Utc> {
+    fn encode(&self) -> Value {
+        Value::from(self.timestamp_millis())
+    }
+
+    fn decode(value: &Value) -> Result<Self, KhromaError> {
+        let millis = value
+            .as_i64()
+            .ok_or_else(|| mismatch("timestamp integer", value))?;
+        chrono::DateTime::from_timestamp_millis(millis)
+            .ok_or_else(|| KhromaError::Parse(format!("Timestamp out of range: {}", millis)))
+    }
+}
+
+/// Stored as an ISO 8601 `YYYY-MM-DD` string.
+#[cfg(feature = "chrono")]
+impl MetadataCodec for chrono::NaiveDate {
+    fn encode(&self) -> Value {
+        Value::String(self.format("%Y-%m-%d").to_string())
+    }
+
+    fn decode(value: &Value) -> Result<Self, KhromaError> {
+        let s = value.as_str().ok_or_else(|| mismatch("date string", value))?;
+        chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d").map_err(|e| KhromaError::Parse(e.to_string()))
+    }
+}
+
+/// Stored as a string to keep full precision.
+#[cfg(feature = "rust_decimal")]
+impl MetadataCodec for rust_decimal::Decimal {
+    fn encode(&self) -> Value {
+        Value::String(self.to_string())
+    }
+
+    fn decode(value: &Value) -> Result<Self, KhromaError> {
+        let s = value.as_str().ok_or_else(|| mismatch("decimal string", value))?;
+        s.parse().map_err(|e: rust_decimal::Error| KhromaError::Parse(e.to_string()))
+    }
+}
+
+/// Typed access to `Metadata` maps through `MetadataCodec`.
+pub trait MetadataExt {
+    fn insert_typed<T: MetadataCodec>(&mut self, key: impl Into<String>, value: &T);
+
+    /// Returns `Ok(None)` when the key is absent and an error when it cannot be decoded.
+    fn get_typed<T: MetadataCodec>(&self, key: &str) -> Result<Option<T>, KhromaError>;
+
+    /// Fails on the first value Chroma would reject, naming its key. `null`, which metadata
+    /// updates use to remove a key, counts as rejected here.
+    fn validate(&self) -> Result<(), KhromaError>;
+}
+
+impl MetadataExt for Metadata {
+    fn insert_typed<T: MetadataCodec>(&mut self, key: impl Into<String>, value: &T) {
+        self.insert(key.into(), value.encode());
+    }
+
+    fn get_typed<T: MetadataCodec>(&self, key: &str) -> Result<Option<T>, KhromaError> {
+        self.get(key).map(T::decode).transpose()
+    }
+
+    fn validate(&self) -> Result<(), KhromaError> {
+        for (key, value) in self {
+            if !MetadataValue::from(value.clone()).is_valid() {
+                return Err(KhromaError::Validation(format!(
+                    "Metadata value for {:?} must be a string, number or boolean, got {}",
+                    key, value
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Stores a struct as record metadata, one key per field.
+///
+/// Implemented for every `Serialize` type that serializes to a map. Fields holding
+/// `None` are left out, and fields Chroma cannot store, such as nested structs or
+/// vectors, fail with [`KhromaError::Validation`] naming the field.
+pub trait ToMetadata {
+    fn to_metadata(&self) -> Result<Metadata, KhromaError>;
+}
+
+impl<T: Serialize + ?Sized> ToMetadata for T {
+    fn to_metadata(&self) -> Result<Metadata, KhromaError> {
+        let value = serde_json::to_value(self).map_err(|e| KhromaError::Validation(e.to_string()))?;
+        let Value::Object(map) = value else {
+            return Err(KhromaError::Validation(format!(
+                "Metadata must serialize to a map, got {}",
+                value
+            )));
+        };
+        let metadata: Metadata = map.into_iter().filter(|(_, value)| !value.is_null()).collect();
+        metadata.validate()?;
+        Ok(metadata)
+    }
+}
+
+/// Reads a struct back from record metadata written with [`ToMetadata`].
+///
+/// Implemented for every `DeserializeOwned` type. Keys missing from the metadata read
+/// as `None` for `Option` fields; keys the type doesn't know are ignored unless it
+/// denies unknown fields.
+pub trait FromMetadata: Sized {
+    fn from_metadata(metadata: &Metadata) -> Result<Self, KhromaError>;
+}
+
+impl<T: DeserializeOwned> FromMetadata for T {
+    fn from_metadata(metadata: &Metadata) -> Result<Self, KhromaError> {
+        let map = metadata.iter().map(|(key, value)| (key.clone(), value.clone())).collect();
+        serde_json::from_value(Value::Object(map)).map_err(|e| KhromaError::Parse(e.to_string()))
+    }
+}
@@ -0,0 +1,87 @@
+//! Hooks around every HTTP request a client sends, for signing, request IDs, audit logs
+//! or metrics.
+
+use crate::error::KhromaError;
+use async_trait::async_trait;
+use reqwest::{Method, Request, Response};
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Inspects or changes the requests a client sends and the responses it receives.
+///
+/// Attach with [`KhromaClientBuilder::middleware`](crate::KhromaClientBuilder::middleware).
+/// Requests pass through middleware in the order it was added and responses in the
+/// reverse order. An error from either hook fails the call with that error.
+#[async_trait]
+pub trait Middleware: Send + Sync {
+    /// Called once per call with the finished request, auth headers included, before it
+    /// is sent. Retries resend the request as changed here.
+    async fn on_request(&self, request: &mut Request) -> Result<(), KhromaError> {
+        let _ = request;
+        Ok(())
+    }
+
+    /// Called with the final response of a call, error statuses included, before it is
+    /// parsed. `elapsed` covers every attempt and the waits between them.
+    async fn on_response(
+        &self,
+        method: &Method,
+        elapsed: Duration,
+        response: &mut Response,
+    ) -> Result<(), KhromaError> {
+        let _ = (method, elapsed, response);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<M: Middleware + ?Sized> Middleware for Arc<M> {
+    async fn on_request(&self, request: &mut Request) -> Result<(), KhromaError> {
+        (**self).on_request(request).await
+    }
+
+    async fn on_response(
+        &self,
+        method: &Method,
+        elapsed: Duration,
+        response: &mut Response,
+    ) -> Result<(), KhromaError> {
+        (**self).on_response(method, elapsed, response).await
+    }
+}
+
+/// The middleware attached to a client, in the order it was added.
+#[derive(Clone, Default)]
+pub(crate) struct Middlewares(Vec<Arc<dyn Middleware>>);
+
+impl Middlewares {
+    pub(crate) fn push(&mut self, middleware: impl Middleware + 'static) {
+        self.0.push(Arc::new(middleware));
+    }
+
+    pub(crate) async fn on_request(&self, request: &mut Request) -> Result<(), KhromaError> {
+        for middleware in &self.0 {
+            middleware.on_request(request).await?;
+        }
+        Ok(())
+    }
+
+    pub(crate) async fn on_response(
+        &self,
+        method: &Method,
+        elapsed: Duration,
+        response: &mut Response,
+    ) -> Result<(), KhromaError> {
+        for middleware in self.0.iter().rev() {
+            middleware.on_response(method, elapsed, response).await?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Middlewares {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Middlewares({})", self.0.len())
+    }
+}
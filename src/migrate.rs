@@ -0,0 +1,143 @@
+//! Copying records from one collection to another, on the same server or a different one.
+
+use crate::error::KhromaError;
+use crate::high_level::Collection;
+use crate::models::{GetRequestPayload, Include, RawWhereFields, UpsertCollectionRecordsPayload};
+use futures::TryStreamExt;
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+use std::time::{Duration, Instant};
+
+/// How [`copy_collection`] reads and writes.
+#[derive(Debug, Clone)]
+pub struct CopyOptions {
+    page_size: u32,
+    concurrency: usize,
+    filter: RawWhereFields,
+    start_offset: u32,
+}
+
+impl Default for CopyOptions {
+    fn default() -> Self {
+        Self {
+            page_size: 1000,
+            concurrency: 4,
+            filter: RawWhereFields::default(),
+            start_offset: 0,
+        }
+    }
+}
+
+impl CopyOptions {
+    /// Records read from the source per request. 1000 by default.
+    pub fn page_size(mut self, page_size: u32) -> Self {
+        self.page_size = page_size.max(1);
+        self
+    }
+
+    /// Limits how many write batches of one page are in flight at once. Four by default.
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Copies only the records matching `filter`'s `where` and `where_document` clauses.
+    pub fn filter(mut self, filter: RawWhereFields) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Skips the first `offset` source records, to resume from [`CopyStats::next_offset`].
+    pub fn start_offset(mut self, offset: u32) -> Self {
+        self.start_offset = offset;
+        self
+    }
+}
+
+/// What [`copy_collection`] copied.
+///
+/// Serializes with `elapsed` as `elapsed_secs` and with `records_per_second`.
+#[derive(Debug, Clone, Default)]
+pub struct CopyStats {
+    pub records_copied: u64,
+    pub pages_copied: u64,
+    /// The offset of the first source record not yet copied.
+    pub next_offset: u32,
+    pub elapsed: Duration,
+}
+
+impl CopyStats {
+    /// Records copied per second so far.
+    pub fn records_per_second(&self) -> f64 {
+        crate::ingest::per_second(self.records_copied, self.elapsed)
+    }
+}
+
+impl Serialize for CopyStats {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("CopyStats", 5)?;
+        state.serialize_field("records_copied", &self.records_copied)?;
+        state.serialize_field("pages_copied", &self.pages_copied)?;
+        state.serialize_field("next_offset", &self.next_offset)?;
+        state.serialize_field("elapsed_secs", &self.elapsed.as_secs_f64())?;
+        state.serialize_field("records_per_second", &self.records_per_second())?;
+        state.end()
+    }
+}
+
+/// Copies the records of `src` into `dst`, keeping their ids, embeddings, documents,
+/// metadata and URIs.
+///
+/// The handles may come from different clients, for instance a local server and Chroma
+/// Cloud. Records are read a page at a time and upserted, so an interrupted copy can be
+/// rerun, or resumed by passing the last progress report's `next_offset` to
+/// [`CopyOptions::start_offset`]. Writes to `src` during the copy may shift its pages and
+/// cause records to be skipped or copied twice.
+pub async fn copy_collection(
+    src: &Collection,
+    dst: &Collection,
+    options: &CopyOptions,
+) -> Result<CopyStats, KhromaError> {
+    copy_collection_with_progress(src, dst, options, |_| {}).await
+}
+
+/// Like [`copy_collection`], calling `on_progress` after each page is written.
+pub async fn copy_collection_with_progress(
+    src: &Collection,
+    dst: &Collection,
+    options: &CopyOptions,
+    mut on_progress: impl FnMut(&CopyStats),
+) -> Result<CopyStats, KhromaError> {
+    if let (Some(from), Some(to)) = (src.dimension(), dst.dimension())
+        && from != to
+    {
+        return Err(KhromaError::Validation(format!(
+            "Cannot copy {}-dimensional embeddings into a {}-dimensional collection",
+            from, to
+        )));
+    }
+    let started = Instant::now();
+    let mut stats = CopyStats {
+        next_offset: options.start_offset,
+        ..Default::default()
+    };
+    let payload = GetRequestPayload {
+        where_fields: options.filter.clone(),
+        offset: Some(options.start_offset.min(i32::MAX as u32) as i32),
+        ..Default::default()
+    }
+    .with_include(Include::ALL);
+    let mut pages = std::pin::pin!(src.get_paginated(&payload, options.page_size));
+    while let Some(page) = pages.try_next().await? {
+        let records = page.into_records()?;
+        let count = records.len();
+        let payload = UpsertCollectionRecordsPayload::from_records(records)?;
+        dst.upsert_batched(&payload, options.concurrency).await?;
+        stats.records_copied += count as u64;
+        stats.pages_copied += 1;
+        stats.next_offset = stats.next_offset.saturating_add(count as u32);
+        stats.elapsed = started.elapsed();
+        on_progress(&stats);
+    }
+    stats.elapsed = started.elapsed();
+    Ok(stats)
+}
@@ -0,0 +1,1176 @@
+//! An in-memory stand-in for [`KhromaClient`](crate::KhromaClient), for testing code that
+//! talks to Chroma without running a server.
+
+use crate::error::KhromaError;
+use crate::filter::{matches_where, matches_where_document};
+use crate::models::*;
+use reqwest::StatusCode;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{SystemTime, UNIX_EPOCH};
+use uuid::Uuid;
+
+const DEFAULT_TENANT: &str = "default_tenant";
+const DEFAULT_DATABASE: &str = "default_database";
+
+/// A client with the endpoint methods of [`KhromaClient`](crate::KhromaClient), backed by
+/// in-memory maps instead of a server.
+///
+/// Queries rank every matching record by brute force, using the distance of the
+/// collection's configured space (squared L2 unless set). `where` and `where_document`
+/// filters are evaluated with Chroma's semantics. Embeddings must be sent with the
+/// records: the mock has no server-side embedding function.
+///
+/// Like a fresh server it starts with `default_tenant` and `default_database`. Clones
+/// share the same data.
+#[derive(Debug, Clone)]
+pub struct MockKhromaClient {
+    state: Arc<Mutex<State>>,
+    max_batch_size: i32,
+}
+
+#[derive(Debug)]
+struct State {
+    tenants: HashMap<String, HashMap<String, MockDatabase>>,
+}
+
+#[derive(Debug)]
+struct MockDatabase {
+    id: Uuid,
+    collections: Vec<MockCollection>,
+}
+
+#[derive(Debug, Clone)]
+struct MockCollection {
+    model: Collection,
+    records: Vec<Record>,
+}
+
+impl Default for MockKhromaClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Default for State {
+    fn default() -> Self {
+        let mut databases = HashMap::new();
+        databases.insert(DEFAULT_DATABASE.to_string(), MockDatabase::new());
+        let mut tenants = HashMap::new();
+        tenants.insert(DEFAULT_TENANT.to_string(), databases);
+        Self { tenants }
+    }
+}
+
+impl MockDatabase {
+    fn new() -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            collections: Vec::new(),
+        }
+    }
+}
+
+impl MockKhromaClient {
+    pub fn new() -> Self {
+        Self {
+            state: Arc::default(),
+            max_batch_size: 1000,
+        }
+    }
+
+    /// Sets the batch size reported by the pre-flight checks, and enforced on writes.
+    pub fn with_max_batch_size(mut self, max_batch_size: i32) -> Self {
+        self.max_batch_size = max_batch_size.max(1);
+        self
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap()
+    }
+
+    pub async fn get_user_identity(&self) -> Result<GetUserIdentityResponse, KhromaError> {
+        let state = self.state();
+        Ok(GetUserIdentityResponse {
+            user_id: String::new(),
+            tenant: DEFAULT_TENANT.to_string(),
+            databases: state
+                .tenants
+                .get(DEFAULT_TENANT)
+                .map(|databases| databases.keys().cloned().collect())
+                .unwrap_or_default(),
+        })
+    }
+
+    pub async fn healthcheck(&self) -> Result<String, KhromaError> {
+        Ok(r#"{"is_executor_ready":true,"is_log_client_ready":true}"#.to_string())
+    }
+
+    pub async fn heartbeat(&self) -> Result<HeartbeatResponse, KhromaError> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        Ok(HeartbeatResponse {
+            nanosecond_heartbeat: now.as_nanos() as u64,
+        })
+    }
+
+    pub async fn pre_flight_checks(&self) -> Result<ChecklistResponse, KhromaError> {
+        Ok(ChecklistResponse {
+            max_batch_size: self.max_batch_size,
+            supports_base64_encoding: false,
+        })
+    }
+
+    pub async fn cached_pre_flight_checks(&self) -> Result<ChecklistResponse, KhromaError> {
+        self.pre_flight_checks().await
+    }
+
+    /// Drops every tenant, database and collection, leaving only the defaults.
+    pub async fn reset(&self) -> Result<bool, KhromaError> {
+        *self.state() = State::default();
+        Ok(true)
+    }
+
+    pub async fn version(&self) -> Result<String, KhromaError> {
+        Ok(format!("khroma-mock-{}", env!("CARGO_PKG_VERSION")))
+    }
+
+    pub async fn create_tenant(
+        &self,
+        payload: &CreateTenantPayload,
+    ) -> Result<CreateTenantResponse, KhromaError> {
+        let mut state = self.state();
+        if state.tenants.contains_key(&payload.name) {
+            return Err(conflict(format!(
+                "Tenant [{}] already exists",
+                payload.name
+            )));
+        }
+        state.tenants.insert(payload.name.clone(), HashMap::new());
+        Ok(CreateTenantResponse {})
+    }
+
+    pub async fn get_tenant(&self, tenant_name: &str) -> Result<GetTenantResponse, KhromaError> {
+        self.state().tenant(tenant_name)?;
+        Ok(GetTenantResponse {
+            name: tenant_name.to_string(),
+        })
+    }
+
+    pub async fn list_databases(
+        &self,
+        tenant: &str,
+        limit: Option<i32>,
+        offset: Option<i32>,
+    ) -> Result<Vec<Database>, KhromaError> {
+        let mut state = self.state();
+        let mut databases: Vec<Database> = state
+            .tenant(tenant)?
+            .iter()
+            .map(|(name, database)| database_model(tenant, name, database))
+            .collect();
+        databases.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(window(databases, limit, offset))
+    }
+
+    pub async fn create_database(
+        &self,
+        tenant: &str,
+        payload: &CreateDatabasePayload,
+    ) -> Result<CreateDatabaseResponse, KhromaError> {
+        let mut state = self.state();
+        let databases = state.tenant(tenant)?;
+        if databases.contains_key(&payload.name) {
+            return Err(conflict(format!(
+                "Database [{}] already exists",
+                payload.name
+            )));
+        }
+        databases.insert(payload.name.clone(), MockDatabase::new());
+        Ok(CreateDatabaseResponse {})
+    }
+
+    pub async fn get_database(
+        &self,
+        tenant: &str,
+        database: &str,
+    ) -> Result<Database, KhromaError> {
+        let mut state = self.state();
+        let found = state.database(tenant, database)?;
+        Ok(database_model(tenant, database, found))
+    }
+
+    pub async fn delete_database(
+        &self,
+        tenant: &str,
+        database: &str,
+    ) -> Result<DeleteDatabaseResponse, KhromaError> {
+        let mut state = self.state();
+        state
+            .tenant(tenant)?
+            .remove(database)
+            .ok_or_else(|| not_found(format!("Database [{}] does not exist", database)))?;
+        Ok(DeleteDatabaseResponse {})
+    }
+
+    pub async fn list_collections(
+        &self,
+        tenant: &str,
+        database: &str,
+        limit: Option<i32>,
+        offset: Option<i32>,
+    ) -> Result<Vec<Collection>, KhromaError> {
+        let mut state = self.state();
+        let collections = state
+            .database(tenant, database)?
+            .collections
+            .iter()
+            .map(|collection| collection.model.clone())
+            .collect();
+        Ok(window(collections, limit, offset))
+    }
+
+    pub async fn create_collection(
+        &self,
+        tenant: &str,
+        database: &str,
+        payload: &CreateCollectionPayload,
+    ) -> Result<Collection, KhromaError> {
+        if let Some(configuration) = &payload.configuration {
+            configuration.index()?;
+        }
+        let mut state = self.state();
+        let found = state.database(tenant, database)?;
+        if let Some(existing) = found
+            .collections
+            .iter()
+            .find(|c| c.model.name == payload.name)
+        {
+            if payload.get_or_create == Some(true) {
+                return Ok(existing.model.clone());
+            }
+            return Err(conflict(format!(
+                "Collection [{}] already exists",
+                payload.name
+            )));
+        }
+        let model = Collection {
+            id: Uuid::new_v4(),
+            name: payload.name.clone(),
+            metadata: payload.metadata.clone(),
+            configuration_json: payload.configuration.clone().unwrap_or_default(),
+            tenant: tenant.to_string(),
+            database: database.to_string(),
+            log_position: 0,
+            version: 0,
+            dimension: None,
+        };
+        found.collections.push(MockCollection {
+            model: model.clone(),
+            records: Vec::new(),
+        });
+        Ok(model)
+    }
+
+    /// Looks the collection up by UUID, or by name like the server does.
+    pub async fn get_collection(
+        &self,
+        tenant: &str,
+        database: &str,
+        collection_id: &str,
+    ) -> Result<Collection, KhromaError> {
+        let mut state = self.state();
+        Ok(state
+            .collection(tenant, database, collection_id)?
+            .model
+            .clone())
+    }
+
+    pub async fn update_collection(
+        &self,
+        tenant: &str,
+        database: &str,
+        collection_id: &str,
+        payload: &UpdateCollectionPayload,
+    ) -> Result<UpdateCollectionResponse, KhromaError> {
+        let mut state = self.state();
+        let found = state.database(tenant, database)?;
+        if let Some(new_name) = &payload.new_name
+            && found
+                .collections
+                .iter()
+                .any(|c| &c.model.name == new_name && !is_ref(c, collection_id))
+        {
+            return Err(conflict(format!(
+                "Collection [{}] already exists",
+                new_name
+            )));
+        }
+        let collection = find(&mut found.collections, collection_id)?;
+        if let Some(new_name) = &payload.new_name {
+            collection.model.name = new_name.clone();
+        }
+        if let Some(new_metadata) = &payload.new_metadata {
+            collection.model.metadata = Some(new_metadata.clone());
+        }
+        if let Some(new_configuration) = &payload.new_configuration {
+            apply_configuration(&mut collection.model.configuration_json, new_configuration);
+        }
+        collection.model.version += 1;
+        Ok(UpdateCollectionResponse {})
+    }
+
+    pub async fn delete_collection(
+        &self,
+        tenant: &str,
+        database: &str,
+        collection_id: &str,
+    ) -> Result<UpdateCollectionResponse, KhromaError> {
+        let mut state = self.state();
+        let found = state.database(tenant, database)?;
+        let index = found
+            .collections
+            .iter()
+            .position(|c| is_ref(c, collection_id))
+            .ok_or_else(|| collection_not_found(collection_id))?;
+        found.collections.remove(index);
+        Ok(UpdateCollectionResponse {})
+    }
+
+    /// Adds the records whose ids are not in the collection yet, ignoring the others as
+    /// the server does. An id repeated within the payload is a conflict.
+    pub async fn collection_add(
+        &self,
+        tenant: &str,
+        database: &str,
+        collection_id: &str,
+        payload: &AddCollectionRecordsPayload,
+    ) -> Result<AddCollectionRecordsResponse, KhromaError> {
+        let records = self.records(
+            &payload.ids,
+            payload.embeddings.as_ref(),
+            &payload.metadatas,
+            &payload.documents,
+            &payload.uris,
+        )?;
+        let mut state = self.state();
+        let collection = state.collection(tenant, database, collection_id)?;
+        collection.check_dimension(&records)?;
+        for record in records {
+            if collection.position(&record.id).is_none() {
+                collection.records.push(record);
+            }
+        }
+        collection.model.log_position += 1;
+        Ok(AddCollectionRecordsResponse {})
+    }
+
+    pub async fn collection_count(
+        &self,
+        tenant: &str,
+        database: &str,
+        collection_id: &str,
+    ) -> Result<u32, KhromaError> {
+        let mut state = self.state();
+        Ok(state
+            .collection(tenant, database, collection_id)?
+            .records
+            .len() as u32)
+    }
+
+    pub async fn collection_delete(
+        &self,
+        tenant: &str,
+        database: &str,
+        collection_id: &str,
+        payload: &DeleteCollectionRecordsPayload,
+    ) -> Result<DeleteCollectionRecordsResponse, KhromaError> {
+        let mut state = self.state();
+        let collection = state.collection(tenant, database, collection_id)?;
+        let mut matched = Vec::with_capacity(collection.records.len());
+        for record in &collection.records {
+            matched.push(matches(
+                record,
+                payload.ids.as_deref(),
+                &payload.where_fields,
+            )?);
+        }
+        let mut matched = matched.into_iter();
+        collection
+            .records
+            .retain(|_| !matched.next().unwrap_or(false));
+        collection.model.log_position += 1;
+        Ok(DeleteCollectionRecordsResponse {})
+    }
+
+    pub async fn fork_collection(
+        &self,
+        tenant: &str,
+        database: &str,
+        collection_id: &str,
+        payload: &ForkCollectionPayload,
+    ) -> Result<Collection, KhromaError> {
+        let mut state = self.state();
+        let found = state.database(tenant, database)?;
+        if found
+            .collections
+            .iter()
+            .any(|c| c.model.name == payload.new_name)
+        {
+            return Err(conflict(format!(
+                "Collection [{}] already exists",
+                payload.new_name
+            )));
+        }
+        let mut fork = find(&mut found.collections, collection_id)?.clone();
+        fork.model.id = Uuid::new_v4();
+        fork.model.name = payload.new_name.clone();
+        let model = fork.model.clone();
+        found.collections.push(fork);
+        Ok(model)
+    }
+
+    pub async fn collection_get(
+        &self,
+        tenant: &str,
+        database: &str,
+        collection_id: &str,
+        payload: &GetRequestPayload,
+    ) -> Result<GetResponse, KhromaError> {
+        let mut state = self.state();
+        let collection = state.collection(tenant, database, collection_id)?;
+        let mut found = Vec::new();
+        for record in &collection.records {
+            if matches(record, payload.ids.as_deref(), &payload.where_fields)? {
+                found.push(record);
+            }
+        }
+        let offset = payload.offset.unwrap_or(0).max(0) as usize;
+        let limit = payload
+            .limit
+            .map_or(usize::MAX, |limit| limit.max(0) as usize);
+        let found: Vec<&Record> = found.into_iter().skip(offset).take(limit).collect();
+        let include = payload
+            .include
+            .clone()
+            .unwrap_or_else(|| vec![Include::Metadatas, Include::Documents]);
+        let has = |field: Include| include.contains(&field);
+        Ok(GetResponse {
+            ids: found.iter().map(|r| r.id.clone()).collect(),
+            metadatas: has(Include::Metadatas)
+                .then(|| found.iter().map(|r| r.metadata.clone()).collect()),
+            documents: has(Include::Documents)
+                .then(|| found.iter().map(|r| r.document.clone()).collect()),
+            uris: has(Include::Uris).then(|| found.iter().map(|r| r.uri.clone()).collect()),
+            embeddings: has(Include::Embeddings).then(|| {
+                found
+                    .iter()
+                    .map(|r| r.embedding.clone().unwrap_or_default())
+                    .collect()
+            }),
+            include,
+        })
+    }
+
+    pub async fn collection_get_arena(
+        &self,
+        tenant: &str,
+        database: &str,
+        collection_id: &str,
+        payload: &GetRequestPayload,
+    ) -> Result<ArenaGetResponse, KhromaError> {
+        let response = self
+            .collection_get(tenant, database, collection_id, payload)
+            .await?;
+        Ok(GetResponse {
+            ids: response.ids,
+            include: response.include,
+            metadatas: response.metadatas,
+            documents: response.documents,
+            uris: response.uris,
+            embeddings: response
+                .embeddings
+                .map(|embeddings| arena(embeddings.iter().map(Vec::as_slice))),
+        })
+    }
+
+    /// Ranks the matching records by distance to each query embedding. `limit` and
+    /// `offset` are accepted for parity and ignored, as by the server.
+    pub async fn collection_query(
+        &self,
+        tenant: &str,
+        database: &str,
+        collection_id: &str,
+        limit: Option<i32>,
+        offset: Option<i32>,
+        payload: &QueryRequestPayload,
+    ) -> Result<QueryResponse, KhromaError> {
+        let _ = (limit, offset);
+        let mut state = self.state();
+        let collection = state.collection(tenant, database, collection_id)?;
+        let space = collection.space();
+        let mut candidates = Vec::new();
+        for record in &collection.records {
+            if matches(record, payload.ids.as_deref(), &payload.where_fields)?
+                && let Some(embedding) = &record.embedding
+            {
+                candidates.push((record, embedding));
+            }
+        }
+        let n_results = payload.n_results.unwrap_or(10).max(0) as usize;
+        let include = payload
+            .include
+            .clone()
+            .unwrap_or_else(|| vec![Include::Metadatas, Include::Documents, Include::Distances]);
+        let has = |field: Include| include.contains(&field);
+        let mut hits = Vec::with_capacity(payload.query_embeddings.len());
+        for query in &payload.query_embeddings {
+            if let Some(dimension) = collection.model.dimension
+                && query.len() != dimension as usize
+            {
+                return Err(invalid_argument(format!(
+                    "Collection expecting embedding with dimension of {}, got {}",
+                    dimension,
+                    query.len()
+                )));
+            }
+            let mut ranked: Vec<(f32, &Record)> = candidates
+                .iter()
+                .map(|(record, embedding)| (distance(&space, query, embedding), *record))
+                .collect();
+            ranked.sort_by(|a, b| a.0.total_cmp(&b.0));
+            ranked.truncate(n_results);
+            hits.push(ranked);
+        }
+        Ok(QueryResponse {
+            ids: per_hit(&hits, |_, r| r.id.clone()),
+            distances: has(Include::Distances).then(|| per_hit(&hits, |d, _| Some(d))),
+            metadatas: has(Include::Metadatas).then(|| per_hit(&hits, |_, r| r.metadata.clone())),
+            documents: has(Include::Documents).then(|| per_hit(&hits, |_, r| r.document.clone())),
+            uris: has(Include::Uris).then(|| per_hit(&hits, |_, r| r.uri.clone())),
+            embeddings: has(Include::Embeddings).then(|| {
+                per_hit(&hits, |_, r| {
+                    r.embedding.iter().flatten().copied().map(Some).collect()
+                })
+            }),
+            include,
+        })
+    }
+
+    pub async fn collection_query_arena(
+        &self,
+        tenant: &str,
+        database: &str,
+        collection_id: &str,
+        limit: Option<i32>,
+        offset: Option<i32>,
+        payload: &QueryRequestPayload,
+    ) -> Result<ArenaQueryResponse, KhromaError> {
+        let response = self
+            .collection_query(tenant, database, collection_id, limit, offset, payload)
+            .await?;
+        Ok(QueryResponse {
+            ids: response.ids,
+            include: response.include,
+            distances: response.distances,
+            metadatas: response.metadatas,
+            documents: response.documents,
+            uris: response.uris,
+            embeddings: response.embeddings.map(|queries| {
+                queries
+                    .iter()
+                    .map(|embeddings| {
+                        let embeddings: Vec<Vec<f32>> = embeddings
+                            .iter()
+                            .map(|e| e.iter().map(|v| v.unwrap_or(f32::NAN)).collect())
+                            .collect();
+                        arena(embeddings.iter().map(Vec::as_slice))
+                    })
+                    .collect()
+            }),
+        })
+    }
+
+    /// Changes the records that exist; ids not in the collection are ignored. Metadata is
+    /// merged key by key, and a `null` value removes the key.
+    pub async fn collection_update(
+        &self,
+        tenant: &str,
+        database: &str,
+        collection_id: &str,
+        payload: &UpdateCollectionRecordsPayload,
+    ) -> Result<UpdateCollectionRecordsResponse, KhromaError> {
+        let ids = payload.ids.len();
+        let embeddings = match &payload.embeddings {
+            None => None,
+            Some(UpdateEmbeddingsPayload::Float(embeddings)) => Some(embeddings),
+            Some(UpdateEmbeddingsPayload::String(_)) => return Err(base64_unsupported()),
+        };
+        check_len("embeddings", embeddings.map(Vec::len), ids)?;
+        check_len("metadatas", payload.metadatas.as_ref().map(Vec::len), ids)?;
+        check_len("documents", payload.documents.as_ref().map(Vec::len), ids)?;
+        check_len("uris", payload.uris.as_ref().map(Vec::len), ids)?;
+        let mut state = self.state();
+        let collection = state.collection(tenant, database, collection_id)?;
+        let changes: Vec<Record> = (0..ids)
+            .map(|i| Record {
+                id: payload.ids[i].clone(),
+                embedding: embeddings.and_then(|e| e[i].clone()),
+                metadata: payload.metadatas.as_ref().and_then(|m| m[i].clone()),
+                document: payload.documents.as_ref().and_then(|d| d[i].clone()),
+                uri: payload.uris.as_ref().and_then(|u| u[i].clone()),
+            })
+            .collect();
+        collection.check_dimension(&changes)?;
+        for change in changes {
+            if let Some(index) = collection.position(&change.id) {
+                merge(&mut collection.records[index], change);
+            }
+        }
+        collection.model.log_position += 1;
+        Ok(UpdateCollectionRecordsResponse {})
+    }
+
+    /// Adds the new records and updates the existing ones like
+    /// [`collection_update`](Self::collection_update).
+    pub async fn collection_upsert(
+        &self,
+        tenant: &str,
+        database: &str,
+        collection_id: &str,
+        payload: &UpsertCollectionRecordsPayload,
+    ) -> Result<UpsertCollectionRecordsResponse, KhromaError> {
+        let records = self.records(
+            &payload.ids,
+            payload.embeddings.as_ref(),
+            &payload.metadatas,
+            &payload.documents,
+            &payload.uris,
+        )?;
+        let mut state = self.state();
+        let collection = state.collection(tenant, database, collection_id)?;
+        collection.check_dimension(&records)?;
+        for record in records {
+            match collection.position(&record.id) {
+                Some(index) => merge(&mut collection.records[index], record),
+                None => collection.records.push(record),
+            }
+        }
+        collection.model.log_position += 1;
+        Ok(UpsertCollectionRecordsResponse {})
+    }
+
+    pub async fn count_collections(
+        &self,
+        tenant: &str,
+        database: &str,
+    ) -> Result<u32, KhromaError> {
+        let mut state = self.state();
+        Ok(state.database(tenant, database)?.collections.len() as u32)
+    }
+
+    pub async fn resolve_collection_id(
+        &self,
+        tenant: &str,
+        database: &str,
+        name: &str,
+    ) -> Result<CollectionUuid, KhromaError> {
+        Ok(self.get_collection(tenant, database, name).await?.id)
+    }
+
+    /// Checks a columnar write and converts it into records.
+    fn records(
+        &self,
+        ids: &[String],
+        embeddings: Option<&EmbeddingsPayload>,
+        metadatas: &Option<Vec<Option<Metadata>>>,
+        documents: &Option<Vec<Option<String>>>,
+        uris: &Option<Vec<Option<String>>>,
+    ) -> Result<Vec<Record>, KhromaError> {
+        if ids.len() > self.max_batch_size as usize {
+            return Err(invalid_argument(format!(
+                "Cannot submit more than {} records at once, got {}",
+                self.max_batch_size,
+                ids.len()
+            )));
+        }
+        let embeddings = match embeddings {
+            Some(EmbeddingsPayload::Float(embeddings)) => embeddings,
+            Some(EmbeddingsPayload::String(_)) => return Err(base64_unsupported()),
+            None => {
+                return Err(invalid_argument(
+                    "The mock client has no embedding function; send embeddings with the records"
+                        .to_string(),
+                ));
+            }
+        };
+        check_len("embeddings", Some(embeddings.len()), ids.len())?;
+        check_len("metadatas", metadatas.as_ref().map(Vec::len), ids.len())?;
+        check_len("documents", documents.as_ref().map(Vec::len), ids.len())?;
+        check_len("uris", uris.as_ref().map(Vec::len), ids.len())?;
+        let mut seen = std::collections::HashSet::with_capacity(ids.len());
+        if let Some(duplicate) = ids.iter().find(|id| !seen.insert(id.as_str())) {
+            return Err(conflict(format!(
+                "Expected IDs to be unique, found duplicates of: {}",
+                duplicate
+            )));
+        }
+        Ok((0..ids.len())
+            .map(|i| Record {
+                id: ids[i].clone(),
+                embedding: Some(embeddings[i].clone()),
+                metadata: metadatas.as_ref().and_then(|m| m[i].clone()),
+                document: documents.as_ref().and_then(|d| d[i].clone()),
+                uri: uris.as_ref().and_then(|u| u[i].clone()),
+            })
+            .collect())
+    }
+}
+
+impl State {
+    fn tenant(&mut self, tenant: &str) -> Result<&mut HashMap<String, MockDatabase>, KhromaError> {
+        self.tenants
+            .get_mut(tenant)
+            .ok_or_else(|| not_found(format!("Tenant [{}] does not exist", tenant)))
+    }
+
+    fn database(&mut self, tenant: &str, database: &str) -> Result<&mut MockDatabase, KhromaError> {
+        self.tenant(tenant)?
+            .get_mut(database)
+            .ok_or_else(|| not_found(format!("Database [{}] does not exist", database)))
+    }
+
+    fn collection(
+        &mut self,
+        tenant: &str,
+        database: &str,
+        collection: &str,
+    ) -> Result<&mut MockCollection, KhromaError> {
+        find(
+            &mut self.database(tenant, database)?.collections,
+            collection,
+        )
+    }
+}
+
+impl MockCollection {
+    fn position(&self, id: &str) -> Option<usize> {
+        self.records.iter().position(|record| record.id == id)
+    }
+
+    fn space(&self) -> HnswSpace {
+        let configuration = &self.model.configuration_json;
+        configuration
+            .hnsw
+            .as_ref()
+            .and_then(|hnsw| hnsw.space.clone())
+            .or_else(|| {
+                configuration
+                    .spann
+                    .as_ref()
+                    .and_then(|spann| spann.space.clone())
+            })
+            .unwrap_or(HnswSpace::L2)
+    }
+
+    /// Fixes the dimension on the first write, and rejects embeddings of any other.
+    fn check_dimension(&mut self, records: &[Record]) -> Result<(), KhromaError> {
+        for embedding in records
+            .iter()
+            .filter_map(|record| record.embedding.as_ref())
+        {
+            let dimension = *self.model.dimension.get_or_insert(embedding.len() as i32);
+            if embedding.len() != dimension as usize {
+                return Err(invalid_argument(format!(
+                    "Collection expecting embedding with dimension of {}, got {}",
+                    dimension,
+                    embedding.len()
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn find<'a>(
+    collections: &'a mut [MockCollection],
+    collection: &str,
+) -> Result<&'a mut MockCollection, KhromaError> {
+    collections
+        .iter_mut()
+        .find(|c| is_ref(c, collection))
+        .ok_or_else(|| collection_not_found(collection))
+}
+
+/// Whether `collection` is the one named by `id_or_name`.
+fn is_ref(collection: &MockCollection, id_or_name: &str) -> bool {
+    collection.model.id.to_string() == id_or_name || collection.model.name == id_or_name
+}
+
+fn matches(
+    record: &Record,
+    ids: Option<&[String]>,
+    filters: &RawWhereFields,
+) -> Result<bool, KhromaError> {
+    if let Some(ids) = ids
+        && !ids.contains(&record.id)
+    {
+        return Ok(false);
+    }
+    if let Some(filter) = &filters.r#where {
+        let empty = Metadata::new();
+        if !matches_where(filter, record.metadata.as_ref().unwrap_or(&empty))? {
+            return Ok(false);
+        }
+    }
+    if let Some(filter) = &filters.where_document
+        && !matches_where_document(filter, record.document.as_deref())?
+    {
+        return Ok(false);
+    }
+    Ok(true)
+}
+
+/// Applies the fields `change` sets to `record`.
+fn merge(record: &mut Record, change: Record) {
+    if change.embedding.is_some() {
+        record.embedding = change.embedding;
+    }
+    if change.document.is_some() {
+        record.document = change.document;
+    }
+    if change.uri.is_some() {
+        record.uri = change.uri;
+    }
+    if let Some(changes) = change.metadata {
+        let metadata = record.metadata.get_or_insert_with(Metadata::new);
+        for (key, value) in changes {
+            if value.is_null() {
+                metadata.remove(&key);
+            } else {
+                metadata.insert(key, value);
+            }
+        }
+    }
+}
+
+fn apply_configuration(
+    configuration: &mut CollectionConfiguration,
+    update: &UpdateCollectionConfiguration,
+) {
+    if let Some(embedding_function) = &update.embedding_function {
+        configuration.embedding_function = Some(embedding_function.clone());
+    }
+    if let Some(update) = &update.hnsw {
+        let hnsw = configuration.hnsw.get_or_insert_with(Default::default);
+        hnsw.ef_search = update.ef_search.or(hnsw.ef_search);
+        hnsw.max_neighbors = update.max_neighbors.or(hnsw.max_neighbors);
+        hnsw.resize_factor = update.resize_factor.or(hnsw.resize_factor);
+        hnsw.sync_threshold = update.sync_threshold.or(hnsw.sync_threshold);
+    }
+    if let Some(update) = &update.spann {
+        let spann = configuration.spann.get_or_insert_with(Default::default);
+        spann.ef_search = update.ef_search.or(spann.ef_search);
+        spann.search_nprobe = update.search_nprobe.or(spann.search_nprobe);
+    }
+}
+
+fn distance(space: &HnswSpace, a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    match space {
+        HnswSpace::L2 => a.iter().zip(b).map(|(x, y)| (x - y) * (x - y)).sum(),
+        HnswSpace::Ip => 1.0 - dot,
+        HnswSpace::Cosine => {
+            let norm = |v: &[f32]| v.iter().map(|x| x * x).sum::<f32>().sqrt();
+            let norms = norm(a) * norm(b);
+            if norms == 0.0 { 1.0 } else { 1.0 - dot / norms }
+        }
+    }
+}
+
+/// One column of a query response, from the ranked hits of each query embedding.
+fn per_hit<T>(hits: &[Vec<(f32, &Record)>], f: impl Fn(f32, &Record) -> T) -> Vec<Vec<T>> {
+    hits.iter()
+        .map(|ranked| {
+            ranked
+                .iter()
+                .map(|(distance, record)| f(*distance, record))
+                .collect()
+        })
+        .collect()
+}
+
+fn arena<'a>(embeddings: impl Iterator<Item = &'a [f32]>) -> EmbeddingArena {
+    let mut arena = EmbeddingArena::default();
+    for embedding in embeddings {
+        arena.push(embedding);
+    }
+    arena
+}
+
+fn database_model(tenant: &str, name: &str, database: &MockDatabase) -> Database {
+    Database {
+        id: database.id,
+        name: name.to_string(),
+        tenant: tenant.to_string(),
+    }
+}
+
+fn window<T>(items: Vec<T>, limit: Option<i32>, offset: Option<i32>) -> Vec<T> {
+    let offset = offset.unwrap_or(0).max(0) as usize;
+    let limit = limit.map_or(usize::MAX, |limit| limit.max(0) as usize);
+    items.into_iter().skip(offset).take(limit).collect()
+}
+
+fn check_len(column: &str, len: Option<usize>, ids: usize) -> Result<(), KhromaError> {
+    match len {
+        Some(len) if len != ids => Err(invalid_argument(format!(
+            "{} has {} entries for {} ids",
+            column, len, ids
+        ))),
+        _ => Ok(()),
+    }
+}
+
+fn base64_unsupported() -> KhromaError {
+    invalid_argument("The mock client does not accept base64-encoded embeddings".to_string())
+}
+
+fn not_found(message: String) -> KhromaError {
+    KhromaError::from_status(StatusCode::NOT_FOUND, Some("NotFoundError"), message, None)
+}
+
+fn collection_not_found(collection: &str) -> KhromaError {
+    not_found(format!("Collection [{}] does not exist", collection))
+}
+
+fn conflict(message: String) -> KhromaError {
+    KhromaError::from_status(
+        StatusCode::CONFLICT,
+        Some("UniqueConstraintError"),
+        message,
+        None,
+    )
+}
+
+fn invalid_argument(message: String) -> KhromaError {
+    KhromaError::from_status(
+        StatusCode::BAD_REQUEST,
+        Some("InvalidArgumentError"),
+        message,
+        None,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::high_level::{Collection, Khroma};
+    use serde_json::json;
+
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    async fn collection(khroma: &Khroma) -> Collection {
+        khroma
+            .get_tenant(DEFAULT_TENANT)
+            .await
+            .unwrap()
+            .get_database(DEFAULT_DATABASE)
+            .await
+            .unwrap()
+            .create_collection(&CreateCollectionPayload::new("docs"))
+            .await
+            .unwrap()
+    }
+
+    fn record(id: &str, embedding: [f32; 2], genre: &str) -> Record {
+        let mut metadata = Metadata::new();
+        metadata.insert("genre".to_string(), json!(genre));
+        Record::new(id)
+            .with_embedding(embedding.to_vec())
+            .with_document(format!("{} document", genre))
+            .with_metadata(metadata)
+    }
+
+    fn records() -> Vec<Record> {
+        vec![
+            record("a", [0.0, 0.0], "jazz"),
+            record("b", [1.0, 0.0], "rock"),
+            record("c", [3.0, 0.0], "jazz"),
+            record("d", [0.0, 5.0], "blues"),
+        ]
+    }
+
+    #[test]
+    fn added_records_read_back_unchanged() {
+        block_on(async {
+            let khroma = Khroma::from_api(MockKhromaClient::new());
+            let collection = collection(&khroma).await;
+            collection.add_records(records()).await.unwrap();
+
+            let payload = GetRequestPayload::builder()
+                .ids(["c", "a"])
+                .include(Include::ALL)
+                .build();
+            let mut read = collection.get_records(&payload).await.unwrap();
+            read.sort_by(|a, b| a.id.cmp(&b.id));
+            let expected = records();
+            assert_eq!(read, [expected[0].clone(), expected[2].clone()]);
+            assert_eq!(collection.count().await.unwrap(), 4);
+        });
+    }
+
+    #[test]
+    fn query_ranks_by_distance() {
+        block_on(async {
+            let khroma = Khroma::from_api(MockKhromaClient::new());
+            let collection = collection(&khroma).await;
+            collection.add_records(records()).await.unwrap();
+
+            let payload = QueryRequestPayload::builder(vec![0.9, 0.0])
+                .unwrap()
+                .n_results(3)
+                .include(&[Include::Distances])
+                .build();
+            let response = collection.query(&payload, None, None).await.unwrap();
+            assert_eq!(response.ids, [["b", "a", "c"]]);
+            let distances: Vec<f32> = response.distances.unwrap()[0]
+                .iter()
+                .map(|d| d.unwrap())
+                .collect();
+            assert!(distances.windows(2).all(|pair| pair[0] <= pair[1]));
+            assert!((distances[0] - 0.01).abs() < 1e-6);
+            assert!((distances[1] - 0.81).abs() < 1e-6);
+        });
+    }
+
+    #[test]
+    fn duplicate_ids_in_one_add_conflict() {
+        block_on(async {
+            let khroma = Khroma::from_api(MockKhromaClient::new());
+            let collection = collection(&khroma).await;
+            let mut batch = records();
+            batch.push(record("a", [9.0, 9.0], "pop"));
+
+            let error = collection.add_records(batch).await.unwrap_err();
+            assert!(error.is_conflict(), "{:?}", error);
+            assert_eq!(collection.count().await.unwrap(), 0);
+        });
+    }
+
+    #[test]
+    fn adding_existing_ids_keeps_the_stored_records() {
+        block_on(async {
+            let khroma = Khroma::from_api(MockKhromaClient::new());
+            let collection = collection(&khroma).await;
+            collection.add_records(records()).await.unwrap();
+            collection
+                .add_records(vec![record("a", [9.0, 9.0], "pop")])
+                .await
+                .unwrap();
+
+            let payload = GetRequestPayload::builder().ids(["a"]).build();
+            let read = collection.get_records(&payload).await.unwrap();
+            assert_eq!(read[0].document.as_deref(), Some("jazz document"));
+        });
+    }
+
+    #[test]
+    fn unknown_collections_are_not_found() {
+        block_on(async {
+            let mock = MockKhromaClient::new();
+            let khroma = Khroma::from_api(mock.clone());
+            let database = khroma
+                .get_tenant(DEFAULT_TENANT)
+                .await
+                .unwrap()
+                .get_database(DEFAULT_DATABASE)
+                .await
+                .unwrap();
+
+            let error = database
+                .get_collection_by_name("missing")
+                .await
+                .unwrap_err();
+            assert!(error.is_not_found(), "{:?}", error);
+            let id = Uuid::new_v4().to_string();
+            let error = mock
+                .collection_count(DEFAULT_TENANT, DEFAULT_DATABASE, &id)
+                .await
+                .unwrap_err();
+            assert!(error.is_not_found(), "{:?}", error);
+            let error = mock
+                .get_collection("nobody", DEFAULT_DATABASE, "docs")
+                .await;
+            assert!(error.unwrap_err().is_not_found());
+        });
+    }
+
+    #[test]
+    fn collections_are_fetched_by_id_in_any_form() {
+        block_on(async {
+            let khroma = Khroma::from_api(MockKhromaClient::new());
+            let database = khroma
+                .get_tenant(DEFAULT_TENANT)
+                .await
+                .unwrap()
+                .get_database(DEFAULT_DATABASE)
+                .await
+                .unwrap();
+            let id = collection(&khroma).await.id;
+            let text = id.to_string();
+
+            assert_eq!(database.get_collection(id).await.unwrap().id, id);
+            assert_eq!(database.get_collection(id.0).await.unwrap().id, id);
+            assert_eq!(database.get_collection(text.as_str()).await.unwrap().id, id);
+            assert_eq!(database.get_collection(text).await.unwrap().id, id);
+            let error = database.get_collection("docs").await.unwrap_err();
+            assert!(matches!(error, KhromaError::Validation(_)), "{:?}", error);
+        });
+    }
+
+    #[test]
+    fn where_filters_select_records() {
+        block_on(async {
+            let khroma = Khroma::from_api(MockKhromaClient::new());
+            let collection = collection(&khroma).await;
+            collection.add_records(records()).await.unwrap();
+
+            let payload = GetRequestPayload::builder()
+                .where_metadata(json!({"genre": {"$in": ["jazz", "blues"]}}))
+                .build();
+            let mut ids = collection.get(&payload).await.unwrap().ids;
+            ids.sort();
+            assert_eq!(ids, ["a", "c", "d"]);
+
+            let payload = QueryRequestPayload::builder(vec![1.0, 0.0])
+                .unwrap()
+                .where_metadata(json!({"$and": [{"genre": "jazz"}, {"genre": {"$ne": "rock"}}]}))
+                .where_document(json!({"$contains": "jazz"}))
+                .n_results(10)
+                .build();
+            let response = collection.query(&payload, None, None).await.unwrap();
+            assert_eq!(response.ids, [["a", "c"]]);
+        });
+    }
+
+    #[test]
+    fn writes_over_the_batch_size_are_rejected() {
+        block_on(async {
+            let khroma = Khroma::from_api(MockKhromaClient::new().with_max_batch_size(2));
+            let collection = collection(&khroma).await;
+            let error = collection
+                .add(&AddCollectionRecordsPayload::from_records(records()).unwrap())
+                .await
+                .unwrap_err();
+            assert_eq!(error.status(), Some(StatusCode::BAD_REQUEST));
+        });
+    }
+}
@@ -1,18 +1,176 @@
 #![allow(non_snake_case)]
+use crate::error::KhromaError;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use uuid::Uuid;
 
 pub type CollectionUuid = Uuid;
 pub type Metadata = HashMap<String, serde_json::Value>;
 
+/// The UUID of a collection, as expected in collection path segments of the v2 API.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(transparent)]
+pub struct CollectionId(pub Uuid);
+
+impl CollectionId {
+    pub fn as_uuid(&self) -> &Uuid {
+        &self.0
+    }
+}
+
+impl From<Uuid> for CollectionId {
+    fn from(id: Uuid) -> Self {
+        Self(id)
+    }
+}
+
+impl From<CollectionId> for Uuid {
+    fn from(id: CollectionId) -> Self {
+        id.0
+    }
+}
+
+impl std::str::FromStr for CollectionId {
+    type Err = uuid::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Uuid::parse_str(s).map(Self)
+    }
+}
+
+impl TryFrom<&str> for CollectionId {
+    type Error = KhromaError;
+
+    /// Parses a collection UUID, failing with [`KhromaError::Validation`] otherwise.
+    fn try_from(id: &str) -> Result<Self, Self::Error> {
+        id.parse()
+            .map_err(|e| KhromaError::Validation(format!("invalid collection id {:?}: {}", id, e)))
+    }
+}
+
+impl TryFrom<String> for CollectionId {
+    type Error = KhromaError;
+
+    fn try_from(id: String) -> Result<Self, Self::Error> {
+        Self::try_from(id.as_str())
+    }
+}
+
+impl std::fmt::Display for CollectionId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// The human-readable name of a collection.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[serde(transparent)]
+pub struct CollectionName(pub String);
+
+impl CollectionName {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<String> for CollectionName {
+    fn from(name: String) -> Self {
+        Self(name)
+    }
+}
+
+impl From<&str> for CollectionName {
+    fn from(name: &str) -> Self {
+        Self(name.to_string())
+    }
+}
+
+impl From<CollectionName> for String {
+    fn from(name: CollectionName) -> Self {
+        name.0
+    }
+}
+
+impl std::ops::Deref for CollectionName {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for CollectionName {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl PartialEq<str> for CollectionName {
+    fn eq(&self, other: &str) -> bool {
+        self.0 == other
+    }
+}
+
+impl PartialEq<&str> for CollectionName {
+    fn eq(&self, other: &&str) -> bool {
+        self.0 == *other
+    }
+}
+
+impl std::fmt::Display for CollectionName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// A collection named either way the server accepts. Converts from a [`CollectionId`] or
+/// `Uuid`, and from a [`CollectionName`], `String` or `&str`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum CollectionRef {
+    Id(CollectionId),
+    Name(CollectionName),
+}
+
+impl From<CollectionId> for CollectionRef {
+    fn from(id: CollectionId) -> Self {
+        Self::Id(id)
+    }
+}
+
+impl From<Uuid> for CollectionRef {
+    fn from(id: Uuid) -> Self {
+        Self::Id(id.into())
+    }
+}
+
+impl From<CollectionName> for CollectionRef {
+    fn from(name: CollectionName) -> Self {
+        Self::Name(name)
+    }
+}
+
+impl From<String> for CollectionRef {
+    fn from(name: String) -> Self {
+        Self::Name(name.into())
+    }
+}
+
+impl From<&str> for CollectionRef {
+    fn from(name: &str) -> Self {
+        Self::Name(name.into())
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[non_exhaustive]
 pub struct ErrorResponse {
     pub error: String,
     pub message: String,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[non_exhaustive]
 pub struct GetUserIdentityResponse {
     pub user_id: String,
     pub tenant: String,
@@ -20,39 +178,134 @@ pub struct GetUserIdentityResponse {
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[non_exhaustive]
 pub struct HeartbeatResponse {
     #[serde(rename = "nanosecond heartbeat")]
     pub nanosecond_heartbeat: u64,
 }
 
+impl HeartbeatResponse {
+    /// The server's clock at the time of the heartbeat.
+    pub fn as_system_time(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_nanos(self.nanosecond_heartbeat)
+    }
+
+    /// How long ago the heartbeat was produced according to the local clock.
+    /// Returns zero when the server clock is ahead of the local one.
+    pub fn age(&self) -> Duration {
+        SystemTime::now()
+            .duration_since(self.as_system_time())
+            .unwrap_or_default()
+    }
+}
+
+/// The combined result of a healthcheck and a heartbeat, for liveness probes.
+#[derive(Debug, Clone)]
+pub struct HealthStatus {
+    /// Whether the healthcheck endpoint answered successfully.
+    pub healthy: bool,
+    pub heartbeat: Option<HeartbeatResponse>,
+    /// The first failure encountered, if any.
+    pub error: Option<String>,
+    /// Wall time spent waiting for both endpoints.
+    pub round_trip: Duration,
+}
+
+impl HealthStatus {
+    /// Healthy, and the heartbeat is no older than `max_age`.
+    pub fn is_live(&self, max_age: Duration) -> bool {
+        self.healthy
+            && self
+                .heartbeat
+                .as_ref()
+                .is_some_and(|heartbeat| heartbeat.age() <= max_age)
+    }
+}
+
+/// A stage of [`Khroma::self_test`](crate::Khroma::self_test).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelfTestStep {
+    Heartbeat,
+    CreateCollection,
+    Add,
+    Query,
+    DeleteCollection,
+}
+
+#[derive(Debug, Clone)]
+pub struct SelfTestStepResult {
+    pub step: SelfTestStep,
+    pub duration: Duration,
+    pub error: Option<String>,
+}
+
+/// The steps a self-test ran, in order. It stops at the first failure, except that the
+/// temporary collection is always deleted once created.
+#[derive(Debug, Clone)]
+pub struct SelfTestReport {
+    pub tenant: String,
+    pub database: String,
+    pub steps: Vec<SelfTestStepResult>,
+}
+
+impl SelfTestReport {
+    pub fn passed(&self) -> bool {
+        self.steps.iter().all(|step| step.error.is_none())
+    }
+
+    /// The first step that failed.
+    pub fn failure(&self) -> Option<&SelfTestStepResult> {
+        self.steps.iter().find(|step| step.error.is_some())
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[non_exhaustive]
 pub struct ChecklistResponse {
     pub max_batch_size: i32,
     pub supports_base64_encoding: bool,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[non_exhaustive]
 pub struct CreateTenantPayload {
     pub name: String,
 }
 
+impl CreateTenantPayload {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self { name: name.into() }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[non_exhaustive]
 pub struct CreateTenantResponse {}
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[non_exhaustive]
 pub struct GetTenantResponse {
     pub name: String,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[non_exhaustive]
 pub struct CreateDatabasePayload {
     pub name: String,
 }
 
+impl CreateDatabasePayload {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self { name: name.into() }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[non_exhaustive]
 pub struct CreateDatabaseResponse {}
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[non_exhaustive]
 pub struct Database {
     pub id: Uuid,
     pub name: String,
@@ -60,6 +313,7 @@ pub struct Database {
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[non_exhaustive]
 pub struct DeleteDatabaseResponse {}
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -90,7 +344,6 @@ pub enum HnswSpace {
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
-#[serde(deny_unknown_fields)]
 pub struct HnswConfiguration {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub ef_construction: Option<u32>,
@@ -108,7 +361,6 @@ pub struct HnswConfiguration {
 
 
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
-#[serde(deny_unknown_fields)]
 pub struct SpannConfiguration {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub ef_construction: Option<u32>,
@@ -140,7 +392,225 @@ pub struct CollectionConfiguration {
     pub spann: Option<SpannConfiguration>,
 }
 
+/// The vector index of a collection. A collection has exactly one: HNSW, supported by
+/// every deployment, or SPANN, supported by distributed deployments and Chroma Cloud.
+#[derive(Debug, Clone)]
+pub enum IndexConfig {
+    Hnsw(HnswConfiguration),
+    Spann(SpannConfiguration),
+}
+
+impl From<IndexConfig> for CollectionConfiguration {
+    fn from(index: IndexConfig) -> Self {
+        CollectionConfiguration::default().with_index(index)
+    }
+}
+
+impl CollectionConfiguration {
+    /// Sets the index, replacing any index configured before.
+    pub fn with_index(mut self, index: IndexConfig) -> Self {
+        match index {
+            IndexConfig::Hnsw(hnsw) => {
+                self.hnsw = Some(hnsw);
+                self.spann = None;
+            }
+            IndexConfig::Spann(spann) => {
+                self.spann = Some(spann);
+                self.hnsw = None;
+            }
+        }
+        self
+    }
+
+    /// The configured index, or `None` to let the server choose. Fails if both are set.
+    pub fn index(&self) -> Result<Option<IndexConfig>, KhromaError> {
+        match (&self.hnsw, &self.spann) {
+            (Some(_), Some(_)) => Err(KhromaError::Validation(
+                "A collection cannot have both an HNSW and a SPANN index".to_string(),
+            )),
+            (Some(hnsw), None) => Ok(Some(IndexConfig::Hnsw(hnsw.clone()))),
+            (None, Some(spann)) => Ok(Some(IndexConfig::Spann(spann.clone()))),
+            (None, None) => Ok(None),
+        }
+    }
+}
+
+macro_rules! impl_config_text {
+    ($ty:ty) => {
+        impl $ty {
+            pub fn to_json(&self) -> Result<String, KhromaError> {
+                serde_json::to_string_pretty(self).map_err(|e| KhromaError::Config(e.to_string()))
+            }
+
+            pub fn from_json(text: &str) -> Result<Self, KhromaError> {
+                serde_json::from_str(text).map_err(|e| KhromaError::Config(e.to_string()))
+            }
+
+            /// Like `from_json`, but fails on keys khroma doesn't know, such as a
+            /// misspelled index parameter, instead of ignoring them.
+            pub fn from_json_strict(text: &str) -> Result<Self, KhromaError> {
+                let raw = serde_json::from_str(text).map_err(|e| KhromaError::Config(e.to_string()))?;
+                from_value_strict(raw)
+            }
+
+            #[cfg(feature = "config")]
+            pub fn to_toml(&self) -> Result<String, KhromaError> {
+                toml::to_string_pretty(self).map_err(|e| KhromaError::Config(e.to_string()))
+            }
+
+            #[cfg(feature = "config")]
+            pub fn from_toml(text: &str) -> Result<Self, KhromaError> {
+                toml::from_str(text).map_err(|e| KhromaError::Config(e.to_string()))
+            }
+
+            /// Like `from_toml`, but fails on keys khroma doesn't know, such as a
+            /// misspelled index parameter, instead of ignoring them.
+            #[cfg(feature = "config")]
+            pub fn from_toml_strict(text: &str) -> Result<Self, KhromaError> {
+                let raw = toml::from_str(text).map_err(|e| KhromaError::Config(e.to_string()))?;
+                from_value_strict(raw)
+            }
+        }
+    };
+}
+
+/// Deserializes `raw`, failing if it has keys that don't survive the round trip through
+/// `T`, i.e. keys `T` doesn't model.
+fn from_value_strict<T: Serialize + serde::de::DeserializeOwned>(
+    raw: serde_json::Value,
+) -> Result<T, KhromaError> {
+    let parsed: T =
+        serde_json::from_value(raw.clone()).map_err(|e| KhromaError::Config(e.to_string()))?;
+    let modeled = serde_json::to_value(&parsed).map_err(|e| KhromaError::Config(e.to_string()))?;
+    let unknown = crate::drift::unknown_fields(&raw, &modeled);
+    if !unknown.is_empty() {
+        return Err(KhromaError::Config(format!(
+            "Unknown configuration keys: {}",
+            unknown.join(", ")
+        )));
+    }
+    Ok(parsed)
+}
+
+impl_config_text!(CollectionConfiguration);
+impl_config_text!(DatabaseSpec);
+
+/// The desired collections of a database, applied with `Database::apply_config`.
+///
+/// ```toml
+/// [[collections]]
+/// name = "articles"
+/// metadata = { team = "search" }
+/// configuration = { hnsw = { space = "cosine", ef_search = 100 } }
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct DatabaseSpec {
+    #[serde(default)]
+    pub collections: Vec<CollectionSpec>,
+}
+
+/// The desired state of one collection. Unset fields are left as they are on the server.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct CollectionSpec {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<Metadata>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub configuration: Option<CollectionConfiguration>,
+}
+
+impl CollectionSpec {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            ..Default::default()
+        }
+    }
+}
+
+impl From<CollectionSpec> for CreateCollectionPayload {
+    fn from(spec: CollectionSpec) -> Self {
+        CreateCollectionPayload {
+            name: spec.name,
+            metadata: spec.metadata,
+            configuration: spec.configuration,
+            get_or_create: None,
+        }
+    }
+}
+
+/// Named collection configurations with sensible index defaults.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Preset {
+    /// HNSW with cosine distance, tuned for small to medium text collections.
+    TextCosineSmall,
+    /// HNSW with cosine distance and a denser graph, trading memory for recall.
+    TextCosineHighRecall,
+    /// HNSW with squared L2 distance, for embeddings that are not normalized.
+    EuclideanSmall,
+    /// SPANN with cosine distance, for corpora too large to keep a single HNSW graph in memory.
+    LargeCorpusSpann,
+}
+
+impl Preset {
+    pub fn configuration(self) -> CollectionConfiguration {
+        match self {
+            Preset::TextCosineSmall => CollectionConfiguration {
+                hnsw: Some(HnswConfiguration {
+                    space: Some(HnswSpace::Cosine),
+                    ef_construction: Some(100),
+                    ef_search: Some(100),
+                    max_neighbors: Some(16),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            Preset::TextCosineHighRecall => CollectionConfiguration {
+                hnsw: Some(HnswConfiguration {
+                    space: Some(HnswSpace::Cosine),
+                    ef_construction: Some(200),
+                    ef_search: Some(200),
+                    max_neighbors: Some(32),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            Preset::EuclideanSmall => CollectionConfiguration {
+                hnsw: Some(HnswConfiguration {
+                    space: Some(HnswSpace::L2),
+                    ef_construction: Some(100),
+                    ef_search: Some(100),
+                    max_neighbors: Some(16),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            Preset::LargeCorpusSpann => CollectionConfiguration {
+                spann: Some(SpannConfiguration {
+                    space: Some(HnswSpace::Cosine),
+                    search_nprobe: Some(64),
+                    write_nprobe: Some(32),
+                    ef_construction: Some(200),
+                    ef_search: Some(200),
+                    max_neighbors: Some(64),
+                    reassign_neighbor_count: Some(64),
+                    split_threshold: Some(50),
+                    merge_threshold: Some(25),
+                }),
+                ..Default::default()
+            },
+        }
+    }
+}
+
+impl From<Preset> for CollectionConfiguration {
+    fn from(preset: Preset) -> Self {
+        preset.configuration()
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[non_exhaustive]
 pub struct Collection {
     pub id: CollectionUuid,
     pub name: String,
@@ -156,6 +626,7 @@ pub struct Collection {
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[non_exhaustive]
 pub struct CreateCollectionPayload {
     pub name: String,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -166,10 +637,28 @@ pub struct CreateCollectionPayload {
     pub get_or_create: Option<bool>,
 }
 
+impl CreateCollectionPayload {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Sets the collection's index, keeping the rest of its configuration.
+    pub fn with_index(mut self, index: IndexConfig) -> Self {
+        let configuration = self.configuration.take().unwrap_or_default();
+        self.configuration = Some(configuration.with_index(index));
+        self
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[non_exhaustive]
 pub struct UpdateCollectionResponse {}
 
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[non_exhaustive]
 pub struct UpdateCollectionPayload {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub new_name: Option<String>,
@@ -179,6 +668,56 @@ pub struct UpdateCollectionPayload {
     pub new_configuration: Option<UpdateCollectionConfiguration>,
 }
 
+impl UpdateCollectionPayload {
+    pub fn builder() -> UpdateCollectionPayloadBuilder {
+        UpdateCollectionPayloadBuilder::default()
+    }
+}
+
+/// Composes several collection changes into a single `update_collection` call.
+#[derive(Debug, Clone, Default)]
+pub struct UpdateCollectionPayloadBuilder {
+    payload: UpdateCollectionPayload,
+}
+
+impl UpdateCollectionPayloadBuilder {
+    pub fn rename(mut self, new_name: impl Into<String>) -> Self {
+        self.payload.new_name = Some(new_name.into());
+        self
+    }
+
+    /// Replaces the collection metadata as a whole: keys missing from `metadata` are dropped.
+    pub fn replace_metadata(mut self, metadata: Metadata) -> Self {
+        self.payload.new_metadata = Some(metadata);
+        self
+    }
+
+    /// Changes HNSW parameters. This merges: parameters left unset keep their current values.
+    pub fn tune_hnsw(mut self, tune: impl FnOnce(&mut UpdateHnswConfiguration)) -> Self {
+        let configuration = self.payload.new_configuration.get_or_insert_with(Default::default);
+        tune(configuration.hnsw.get_or_insert_with(Default::default));
+        self
+    }
+
+    /// Changes SPANN parameters. This merges: parameters left unset keep their current values.
+    pub fn tune_spann(mut self, tune: impl FnOnce(&mut SpannConfiguration)) -> Self {
+        let configuration = self.payload.new_configuration.get_or_insert_with(Default::default);
+        tune(configuration.spann.get_or_insert_with(Default::default));
+        self
+    }
+
+    /// Replaces the embedding function configuration.
+    pub fn embedding_function(mut self, embedding_function: EmbeddingFunctionConfiguration) -> Self {
+        let configuration = self.payload.new_configuration.get_or_insert_with(Default::default);
+        configuration.embedding_function = Some(embedding_function);
+        self
+    }
+
+    pub fn build(self) -> UpdateCollectionPayload {
+        self.payload
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct UpdateCollectionConfiguration {
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -190,7 +729,6 @@ pub struct UpdateCollectionConfiguration {
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
-#[serde(deny_unknown_fields)]
 pub struct UpdateHnswConfiguration {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub batch_size: Option<u32>,
@@ -213,7 +751,201 @@ pub enum EmbeddingsPayload {
     String(Vec<String>),
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+/// Packs `embedding` into Chroma's base64 wire format: little-endian `f32`s, base64-encoded.
+pub fn embedding_to_base64(embedding: &[f32]) -> String {
+    use base64::Engine;
+    let bytes: Vec<u8> = embedding.iter().flat_map(|x| x.to_le_bytes()).collect();
+    base64::engine::general_purpose::STANDARD.encode(bytes)
+}
+
+/// Unpacks an embedding from Chroma's base64 wire format, or `None` if `encoded` isn't one.
+pub fn embedding_from_base64(encoded: &str) -> Option<Vec<f32>> {
+    use base64::Engine;
+    let bytes = base64::engine::general_purpose::STANDARD.decode(encoded).ok()?;
+    if bytes.len() % 4 != 0 {
+        return None;
+    }
+    Some(bytes.chunks_exact(4).map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]])).collect())
+}
+
+impl EmbeddingsPayload {
+    /// The embeddings in base64 form; see [`embedding_to_base64`].
+    pub fn to_base64(&self) -> Self {
+        match self {
+            EmbeddingsPayload::Float(embeddings) => EmbeddingsPayload::String(
+                embeddings.iter().map(|e| embedding_to_base64(e)).collect(),
+            ),
+            EmbeddingsPayload::String(_) => self.clone(),
+        }
+    }
+
+    /// The embeddings as vectors, decoding base64 ones. Fails with
+    /// [`KhromaError::Validation`] if a string isn't a base64 embedding.
+    pub fn into_floats(self) -> Result<Vec<Vec<f32>>, KhromaError> {
+        match self {
+            EmbeddingsPayload::Float(embeddings) => Ok(embeddings),
+            EmbeddingsPayload::String(embeddings) => embeddings
+                .iter()
+                .enumerate()
+                .map(|(i, e)| {
+                    embedding_from_base64(e).ok_or_else(|| {
+                        KhromaError::Validation(format!(
+                            "Embedding {} is not a base64-encoded embedding",
+                            i
+                        ))
+                    })
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<Vec<Vec<f32>>> for EmbeddingsPayload {
+    fn from(embeddings: Vec<Vec<f32>>) -> Self {
+        EmbeddingsPayload::Float(embeddings)
+    }
+}
+
+impl From<Vec<f32>> for EmbeddingsPayload {
+    /// A single embedding.
+    fn from(embedding: Vec<f32>) -> Self {
+        EmbeddingsPayload::Float(vec![embedding])
+    }
+}
+
+/// One embedding per row.
+#[cfg(feature = "ndarray")]
+impl From<::ndarray::ArrayView2<'_, f32>> for EmbeddingsPayload {
+    fn from(embeddings: ::ndarray::ArrayView2<'_, f32>) -> Self {
+        EmbeddingsPayload::Float(embeddings.rows().into_iter().map(|row| row.to_vec()).collect())
+    }
+}
+
+/// One embedding per row.
+#[cfg(feature = "ndarray")]
+impl From<&::ndarray::Array2<f32>> for EmbeddingsPayload {
+    fn from(embeddings: &::ndarray::Array2<f32>) -> Self {
+        embeddings.view().into()
+    }
+}
+
+/// One embedding per row.
+#[cfg(feature = "ndarray")]
+impl From<::ndarray::Array2<f32>> for EmbeddingsPayload {
+    fn from(embeddings: ::ndarray::Array2<f32>) -> Self {
+        embeddings.view().into()
+    }
+}
+
+/// A single embedding.
+#[cfg(feature = "ndarray")]
+impl From<::ndarray::Array1<f32>> for EmbeddingsPayload {
+    fn from(embedding: ::ndarray::Array1<f32>) -> Self {
+        EmbeddingsPayload::Float(vec![embedding.to_vec()])
+    }
+}
+
+/// One embedding per row, decoding base64 ones. Fails with [`KhromaError::Validation`] if
+/// the embeddings differ in length or a string isn't a base64 embedding.
+#[cfg(feature = "ndarray")]
+impl TryFrom<EmbeddingsPayload> for ::ndarray::Array2<f32> {
+    type Error = KhromaError;
+
+    fn try_from(embeddings: EmbeddingsPayload) -> Result<Self, KhromaError> {
+        let rows = embeddings.into_floats()?;
+        let dimension = rows.first().map_or(0, Vec::len);
+        if let Some(row) = rows.iter().find(|row| row.len() != dimension) {
+            return Err(KhromaError::Validation(format!(
+                "Embeddings differ in length: {} and {}",
+                dimension,
+                row.len()
+            )));
+        }
+        let count = rows.len();
+        ::ndarray::Array2::from_shape_vec((count, dimension), rows.concat())
+            .map_err(|e| KhromaError::Validation(e.to_string()))
+    }
+}
+
+/// A rank-2 tensor holds one embedding per row, and a rank-1 tensor a single embedding.
+/// Floating-point tensors of any precision are converted to `f32`, on any device; other
+/// dtypes and ranks fail.
+#[cfg(feature = "candle")]
+impl TryFrom<&candle_core::Tensor> for EmbeddingsPayload {
+    type Error = KhromaError;
+
+    fn try_from(tensor: &candle_core::Tensor) -> Result<Self, KhromaError> {
+        if !tensor.dtype().is_float() {
+            return Err(KhromaError::Validation(format!(
+                "Embedding tensors must be floating-point, not {:?}",
+                tensor.dtype()
+            )));
+        }
+        let candle_error = |e: candle_core::Error| KhromaError::Validation(e.to_string());
+        let tensor = tensor.to_dtype(candle_core::DType::F32).map_err(candle_error)?;
+        match tensor.rank() {
+            1 => Ok(EmbeddingsPayload::Float(vec![tensor.to_vec1().map_err(candle_error)?])),
+            2 => Ok(EmbeddingsPayload::Float(tensor.to_vec2().map_err(candle_error)?)),
+            _ => Err(KhromaError::Validation(format!(
+                "Embedding tensors must have shape (dimension) or (count, dimension), not {:?}",
+                tensor.dims()
+            ))),
+        }
+    }
+}
+
+#[cfg(feature = "candle")]
+impl TryFrom<candle_core::Tensor> for EmbeddingsPayload {
+    type Error = KhromaError;
+
+    fn try_from(tensor: candle_core::Tensor) -> Result<Self, KhromaError> {
+        Self::try_from(&tensor)
+    }
+}
+
+/// The column setters shared by the add and upsert payload builders.
+macro_rules! record_columns {
+    ($builder:ident) => {
+        impl $builder {
+            /// One embedding per id, in the same order. Takes nested vectors, or with the
+            /// `ndarray` feature a matrix with one embedding per row.
+            pub fn embeddings(mut self, embeddings: impl Into<EmbeddingsPayload>) -> Self {
+                self.payload.embeddings = Some(embeddings.into());
+                self
+            }
+
+            /// One document per id, in the same order.
+            pub fn documents(mut self, documents: impl IntoIterator<Item = impl Into<String>>) -> Self {
+                self.payload.documents = Some(documents.into_iter().map(|d| Some(d.into())).collect());
+                self
+            }
+
+            /// One metadata map per id, in the same order.
+            pub fn metadatas(mut self, metadatas: impl IntoIterator<Item = Metadata>) -> Self {
+                self.payload.metadatas = Some(metadatas.into_iter().map(Some).collect());
+                self
+            }
+
+            /// One URI per id, in the same order.
+            pub fn uris(mut self, uris: impl IntoIterator<Item = impl Into<String>>) -> Self {
+                self.payload.uris = Some(uris.into_iter().map(|u| Some(u.into())).collect());
+                self
+            }
+        }
+    };
+}
+
+/// Converts a count to the `i32` the API takes, saturating at `i32::MAX`.
+fn api_count(n: u32) -> i32 {
+    n.min(i32::MAX as u32) as i32
+}
+
+fn string_vec(items: impl IntoIterator<Item = impl Into<String>>) -> Vec<String> {
+    items.into_iter().map(Into::into).collect()
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[non_exhaustive]
 pub struct AddCollectionRecordsPayload {
     pub ids: Vec<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -226,10 +958,42 @@ pub struct AddCollectionRecordsPayload {
     pub uris: Option<Vec<Option<String>>>,
 }
 
+impl AddCollectionRecordsPayload {
+    pub fn new(ids: Vec<String>) -> Self {
+        Self {
+            ids,
+            ..Default::default()
+        }
+    }
+
+    pub fn builder(ids: impl IntoIterator<Item = impl Into<String>>) -> AddCollectionRecordsPayloadBuilder {
+        AddCollectionRecordsPayloadBuilder {
+            payload: Self::new(string_vec(ids)),
+        }
+    }
+}
+
+/// Builds an add payload column by column. Each column needs one entry per id, or the
+/// server rejects the payload.
+#[derive(Debug, Clone)]
+pub struct AddCollectionRecordsPayloadBuilder {
+    payload: AddCollectionRecordsPayload,
+}
+
+record_columns!(AddCollectionRecordsPayloadBuilder);
+
+impl AddCollectionRecordsPayloadBuilder {
+    pub fn build(self) -> AddCollectionRecordsPayload {
+        self.payload
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[non_exhaustive]
 pub struct AddCollectionRecordsResponse {}
 
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[non_exhaustive]
 pub struct RawWhereFields {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub r#where: Option<serde_json::Value>,
@@ -237,7 +1001,105 @@ pub struct RawWhereFields {
     pub where_document: Option<serde_json::Value>,
 }
 
+impl RawWhereFields {
+    pub fn new(
+        r#where: Option<serde_json::Value>,
+        where_document: Option<serde_json::Value>,
+    ) -> Self {
+        Self {
+            r#where,
+            where_document,
+        }
+    }
+}
+
+impl From<WhereDocument> for RawWhereFields {
+    fn from(filter: WhereDocument) -> Self {
+        Self::new(None, Some(filter.into()))
+    }
+}
+
+/// A `where_document` filter on the text of records.
+///
+/// Filters compose, as in `WhereDocument::contains("rust").and(WhereDocument::not_regex("(?i)draft"))`.
+/// They convert into a `serde_json::Value`, so they can be passed wherever payload
+/// builders take a document filter, or into [`RawWhereFields`] on their own.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(transparent)]
+pub struct WhereDocument(serde_json::Value);
+
+impl WhereDocument {
+    /// Documents containing `text`, case-sensitively.
+    pub fn contains(text: impl Into<String>) -> Self {
+        Self::operator("$contains", text.into())
+    }
+
+    /// Documents not containing `text`, including records without a document.
+    pub fn not_contains(text: impl Into<String>) -> Self {
+        Self::operator("$not_contains", text.into())
+    }
+
+    /// Documents matching the regular expression `pattern`, in the server's regex syntax.
+    pub fn regex(pattern: impl Into<String>) -> Self {
+        Self::operator("$regex", pattern.into())
+    }
+
+    /// Documents not matching the regular expression `pattern`.
+    pub fn not_regex(pattern: impl Into<String>) -> Self {
+        Self::operator("$not_regex", pattern.into())
+    }
+
+    /// Documents matching every filter.
+    pub fn all(filters: impl IntoIterator<Item = WhereDocument>) -> Self {
+        Self::combine("$and", filters)
+    }
+
+    /// Documents matching at least one filter.
+    pub fn any(filters: impl IntoIterator<Item = WhereDocument>) -> Self {
+        Self::combine("$or", filters)
+    }
+
+    pub fn and(self, other: WhereDocument) -> Self {
+        Self::all([self, other])
+    }
+
+    pub fn or(self, other: WhereDocument) -> Self {
+        Self::any([self, other])
+    }
+
+    fn operator(operator: &str, operand: String) -> Self {
+        Self(serde_json::json!({ operator: operand }))
+    }
+
+    /// Joins `filters` under `operator`, flattening nested clauses of the same operator.
+    fn combine(operator: &str, filters: impl IntoIterator<Item = WhereDocument>) -> Self {
+        let mut clauses = Vec::new();
+        for filter in filters {
+            match filter.0 {
+                serde_json::Value::Object(mut map) if map.len() == 1 && map.contains_key(operator) => {
+                    if let Some(serde_json::Value::Array(nested)) = map.remove(operator) {
+                        clauses.extend(nested);
+                    }
+                }
+                clause => clauses.push(clause),
+            }
+        }
+        // Chroma requires at least two clauses under `$and` and `$or`.
+        if clauses.len() == 1 {
+            return Self(clauses.remove(0));
+        }
+        Self(serde_json::json!({ operator: clauses }))
+    }
+}
+
+impl From<WhereDocument> for serde_json::Value {
+    fn from(filter: WhereDocument) -> Self {
+        filter.0
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[non_exhaustive]
 pub struct DeleteCollectionRecordsPayload {
     #[serde(flatten)]
     pub where_fields: RawWhereFields,
@@ -245,15 +1107,143 @@ pub struct DeleteCollectionRecordsPayload {
     pub ids: Option<Vec<String>>,
 }
 
+impl DeleteCollectionRecordsPayload {
+    pub fn by_ids(ids: Vec<String>) -> Self {
+        Self {
+            ids: Some(ids),
+            ..Default::default()
+        }
+    }
+
+    pub fn matching(where_fields: RawWhereFields) -> Self {
+        Self {
+            where_fields,
+            ..Default::default()
+        }
+    }
+
+    pub fn builder() -> DeleteCollectionRecordsPayloadBuilder {
+        DeleteCollectionRecordsPayloadBuilder::default()
+    }
+}
+
+/// Builds a delete payload. Records must match every criterion that is set.
+#[derive(Debug, Clone, Default)]
+pub struct DeleteCollectionRecordsPayloadBuilder {
+    payload: DeleteCollectionRecordsPayload,
+}
+
+impl DeleteCollectionRecordsPayloadBuilder {
+    pub fn ids(mut self, ids: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.payload.ids = Some(string_vec(ids));
+        self
+    }
+
+    /// A metadata filter, such as `json!({"topic": {"$eq": "rust"}})`.
+    pub fn where_metadata(mut self, filter: impl Into<serde_json::Value>) -> Self {
+        self.payload.where_fields.r#where = Some(filter.into());
+        self
+    }
+
+    /// A document filter, such as `json!({"$contains": "rust"})`.
+    pub fn where_document(mut self, filter: impl Into<serde_json::Value>) -> Self {
+        self.payload.where_fields.where_document = Some(filter.into());
+        self
+    }
+
+    pub fn build(self) -> DeleteCollectionRecordsPayload {
+        self.payload
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[non_exhaustive]
 pub struct DeleteCollectionRecordsResponse {}
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[non_exhaustive]
 pub struct ForkCollectionPayload {
     pub new_name: String,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+impl ForkCollectionPayload {
+    pub fn new(new_name: impl Into<String>) -> Self {
+        Self {
+            new_name: new_name.into(),
+        }
+    }
+
+    pub fn builder(new_name: impl Into<String>) -> ForkCollectionPayloadBuilder {
+        ForkCollectionPayloadBuilder {
+            payload: Self::new(new_name),
+            update: UpdateCollectionPayload::builder(),
+        }
+    }
+}
+
+/// A fork, and changes applied to the forked collection right after it is created.
+#[derive(Debug, Clone)]
+pub struct ForkCollectionRequest {
+    pub fork: ForkCollectionPayload,
+    pub update: Option<UpdateCollectionPayload>,
+}
+
+impl From<ForkCollectionPayload> for ForkCollectionRequest {
+    fn from(fork: ForkCollectionPayload) -> Self {
+        Self { fork, update: None }
+    }
+}
+
+impl From<String> for ForkCollectionRequest {
+    fn from(new_name: String) -> Self {
+        ForkCollectionPayload::new(new_name).into()
+    }
+}
+
+impl From<&str> for ForkCollectionRequest {
+    fn from(new_name: &str) -> Self {
+        ForkCollectionPayload::new(new_name).into()
+    }
+}
+
+/// Composes a fork with metadata and index changes for the forked collection, e.g. to
+/// compare index parameters against the original.
+#[derive(Debug, Clone)]
+pub struct ForkCollectionPayloadBuilder {
+    payload: ForkCollectionPayload,
+    update: UpdateCollectionPayloadBuilder,
+}
+
+impl ForkCollectionPayloadBuilder {
+    /// Replaces the fork's metadata as a whole.
+    pub fn replace_metadata(mut self, metadata: Metadata) -> Self {
+        self.update = self.update.replace_metadata(metadata);
+        self
+    }
+
+    /// Changes the fork's HNSW parameters, keeping the ones left unset.
+    pub fn tune_hnsw(mut self, tune: impl FnOnce(&mut UpdateHnswConfiguration)) -> Self {
+        self.update = self.update.tune_hnsw(tune);
+        self
+    }
+
+    /// Changes the fork's SPANN parameters, keeping the ones left unset.
+    pub fn tune_spann(mut self, tune: impl FnOnce(&mut SpannConfiguration)) -> Self {
+        self.update = self.update.tune_spann(tune);
+        self
+    }
+
+    pub fn build(self) -> ForkCollectionRequest {
+        let update = self.update.build();
+        let changed = update.new_metadata.is_some() || update.new_configuration.is_some();
+        ForkCollectionRequest {
+            fork: self.payload,
+            update: changed.then_some(update),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[serde(rename_all = "lowercase")]
 pub enum Include {
     Distances,
@@ -263,9 +1253,42 @@ pub enum Include {
     Uris,
 }
 
+impl Include {
+    /// Every field. Gets have no distances, so [`GetRequestPayload::with_include`] leaves
+    /// [`Include::Distances`] out.
+    pub const ALL: &'static [Include] = &[
+        Include::Documents,
+        Include::Embeddings,
+        Include::Metadatas,
+        Include::Uris,
+        Include::Distances,
+    ];
+    pub const DOCUMENTS_AND_METADATA: &'static [Include] = &[Include::Documents, Include::Metadatas];
+    pub const DOCUMENTS_AND_DISTANCES: &'static [Include] = &[Include::Documents, Include::Distances];
+}
+
 pub type IncludeList = Vec<Include>;
 
+/// `include` without repeats, in the order given.
+fn include_list(include: &[Include]) -> IncludeList {
+    let mut list = IncludeList::with_capacity(include.len());
+    for field in include {
+        if !list.contains(field) {
+            list.push(*field);
+        }
+    }
+    list
+}
+
+/// Include lists applied to get and query requests that don't set `include` themselves.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DefaultIncludes {
+    pub get: Option<IncludeList>,
+    pub query: Option<IncludeList>,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[non_exhaustive]
 pub struct GetRequestPayload {
     #[serde(flatten)]
     pub where_fields: RawWhereFields,
@@ -279,8 +1302,71 @@ pub struct GetRequestPayload {
     pub offset: Option<i32>,
 }
 
+impl GetRequestPayload {
+    pub fn builder() -> GetRequestPayloadBuilder {
+        GetRequestPayloadBuilder::default()
+    }
+
+    /// Sets the fields to return, dropping repeats and [`Include::Distances`], which gets
+    /// don't have. Accepts the presets on [`Include`], such as [`Include::ALL`].
+    pub fn with_include(mut self, include: &[Include]) -> Self {
+        let mut list = include_list(include);
+        list.retain(|field| *field != Include::Distances);
+        self.include = Some(list);
+        self
+    }
+}
+
+/// Builds a get payload. Records must match every criterion that is set.
+#[derive(Debug, Clone, Default)]
+pub struct GetRequestPayloadBuilder {
+    payload: GetRequestPayload,
+}
+
+impl GetRequestPayloadBuilder {
+    pub fn ids(mut self, ids: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.payload.ids = Some(string_vec(ids));
+        self
+    }
+
+    /// A metadata filter, such as `json!({"topic": {"$eq": "rust"}})`.
+    pub fn where_metadata(mut self, filter: impl Into<serde_json::Value>) -> Self {
+        self.payload.where_fields.r#where = Some(filter.into());
+        self
+    }
+
+    /// A document filter, such as `json!({"$contains": "rust"})`.
+    pub fn where_document(mut self, filter: impl Into<serde_json::Value>) -> Self {
+        self.payload.where_fields.where_document = Some(filter.into());
+        self
+    }
+
+    /// See [`GetRequestPayload::with_include`].
+    pub fn include(mut self, include: &[Include]) -> Self {
+        self.payload = self.payload.with_include(include);
+        self
+    }
+
+    pub fn limit(mut self, limit: u32) -> Self {
+        self.payload.limit = Some(api_count(limit));
+        self
+    }
+
+    pub fn offset(mut self, offset: u32) -> Self {
+        self.payload.offset = Some(api_count(offset));
+        self
+    }
+
+    pub fn build(self) -> GetRequestPayload {
+        self.payload
+    }
+}
+
+/// `E` is how embeddings are held: one `Vec` per record by default, or an
+/// [`EmbeddingArena`] for large result sets.
 #[derive(Serialize, Deserialize, Debug, Clone)]
-pub struct GetResponse {
+#[non_exhaustive]
+pub struct GetResponse<E = Vec<Vec<f32>>> {
     pub ids: Vec<String>,
     pub include: Vec<Include>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -290,10 +1376,11 @@ pub struct GetResponse {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub uris: Option<Vec<Option<String>>>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub embeddings: Option<Vec<Vec<f32>>>,
+    pub embeddings: Option<E>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[non_exhaustive]
 pub struct QueryRequestPayload {
     #[serde(flatten)]
     pub where_fields: RawWhereFields,
@@ -306,8 +1393,80 @@ pub struct QueryRequestPayload {
     pub n_results: Option<i32>,
 }
 
+impl QueryRequestPayload {
+    pub fn new(query_embeddings: Vec<Vec<f32>>) -> Self {
+        Self {
+            query_embeddings,
+            ..Default::default()
+        }
+    }
+
+    /// Takes the query embeddings as nested vectors, one vector, or with the `ndarray`
+    /// feature a matrix with one embedding per row. Base64 embeddings are decoded, failing
+    /// as [`EmbeddingsPayload::into_floats`] does.
+    pub fn builder(
+        query_embeddings: impl Into<EmbeddingsPayload>,
+    ) -> Result<QueryRequestPayloadBuilder, KhromaError> {
+        Ok(QueryRequestPayloadBuilder {
+            payload: Self::new(query_embeddings.into().into_floats()?),
+        })
+    }
+
+    /// Sets the fields to return, dropping repeats. Accepts the presets on [`Include`],
+    /// such as [`Include::DOCUMENTS_AND_DISTANCES`].
+    pub fn with_include(mut self, include: &[Include]) -> Self {
+        self.include = Some(include_list(include));
+        self
+    }
+}
+
+/// Builds a query payload. Only records matching every filter that is set are ranked.
+#[derive(Debug, Clone)]
+pub struct QueryRequestPayloadBuilder {
+    payload: QueryRequestPayload,
+}
+
+impl QueryRequestPayloadBuilder {
+    /// Restricts the search to these records.
+    pub fn ids(mut self, ids: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.payload.ids = Some(string_vec(ids));
+        self
+    }
+
+    /// A metadata filter, such as `json!({"topic": {"$eq": "rust"}})`.
+    pub fn where_metadata(mut self, filter: impl Into<serde_json::Value>) -> Self {
+        self.payload.where_fields.r#where = Some(filter.into());
+        self
+    }
+
+    /// A document filter, such as `json!({"$contains": "rust"})`.
+    pub fn where_document(mut self, filter: impl Into<serde_json::Value>) -> Self {
+        self.payload.where_fields.where_document = Some(filter.into());
+        self
+    }
+
+    /// See [`QueryRequestPayload::with_include`].
+    pub fn include(mut self, include: &[Include]) -> Self {
+        self.payload = self.payload.with_include(include);
+        self
+    }
+
+    /// Results per query embedding.
+    pub fn n_results(mut self, n_results: u32) -> Self {
+        self.payload.n_results = Some(api_count(n_results));
+        self
+    }
+
+    pub fn build(self) -> QueryRequestPayload {
+        self.payload
+    }
+}
+
+/// `E` is how embeddings are held: nested `Vec`s by default, or one [`EmbeddingArena`]
+/// per query embedding for large result sets.
 #[derive(Serialize, Deserialize, Debug, Clone)]
-pub struct QueryResponse {
+#[non_exhaustive]
+pub struct QueryResponse<E = Vec<Vec<Vec<Option<f32>>>>> {
     pub ids: Vec<Vec<String>>,
     pub include: Vec<Include>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -319,7 +1478,119 @@ pub struct QueryResponse {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub uris: Option<Vec<Vec<Option<String>>>>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub embeddings: Option<Vec<Vec<Vec<Option<f32>>>>>,
+    pub embeddings: Option<E>,
+}
+
+pub type ArenaGetResponse = GetResponse<EmbeddingArena>;
+pub type ArenaQueryResponse = QueryResponse<Vec<EmbeddingArena>>;
+
+/// Embeddings stored back to back in a single buffer and read as slices, instead of
+/// one heap allocation per record.
+///
+/// Missing vector components (`null` in a response) are stored as `f32::NAN`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmbeddingArena {
+    values: Vec<f32>,
+    /// Start of each embedding in `values`, followed by the end of the last one.
+    offsets: Vec<usize>,
+}
+
+impl Default for EmbeddingArena {
+    fn default() -> Self {
+        Self {
+            values: Vec::new(),
+            offsets: vec![0],
+        }
+    }
+}
+
+impl EmbeddingArena {
+    pub fn len(&self) -> usize {
+        self.offsets.len() - 1
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, index: usize) -> Option<&[f32]> {
+        let start = *self.offsets.get(index)?;
+        let end = *self.offsets.get(index + 1)?;
+        Some(&self.values[start..end])
+    }
+
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = &[f32]> + '_ {
+        self.offsets.windows(2).map(|w| &self.values[w[0]..w[1]])
+    }
+
+    /// Every embedding's components, concatenated in record order.
+    pub fn as_flat(&self) -> &[f32] {
+        &self.values
+    }
+
+    pub fn push(&mut self, embedding: &[f32]) {
+        self.values.extend_from_slice(embedding);
+        self.offsets.push(self.values.len());
+    }
+}
+
+impl Serialize for EmbeddingArena {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.iter())
+    }
+}
+
+impl<'de> Deserialize<'de> for EmbeddingArena {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ArenaVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for ArenaVisitor {
+            type Value = EmbeddingArena;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a list of embeddings")
+            }
+
+            fn visit_seq<A: serde::de::SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                let mut arena = EmbeddingArena::default();
+                if let Some(len) = seq.size_hint() {
+                    arena.offsets.reserve(len);
+                }
+                while seq.next_element_seed(EmbeddingSeed(&mut arena.values))?.is_some() {
+                    arena.offsets.push(arena.values.len());
+                }
+                Ok(arena)
+            }
+        }
+
+        deserializer.deserialize_seq(ArenaVisitor)
+    }
+}
+
+/// Appends one embedding's components to the arena buffer as they are parsed.
+struct EmbeddingSeed<'a>(&'a mut Vec<f32>);
+
+impl<'de> serde::de::DeserializeSeed<'de> for EmbeddingSeed<'_> {
+    type Value = ();
+
+    fn deserialize<D: serde::Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de> serde::de::Visitor<'de> for EmbeddingSeed<'_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str("an embedding vector")
+    }
+
+    fn visit_seq<A: serde::de::SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        while let Some(value) = seq.next_element::<Option<f32>>()? {
+            self.0.push(value.unwrap_or(f32::NAN));
+        }
+        Ok(())
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -329,7 +1600,23 @@ pub enum UpdateEmbeddingsPayload {
     String(Vec<Option<String>>),
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+impl UpdateEmbeddingsPayload {
+    /// The embeddings in base64 form; see [`embedding_to_base64`].
+    pub fn to_base64(&self) -> Self {
+        match self {
+            UpdateEmbeddingsPayload::Float(embeddings) => UpdateEmbeddingsPayload::String(
+                embeddings
+                    .iter()
+                    .map(|e| e.as_deref().map(embedding_to_base64))
+                    .collect(),
+            ),
+            UpdateEmbeddingsPayload::String(_) => self.clone(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[non_exhaustive]
 pub struct UpdateCollectionRecordsPayload {
     pub ids: Vec<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -342,10 +1629,47 @@ pub struct UpdateCollectionRecordsPayload {
     pub uris: Option<Vec<Option<String>>>,
 }
 
+impl UpdateCollectionRecordsPayload {
+    pub fn new(ids: Vec<String>) -> Self {
+        Self {
+            ids,
+            ..Default::default()
+        }
+    }
+
+    /// The same update with its embeddings in base64 form, borrowing the other columns
+    /// rather than copying them; see [`embedding_to_base64`].
+    pub(crate) fn to_base64(&self) -> UpdateRecordsRef<'_> {
+        UpdateRecordsRef {
+            ids: &self.ids,
+            embeddings: self.embeddings.as_ref().map(UpdateEmbeddingsPayload::to_base64),
+            metadatas: self.metadatas.as_deref(),
+            documents: self.documents.as_deref(),
+            uris: self.uris.as_deref(),
+        }
+    }
+}
+
+/// An [`UpdateCollectionRecordsPayload`] with borrowed columns. Serializes like it.
+#[derive(Serialize, Debug)]
+pub(crate) struct UpdateRecordsRef<'a> {
+    ids: &'a [String],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    embeddings: Option<UpdateEmbeddingsPayload>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    metadatas: Option<&'a [Option<Metadata>]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    documents: Option<&'a [Option<String>]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    uris: Option<&'a [Option<String>]>,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[non_exhaustive]
 pub struct UpdateCollectionRecordsResponse {}
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[non_exhaustive]
 pub struct UpsertCollectionRecordsPayload {
     pub ids: Vec<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -358,5 +1682,738 @@ pub struct UpsertCollectionRecordsPayload {
     pub uris: Option<Vec<Option<String>>>,
 }
 
+impl UpsertCollectionRecordsPayload {
+    pub fn new(ids: Vec<String>) -> Self {
+        Self {
+            ids,
+            ..Default::default()
+        }
+    }
+
+    pub fn builder(ids: impl IntoIterator<Item = impl Into<String>>) -> UpsertCollectionRecordsPayloadBuilder {
+        UpsertCollectionRecordsPayloadBuilder {
+            payload: Self::new(string_vec(ids)),
+        }
+    }
+}
+
+/// Builds an upsert payload column by column. Each column needs one entry per id, or the
+/// server rejects the payload.
+#[derive(Debug, Clone)]
+pub struct UpsertCollectionRecordsPayloadBuilder {
+    payload: UpsertCollectionRecordsPayload,
+}
+
+record_columns!(UpsertCollectionRecordsPayloadBuilder);
+
+impl UpsertCollectionRecordsPayloadBuilder {
+    pub fn build(self) -> UpsertCollectionRecordsPayload {
+        self.payload
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[non_exhaustive]
 pub struct UpsertCollectionRecordsResponse {}
+
+/// Add or upsert records from borrowed columns, for large datasets that shouldn't be
+/// copied into an [`AddCollectionRecordsPayload`] just to be sent. Serializes like the
+/// owned payloads.
+///
+/// Accepted by [`Collection::add_ref`](crate::Collection::add_ref) and
+/// [`Collection::upsert_ref`](crate::Collection::upsert_ref). Each column needs one entry
+/// per id.
+#[derive(Serialize, Debug, Clone)]
+pub struct RecordsRef<'a> {
+    ids: &'a [String],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    embeddings: Option<EmbeddingsRef<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    metadatas: Option<&'a [Option<Metadata>]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    documents: Option<&'a [Option<String>]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    uris: Option<&'a [Option<String>]>,
+}
+
+#[derive(Debug, Clone)]
+enum EmbeddingsRef<'a> {
+    Nested(&'a [Vec<f32>]),
+    /// Row-major, `dimension` values per embedding.
+    Flat { values: &'a [f32], dimension: usize },
+    Base64(Vec<String>),
+}
+
+impl EmbeddingsRef<'_> {
+    fn rows(&self) -> Box<dyn Iterator<Item = &[f32]> + '_> {
+        match self {
+            EmbeddingsRef::Nested(embeddings) => Box::new(embeddings.iter().map(Vec::as_slice)),
+            EmbeddingsRef::Flat { values, dimension } => Box::new(values.chunks(*dimension)),
+            EmbeddingsRef::Base64(_) => Box::new(std::iter::empty()),
+        }
+    }
+}
+
+impl Serialize for EmbeddingsRef<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            EmbeddingsRef::Base64(embeddings) => embeddings.serialize(serializer),
+            _ => serializer.collect_seq(self.rows()),
+        }
+    }
+}
+
+impl<'a> RecordsRef<'a> {
+    pub fn new(ids: &'a [String]) -> Self {
+        Self {
+            ids,
+            embeddings: None,
+            metadatas: None,
+            documents: None,
+            uris: None,
+        }
+    }
+
+    pub fn ids(&self) -> &'a [String] {
+        self.ids
+    }
+
+    pub fn embeddings(mut self, embeddings: &'a [Vec<f32>]) -> Self {
+        self.embeddings = Some(EmbeddingsRef::Nested(embeddings));
+        self
+    }
+
+    /// Embeddings packed row-major in one buffer, `dimension` values each. Fails unless the
+    /// buffer holds exactly one embedding per id.
+    pub fn flat_embeddings(mut self, values: &'a [f32], dimension: usize) -> Result<Self, KhromaError> {
+        if dimension == 0 || values.len() != self.ids.len() * dimension {
+            return Err(KhromaError::Validation(format!(
+                "{} values don't make {} embeddings of dimension {}",
+                values.len(),
+                self.ids.len(),
+                dimension
+            )));
+        }
+        self.embeddings = Some(EmbeddingsRef::Flat { values, dimension });
+        Ok(self)
+    }
+
+    pub fn metadatas(mut self, metadatas: &'a [Option<Metadata>]) -> Self {
+        self.metadatas = Some(metadatas);
+        self
+    }
+
+    pub fn documents(mut self, documents: &'a [Option<String>]) -> Self {
+        self.documents = Some(documents);
+        self
+    }
+
+    pub fn uris(mut self, uris: &'a [Option<String>]) -> Self {
+        self.uris = Some(uris);
+        self
+    }
+
+    pub(crate) fn has_embeddings(&self) -> bool {
+        self.embeddings.is_some()
+    }
+
+    pub(crate) fn documents_column(&self) -> Option<&'a [Option<String>]> {
+        self.documents
+    }
+
+    /// The same records with `embeddings` in place of the borrowed ones.
+    pub(crate) fn with_owned_embeddings<'b>(&self, embeddings: &'b [Vec<f32>]) -> RecordsRef<'b>
+    where
+        'a: 'b,
+    {
+        RecordsRef {
+            embeddings: Some(EmbeddingsRef::Nested(embeddings)),
+            ..self.clone()
+        }
+    }
+
+    /// The same records with their embeddings in base64 form; see [`embedding_to_base64`].
+    pub(crate) fn to_base64(&self) -> Self {
+        let embeddings = match &self.embeddings {
+            Some(EmbeddingsRef::Base64(_)) | None => return self.clone(),
+            Some(embeddings) => embeddings.rows().map(embedding_to_base64).collect(),
+        };
+        Self {
+            embeddings: Some(EmbeddingsRef::Base64(embeddings)),
+            ..self.clone()
+        }
+    }
+
+    fn owned_embeddings(&self) -> Option<EmbeddingsPayload> {
+        self.embeddings.as_ref().map(|embeddings| match embeddings {
+            EmbeddingsRef::Base64(embeddings) => EmbeddingsPayload::String(embeddings.clone()),
+            _ => EmbeddingsPayload::Float(embeddings.rows().map(<[f32]>::to_vec).collect()),
+        })
+    }
+
+    /// Copies the records into an owned add payload.
+    pub fn to_add_payload(&self) -> AddCollectionRecordsPayload {
+        AddCollectionRecordsPayload {
+            ids: self.ids.to_vec(),
+            embeddings: self.owned_embeddings(),
+            metadatas: self.metadatas.map(<[_]>::to_vec),
+            documents: self.documents.map(<[_]>::to_vec),
+            uris: self.uris.map(<[_]>::to_vec),
+        }
+    }
+
+    /// Copies the records into an owned upsert payload.
+    pub fn to_upsert_payload(&self) -> UpsertCollectionRecordsPayload {
+        UpsertCollectionRecordsPayload {
+            ids: self.ids.to_vec(),
+            embeddings: self.owned_embeddings(),
+            metadatas: self.metadatas.map(<[_]>::to_vec),
+            documents: self.documents.map(<[_]>::to_vec),
+            uris: self.uris.map(<[_]>::to_vec),
+        }
+    }
+}
+
+impl<'a> From<&'a AddCollectionRecordsPayload> for RecordsRef<'a> {
+    /// Borrows the columns of `payload`. Base64 embeddings are copied.
+    fn from(payload: &'a AddCollectionRecordsPayload) -> Self {
+        records_ref(&payload.ids, payload.embeddings.as_ref(), &payload.metadatas, &payload.documents, &payload.uris)
+    }
+}
+
+impl<'a> From<&'a UpsertCollectionRecordsPayload> for RecordsRef<'a> {
+    /// Borrows the columns of `payload`. Base64 embeddings are copied.
+    fn from(payload: &'a UpsertCollectionRecordsPayload) -> Self {
+        records_ref(&payload.ids, payload.embeddings.as_ref(), &payload.metadatas, &payload.documents, &payload.uris)
+    }
+}
+
+fn records_ref<'a>(
+    ids: &'a [String],
+    embeddings: Option<&'a EmbeddingsPayload>,
+    metadatas: &'a Option<Vec<Option<Metadata>>>,
+    documents: &'a Option<Vec<Option<String>>>,
+    uris: &'a Option<Vec<Option<String>>>,
+) -> RecordsRef<'a> {
+    RecordsRef {
+        ids,
+        embeddings: embeddings.map(|embeddings| match embeddings {
+            EmbeddingsPayload::Float(embeddings) => EmbeddingsRef::Nested(embeddings),
+            EmbeddingsPayload::String(embeddings) => EmbeddingsRef::Base64(embeddings.clone()),
+        }),
+        metadatas: metadatas.as_deref(),
+        documents: documents.as_deref(),
+        uris: uris.as_deref(),
+    }
+}
+
+macro_rules! impl_record_tuples {
+    ($payload:ident) => {
+        impl<I: Into<String>> FromIterator<(I, String)> for $payload {
+            fn from_iter<T: IntoIterator<Item = (I, String)>>(iter: T) -> Self {
+                let (ids, documents): (Vec<String>, Vec<Option<String>>) = iter
+                    .into_iter()
+                    .map(|(id, document)| (id.into(), Some(document)))
+                    .unzip();
+                Self {
+                    ids,
+                    documents: Some(documents),
+                    ..Default::default()
+                }
+            }
+        }
+
+        impl<'a, I: Into<String>> FromIterator<(I, &'a str)> for $payload {
+            fn from_iter<T: IntoIterator<Item = (I, &'a str)>>(iter: T) -> Self {
+                iter.into_iter()
+                    .map(|(id, document)| (id, document.to_string()))
+                    .collect()
+            }
+        }
+
+        impl<I: Into<String>> FromIterator<(I, Vec<f32>)> for $payload {
+            fn from_iter<T: IntoIterator<Item = (I, Vec<f32>)>>(iter: T) -> Self {
+                let (ids, embeddings): (Vec<String>, Vec<Vec<f32>>) = iter
+                    .into_iter()
+                    .map(|(id, embedding)| (id.into(), embedding))
+                    .unzip();
+                Self {
+                    ids,
+                    embeddings: Some(EmbeddingsPayload::Float(embeddings)),
+                    ..Default::default()
+                }
+            }
+        }
+
+        impl<I: Into<String>, D: Into<String>> FromIterator<(I, D, Metadata)> for $payload {
+            fn from_iter<T: IntoIterator<Item = (I, D, Metadata)>>(iter: T) -> Self {
+                let mut payload = Self::default();
+                let mut documents = Vec::new();
+                let mut metadatas = Vec::new();
+                for (id, document, metadata) in iter {
+                    payload.ids.push(id.into());
+                    documents.push(Some(document.into()));
+                    metadatas.push(Some(metadata));
+                }
+                payload.documents = Some(documents);
+                payload.metadatas = Some(metadatas);
+                payload
+            }
+        }
+
+        impl<I: Into<String>> From<Vec<(I, String)>> for $payload {
+            fn from(records: Vec<(I, String)>) -> Self {
+                records.into_iter().collect()
+            }
+        }
+
+        impl<'a, I: Into<String>> From<Vec<(I, &'a str)>> for $payload {
+            fn from(records: Vec<(I, &'a str)>) -> Self {
+                records.into_iter().collect()
+            }
+        }
+
+        impl<I: Into<String>> From<Vec<(I, Vec<f32>)>> for $payload {
+            fn from(records: Vec<(I, Vec<f32>)>) -> Self {
+                records.into_iter().collect()
+            }
+        }
+
+        impl<I: Into<String>, D: Into<String>> From<Vec<(I, D, Metadata)>> for $payload {
+            fn from(records: Vec<(I, D, Metadata)>) -> Self {
+                records.into_iter().collect()
+            }
+        }
+    };
+}
+
+impl_record_tuples!(AddCollectionRecordsPayload);
+impl_record_tuples!(UpsertCollectionRecordsPayload);
+
+impl EmbeddingsPayload {
+    pub fn len(&self) -> usize {
+        match self {
+            EmbeddingsPayload::Float(embeddings) => embeddings.len(),
+            EmbeddingsPayload::String(embeddings) => embeddings.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn slice(&self, range: std::ops::Range<usize>) -> Self {
+        match self {
+            EmbeddingsPayload::Float(embeddings) => EmbeddingsPayload::Float(embeddings[range].to_vec()),
+            EmbeddingsPayload::String(embeddings) => EmbeddingsPayload::String(embeddings[range].to_vec()),
+        }
+    }
+}
+
+fn check_column_len(column: &str, len: Option<usize>, ids: usize) -> Result<(), KhromaError> {
+    match len {
+        Some(len) if len != ids => Err(KhromaError::Validation(format!(
+            "{} has {} entries for {} ids",
+            column, len, ids
+        ))),
+        _ => Ok(()),
+    }
+}
+
+macro_rules! impl_record_chunks {
+    ($payload:ident) => {
+        impl $payload {
+            /// Splits the records into payloads of at most `size` records each.
+            pub fn chunks(&self, size: usize) -> Result<Vec<Self>, KhromaError> {
+                let ids = self.ids.len();
+                check_column_len("embeddings", self.embeddings.as_ref().map(EmbeddingsPayload::len), ids)?;
+                check_column_len("metadatas", self.metadatas.as_ref().map(Vec::len), ids)?;
+                check_column_len("documents", self.documents.as_ref().map(Vec::len), ids)?;
+                check_column_len("uris", self.uris.as_ref().map(Vec::len), ids)?;
+                Ok((0..ids)
+                    .step_by(size.max(1))
+                    .map(|start| {
+                        let range = start..(start + size.max(1)).min(ids);
+                        Self {
+                            ids: self.ids[range.clone()].to_vec(),
+                            embeddings: self.embeddings.as_ref().map(|e| e.slice(range.clone())),
+                            metadatas: self.metadatas.as_ref().map(|m| m[range.clone()].to_vec()),
+                            documents: self.documents.as_ref().map(|d| d[range.clone()].to_vec()),
+                            uris: self.uris.as_ref().map(|u| u[range].to_vec()),
+                        }
+                    })
+                    .collect())
+            }
+        }
+    };
+}
+
+impl_record_chunks!(AddCollectionRecordsPayload);
+impl_record_chunks!(UpsertCollectionRecordsPayload);
+
+/// One record with all of its fields, as an alternative to the columnar payloads.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Record {
+    pub id: String,
+    pub embedding: Option<Vec<f32>>,
+    pub document: Option<String>,
+    pub metadata: Option<Metadata>,
+    pub uri: Option<String>,
+}
+
+impl Record {
+    pub fn new(id: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_embedding(mut self, embedding: Vec<f32>) -> Self {
+        self.embedding = Some(embedding);
+        self
+    }
+
+    pub fn with_document(mut self, document: impl Into<String>) -> Self {
+        self.document = Some(document.into());
+        self
+    }
+
+    pub fn with_metadata(mut self, metadata: Metadata) -> Self {
+        self.metadata = Some(metadata);
+        self
+    }
+
+    pub fn with_uri(mut self, uri: impl Into<String>) -> Self {
+        self.uri = Some(uri.into());
+        self
+    }
+}
+
+macro_rules! impl_from_records {
+    ($payload:ident) => {
+        impl $payload {
+            /// Builds the columnar payload. Either every record or none has an embedding;
+            /// other fields may be missing on some records.
+            pub fn from_records(records: Vec<Record>) -> Result<Self, KhromaError> {
+                let with_embedding = records.iter().filter(|r| r.embedding.is_some()).count();
+                if with_embedding != 0 && with_embedding != records.len() {
+                    return Err(KhromaError::Validation(format!(
+                        "{} of {} records have an embedding; either all or none must",
+                        with_embedding,
+                        records.len()
+                    )));
+                }
+                let has_documents = records.iter().any(|r| r.document.is_some());
+                let has_metadatas = records.iter().any(|r| r.metadata.is_some());
+                let has_uris = records.iter().any(|r| r.uri.is_some());
+
+                let mut payload = Self::default();
+                let mut embeddings = Vec::with_capacity(with_embedding);
+                let (mut documents, mut metadatas, mut uris) = (Vec::new(), Vec::new(), Vec::new());
+                for record in records {
+                    payload.ids.push(record.id);
+                    embeddings.extend(record.embedding);
+                    documents.push(record.document);
+                    metadatas.push(record.metadata);
+                    uris.push(record.uri);
+                }
+                payload.embeddings = (with_embedding > 0).then_some(EmbeddingsPayload::Float(embeddings));
+                payload.documents = has_documents.then_some(documents);
+                payload.metadatas = has_metadatas.then_some(metadatas);
+                payload.uris = has_uris.then_some(uris);
+                Ok(payload)
+            }
+        }
+    };
+}
+
+impl_from_records!(AddCollectionRecordsPayload);
+impl_from_records!(UpsertCollectionRecordsPayload);
+
+macro_rules! impl_digest {
+    ($($ty:ty),*) => {$(
+        impl $ty {
+            /// The payload in canonical JSON: sorted keys, no whitespace, normalized numbers.
+            pub fn canonical_json(&self) -> Result<String, KhromaError> {
+                crate::canonical::to_canonical_json(self)
+            }
+
+            /// A hex SHA-256 of [`canonical_json`](Self::canonical_json), equal for equal payloads.
+            pub fn digest(&self) -> Result<String, KhromaError> {
+                crate::canonical::digest(self)
+            }
+        }
+    )*};
+}
+
+impl_digest!(
+    AddCollectionRecordsPayload,
+    UpsertCollectionRecordsPayload,
+    UpdateCollectionRecordsPayload,
+    DeleteCollectionRecordsPayload,
+    GetRequestPayload,
+    QueryRequestPayload,
+    CreateCollectionPayload,
+    UpdateCollectionPayload
+);
+
+/// Splits an optional column into one entry per id, checking that the lengths match.
+fn column<T>(name: &str, column: Option<Vec<T>>, ids: usize) -> Result<Vec<Option<T>>, KhromaError> {
+    match column {
+        Some(values) => {
+            check_column_len(name, Some(values.len()), ids)?;
+            Ok(values.into_iter().map(Some).collect())
+        }
+        None => Ok((0..ids).map(|_| None).collect()),
+    }
+}
+
+fn zip_records(
+    ids: Vec<String>,
+    embeddings: Vec<Option<Vec<f32>>>,
+    documents: Vec<Option<Option<String>>>,
+    metadatas: Vec<Option<Option<Metadata>>>,
+    uris: Vec<Option<Option<String>>>,
+) -> Vec<Record> {
+    ids.into_iter()
+        .zip(embeddings)
+        .zip(documents.into_iter().zip(metadatas).zip(uris))
+        .map(|((id, embedding), ((document, metadata), uri))| Record {
+            id,
+            embedding,
+            document: document.flatten(),
+            metadata: metadata.flatten(),
+            uri: uri.flatten(),
+        })
+        .collect()
+}
+
+impl GetResponse {
+    /// Converts the columns into one `Record` per id, with the fields that were included.
+    pub fn into_records(self) -> Result<Vec<Record>, KhromaError> {
+        let ids = self.ids.len();
+        let embeddings = column("embeddings", self.embeddings, ids)?;
+        let documents = column("documents", self.documents, ids)?;
+        let metadatas = column("metadatas", self.metadatas, ids)?;
+        let uris = column("uris", self.uris, ids)?;
+        Ok(zip_records(self.ids, embeddings, documents, metadatas, uris))
+    }
+}
+
+/// One result of a query: a matched record and its distance to the query embedding.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryHit {
+    pub id: String,
+    pub distance: Option<f32>,
+    pub document: Option<String>,
+    pub metadata: Option<Metadata>,
+    pub embedding: Option<Vec<f32>>,
+    pub uri: Option<String>,
+}
+
+impl QueryResponse {
+    /// Joins the responses to single-embedding queries back into one response, in order.
+    /// A `None` part stands for a query without an answer and gets empty result lists.
+    pub(crate) fn concat(parts: Vec<Option<QueryResponse>>) -> Option<QueryResponse> {
+        fn extend<T>(merged: &mut Option<Vec<Vec<T>>>, part: Option<Vec<Vec<T>>>) {
+            if let Some(merged) = merged {
+                merged.extend(part.unwrap_or_else(|| vec![Vec::new()]));
+            }
+        }
+
+        let first = parts.iter().flatten().next()?;
+        let mut merged = QueryResponse {
+            ids: Vec::new(),
+            include: first.include.clone(),
+            distances: first.distances.as_ref().map(|_| Vec::new()),
+            metadatas: first.metadatas.as_ref().map(|_| Vec::new()),
+            documents: first.documents.as_ref().map(|_| Vec::new()),
+            uris: first.uris.as_ref().map(|_| Vec::new()),
+            embeddings: first.embeddings.as_ref().map(|_| Vec::new()),
+        };
+        for part in parts {
+            let part = part.unwrap_or_else(|| QueryResponse {
+                ids: vec![Vec::new()],
+                include: Vec::new(),
+                distances: None,
+                metadatas: None,
+                documents: None,
+                uris: None,
+                embeddings: None,
+            });
+            merged.ids.extend(part.ids);
+            extend(&mut merged.distances, part.distances);
+            extend(&mut merged.metadatas, part.metadatas);
+            extend(&mut merged.documents, part.documents);
+            extend(&mut merged.uris, part.uris);
+            extend(&mut merged.embeddings, part.embeddings);
+        }
+        Some(merged)
+    }
+
+    /// Zips the columns into hits, one list per query embedding, nearest first.
+    /// Fields that were not included are `None`.
+    pub fn into_results(mut self) -> Result<Vec<Vec<QueryHit>>, KhromaError> {
+        let distances = column("distances", self.distances.take(), self.ids.len())?;
+        self.into_records()?
+            .into_iter()
+            .zip(distances)
+            .map(|(records, distances)| {
+                let distances = column("distances", distances, records.len())?;
+                Ok(records
+                    .into_iter()
+                    .zip(distances)
+                    .map(|(record, distance)| QueryHit {
+                        id: record.id,
+                        distance: distance.flatten(),
+                        document: record.document,
+                        metadata: record.metadata,
+                        embedding: record.embedding,
+                        uri: record.uri,
+                    })
+                    .collect())
+            })
+            .collect()
+    }
+
+    /// Converts the columns into records, one list per query embedding, nearest first.
+    /// Missing embedding components are returned as `f32::NAN`.
+    pub fn into_records(self) -> Result<Vec<Vec<Record>>, KhromaError> {
+        let queries = self.ids.len();
+        let mut embeddings = column("embeddings", self.embeddings, queries)?.into_iter();
+        let mut documents = column("documents", self.documents, queries)?.into_iter();
+        let mut metadatas = column("metadatas", self.metadatas, queries)?.into_iter();
+        let mut uris = column("uris", self.uris, queries)?.into_iter();
+        self.ids
+            .into_iter()
+            .map(|ids| {
+                let n = ids.len();
+                let embeddings = column("embeddings", embeddings.next().flatten(), n)?
+                    .into_iter()
+                    .map(|e| e.map(|e| e.into_iter().map(|v| v.unwrap_or(f32::NAN)).collect()))
+                    .collect();
+                let documents = column("documents", documents.next().flatten(), n)?;
+                let metadatas = column("metadatas", metadatas.next().flatten(), n)?;
+                let uris = column("uris", uris.next().flatten(), n)?;
+                Ok(zip_records(ids, embeddings, documents, metadatas, uris))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn assert_same(arena: &EmbeddingArena, nested: &[Vec<Option<f32>>]) {
+        assert_eq!(arena.len(), nested.len());
+        for (slice, vector) in arena.iter().zip(nested) {
+            assert_eq!(slice.len(), vector.len());
+            for (a, b) in slice.iter().zip(vector) {
+                match b {
+                    Some(b) => assert_eq!(a, b),
+                    None => assert!(a.is_nan()),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn arena_parses_like_nested_vectors() {
+        let body = json!([[0.5, -1.0, 2.25], [], [3.0, null], [1e-7]]);
+        let arena: EmbeddingArena = serde_json::from_value(body.clone()).unwrap();
+        let nested: Vec<Vec<Option<f32>>> = serde_json::from_value(body).unwrap();
+        assert_same(&arena, &nested);
+        assert_eq!(arena.get(1), Some(&[][..]));
+        assert_eq!(arena.get(4), None);
+        assert_eq!(arena.as_flat().len(), 6);
+    }
+
+    #[test]
+    fn arena_serializes_like_nested_vectors() {
+        let nested = vec![vec![0.1f32, 0.2], vec![], vec![-3.5]];
+        let mut arena = EmbeddingArena::default();
+        for embedding in &nested {
+            arena.push(embedding);
+        }
+        assert_eq!(
+            serde_json::to_string(&arena).unwrap(),
+            serde_json::to_string(&nested).unwrap()
+        );
+        let back: EmbeddingArena = serde_json::from_str(&serde_json::to_string(&arena).unwrap()).unwrap();
+        assert_eq!(back, arena);
+    }
+
+    #[test]
+    fn empty_arena_round_trips() {
+        let arena: EmbeddingArena = serde_json::from_str("[]").unwrap();
+        assert!(arena.is_empty());
+        assert_eq!(arena, EmbeddingArena::default());
+        assert_eq!(serde_json::to_string(&arena).unwrap(), "[]");
+    }
+
+    #[test]
+    fn arena_rejects_non_lists() {
+        assert!(serde_json::from_str::<EmbeddingArena>("{}").is_err());
+        assert!(serde_json::from_str::<EmbeddingArena>("[1.0]").is_err());
+        assert!(serde_json::from_str::<EmbeddingArena>(r#"[["a"]]"#).is_err());
+    }
+
+    #[test]
+    fn arena_query_response_keeps_one_arena_per_query() {
+        let body = json!({
+            "ids": [["a", "b"], ["c"]],
+            "include": ["embeddings"],
+            "embeddings": [[[1.0, 2.0], [3.0, 4.0]], [[5.0, null]]],
+        });
+        let arena: ArenaQueryResponse = serde_json::from_value(body.clone()).unwrap();
+        let nested: QueryResponse = serde_json::from_value(body).unwrap();
+        let arenas = arena.embeddings.unwrap();
+        let nested = nested.embeddings.unwrap();
+        assert_eq!(arenas.len(), nested.len());
+        for (arena, nested) in arenas.iter().zip(&nested) {
+            assert_same(arena, nested);
+        }
+    }
+
+    #[test]
+    fn undecodable_base64_is_a_validation_error() {
+        let payload = EmbeddingsPayload::String(vec!["not base64!".to_string()]);
+        let error = payload.clone().into_floats().unwrap_err();
+        assert!(matches!(error, KhromaError::Validation(_)), "{:?}", error);
+        #[cfg(feature = "ndarray")]
+        {
+            let error = ::ndarray::Array2::<f32>::try_from(payload).unwrap_err();
+            assert!(matches!(error, KhromaError::Validation(_)), "{:?}", error);
+        }
+    }
+    #[test]
+    fn borrowed_base64_writes_serialize_like_owned_ones() {
+        let add = AddCollectionRecordsPayload::builder(["a", "b"])
+            .embeddings(vec![vec![1.0, 2.0], vec![3.0, 4.0]])
+            .documents(["x", "y"])
+            .build();
+        let owned = AddCollectionRecordsPayload {
+            embeddings: add.embeddings.as_ref().map(EmbeddingsPayload::to_base64),
+            ..add.clone()
+        };
+        assert_eq!(
+            serde_json::to_value(RecordsRef::from(&add).to_base64()).unwrap(),
+            serde_json::to_value(&owned).unwrap()
+        );
+
+        let mut update = UpdateCollectionRecordsPayload::new(vec!["a".into(), "b".into()]);
+        update.embeddings = Some(UpdateEmbeddingsPayload::Float(vec![Some(vec![1.0]), None]));
+        update.uris = Some(vec![None, Some("u".into())]);
+        let owned = UpdateCollectionRecordsPayload {
+            embeddings: update.embeddings.as_ref().map(UpdateEmbeddingsPayload::to_base64),
+            ..update.clone()
+        };
+        assert_eq!(
+            serde_json::to_value(update.to_base64()).unwrap(),
+            serde_json::to_value(&owned).unwrap()
+        );
+    }
+}
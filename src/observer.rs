@@ -0,0 +1,80 @@
+//! Notifications about record changes made through a collection handle.
+
+use crate::models::{CollectionId, RawWhereFields};
+use std::fmt;
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    Add,
+    Upsert,
+    Update,
+    Delete,
+}
+
+/// A successful write to a collection.
+#[derive(Debug, Clone)]
+pub struct ChangeEvent {
+    pub collection_id: CollectionId,
+    pub kind: ChangeKind,
+    /// The ids written. For a delete with a `filter`, only those of these ids matching it
+    /// were deleted, and when none are given, any record matching it may have been: the
+    /// server doesn't say which records matched.
+    pub ids: Vec<String>,
+    /// The `where` and `where_document` filters a delete was restricted to, the handle's
+    /// base filter included. `None` for deletes by id alone and for other writes.
+    pub filter: Option<RawWhereFields>,
+}
+
+/// Receives an event after every successful add, upsert, update or delete made through
+/// a [`Collection`](crate::Collection) handle, e.g. to keep an audit log or a secondary
+/// index. Implemented for closures taking `&ChangeEvent`.
+///
+/// Observers run on the calling task before the write returns, so slow work should be
+/// handed off, for instance to a channel.
+pub trait ChangeObserver: Send + Sync {
+    fn on_change(&self, event: &ChangeEvent);
+}
+
+impl<F: Fn(&ChangeEvent) + Send + Sync> ChangeObserver for F {
+    fn on_change(&self, event: &ChangeEvent) {
+        self(event)
+    }
+}
+
+/// The observers attached to a collection handle.
+#[derive(Clone, Default)]
+pub(crate) struct ChangeObservers(Vec<Arc<dyn ChangeObserver>>);
+
+impl ChangeObservers {
+    pub(crate) fn push(&mut self, observer: impl ChangeObserver + 'static) {
+        self.0.push(Arc::new(observer));
+    }
+
+    pub(crate) fn notify(
+        &self,
+        collection_id: CollectionId,
+        kind: ChangeKind,
+        ids: &[String],
+        filter: Option<&RawWhereFields>,
+    ) {
+        if self.0.is_empty() {
+            return;
+        }
+        let event = ChangeEvent {
+            collection_id,
+            kind,
+            ids: ids.to_vec(),
+            filter: filter.cloned(),
+        };
+        for observer in &self.0 {
+            observer.on_change(&event);
+        }
+    }
+}
+
+impl fmt::Debug for ChangeObservers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ChangeObservers({})", self.0.len())
+    }
+}
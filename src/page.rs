@@ -0,0 +1,70 @@
+//! Page-at-a-time listing without tracking offsets.
+
+use crate::error::KhromaError;
+use std::fmt;
+use std::str::FromStr;
+
+/// An opaque position in a listing, returned with each page to fetch the one after it.
+///
+/// Tokens can be stored or handed to another process as strings, via `Display` and
+/// `FromStr`. Their format is not part of the API: offsets today, server-side cursors
+/// once Chroma offers them.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PageToken(Position);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Position {
+    Offset(u32),
+}
+
+impl PageToken {
+    pub(crate) fn offset(&self) -> i32 {
+        match self.0 {
+            Position::Offset(offset) => offset.min(i32::MAX as u32) as i32,
+        }
+    }
+}
+
+impl fmt::Display for PageToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Position::Offset(offset) => write!(f, "o{}", offset),
+        }
+    }
+}
+
+impl FromStr for PageToken {
+    type Err = KhromaError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.strip_prefix('o')
+            .and_then(|offset| offset.parse().ok())
+            .map(|offset| PageToken(Position::Offset(offset)))
+            .ok_or_else(|| KhromaError::Validation(format!("Invalid page token: {}", s)))
+    }
+}
+
+/// One page of a listing, and the token for the next page if there may be one.
+#[derive(Debug, Clone)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub next: Option<PageToken>,
+}
+
+impl<T> Page<T> {
+    /// Builds the page fetched at `token` with `page_size`. A full page is assumed to
+    /// have more after it.
+    pub(crate) fn new(items: Vec<T>, token: Option<&PageToken>, page_size: u32) -> Self {
+        let start = token.map_or(0, |token| token.offset() as u32);
+        let next = (items.len() as u32 >= page_size)
+            .then(|| PageToken(Position::Offset(start.saturating_add(items.len() as u32))));
+        Page { items, next }
+    }
+
+    pub(crate) fn map<U>(self, f: impl FnMut(T) -> U) -> Page<U> {
+        Page {
+            items: self.items.into_iter().map(f).collect(),
+            next: self.next,
+        }
+    }
+}
@@ -0,0 +1,173 @@
+use crate::error::KhromaError;
+use crate::high_level::{Collection, Database};
+use crate::models;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// How wide each time bucket of a `PartitionedCollection` is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BucketInterval {
+    /// Buckets named `<prefix>-YYYY-MM-DD`.
+    Daily,
+    /// Buckets named `<prefix>-YYYY-MM`.
+    Monthly,
+}
+
+impl BucketInterval {
+    fn suffix_len(self) -> usize {
+        match self {
+            BucketInterval::Daily => 10,
+            BucketInterval::Monthly => 7,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CollectionQueryResult {
+    pub collection: Collection,
+    pub response: models::QueryResponse,
+}
+
+/// Routes records into time-bucketed collections such as `logs-2024-06`.
+///
+/// Buckets are created on first write. Because bucket names sort chronologically,
+/// the newest buckets are found by name alone.
+#[derive(Debug, Clone)]
+pub struct PartitionedCollection {
+    database: Database,
+    prefix: String,
+    interval: BucketInterval,
+    configuration: Option<models::CollectionConfiguration>,
+}
+
+impl PartitionedCollection {
+    pub fn new(database: Database, prefix: impl Into<String>, interval: BucketInterval) -> Self {
+        Self {
+            database,
+            prefix: prefix.into(),
+            interval,
+            configuration: None,
+        }
+    }
+
+    /// Configuration applied to buckets when they are created.
+    pub fn with_configuration(mut self, configuration: models::CollectionConfiguration) -> Self {
+        self.configuration = Some(configuration);
+        self
+    }
+
+    pub fn bucket_name(&self, time: SystemTime) -> String {
+        let secs = match time.duration_since(UNIX_EPOCH) {
+            Ok(d) => d.as_secs() as i64,
+            Err(e) => -(e.duration().as_secs() as i64),
+        };
+        let (year, month, day) = civil_from_days(secs.div_euclid(86_400));
+        match self.interval {
+            BucketInterval::Daily => format!("{}-{:04}-{:02}-{:02}", self.prefix, year, month, day),
+            BucketInterval::Monthly => format!("{}-{:04}-{:02}", self.prefix, year, month),
+        }
+    }
+
+    /// The bucket covering `time`, created if it doesn't exist yet.
+    pub async fn bucket(&self, time: SystemTime) -> Result<Collection, KhromaError> {
+        self.database
+            .get_or_create_collection(models::CreateCollectionPayload {
+                name: self.bucket_name(time),
+                configuration: self.configuration.clone(),
+                ..Default::default()
+            })
+            .await
+    }
+
+    pub async fn add(
+        &self,
+        time: SystemTime,
+        payload: &models::AddCollectionRecordsPayload,
+    ) -> Result<(), KhromaError> {
+        self.bucket(time).await?.add(payload).await
+    }
+
+    pub async fn upsert(
+        &self,
+        time: SystemTime,
+        payload: &models::UpsertCollectionRecordsPayload,
+    ) -> Result<(), KhromaError> {
+        self.bucket(time).await?.upsert(payload).await
+    }
+
+    /// Existing buckets, newest first.
+    pub async fn buckets(&self) -> Result<Vec<Collection>, KhromaError> {
+        let mut buckets: Vec<Collection> = self
+            .database
+            .list_collections(None, None)
+            .await?
+            .into_iter()
+            .filter(|c| self.is_bucket(&c.name))
+            .collect();
+        buckets.sort_by(|a, b| b.name.cmp(&a.name));
+        Ok(buckets)
+    }
+
+    /// Queries the `count` newest buckets in parallel.
+    pub async fn query_recent(
+        &self,
+        count: usize,
+        payload: &models::QueryRequestPayload,
+    ) -> Result<Vec<CollectionQueryResult>, KhromaError> {
+        let buckets = self.buckets().await?;
+        futures::future::try_join_all(buckets.into_iter().take(count).map(
+            |collection| async move {
+                let response = collection.query(payload, None, None).await?;
+                Ok::<_, KhromaError>(CollectionQueryResult {
+                    collection,
+                    response,
+                })
+            },
+        ))
+        .await
+    }
+
+    /// Deletes every bucket older than the one covering `cutoff` and returns their names.
+    pub async fn expire(
+        &self,
+        cutoff: SystemTime,
+    ) -> Result<Vec<models::CollectionName>, KhromaError> {
+        let oldest_kept = self.bucket_name(cutoff);
+        let mut expired = Vec::new();
+        for bucket in self.buckets().await? {
+            if bucket.name.as_str() < oldest_kept.as_str() {
+                self.database.delete_collection(bucket.id).await?;
+                expired.push(bucket.name);
+            }
+        }
+        Ok(expired)
+    }
+
+    fn is_bucket(&self, name: &str) -> bool {
+        name.strip_prefix(self.prefix.as_str())
+            .and_then(|rest| rest.strip_prefix('-'))
+            .is_some_and(|suffix| {
+                suffix.len() == self.interval.suffix_len()
+                    && suffix.chars().enumerate().all(|(i, c)| {
+                        if i == 4 || i == 7 {
+                            c == '-'
+                        } else {
+                            c.is_ascii_digit()
+                        }
+                    })
+            })
+    }
+}
+
+/// Converts days since the Unix epoch into a proleptic Gregorian (year, month, day).
+pub(crate) fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
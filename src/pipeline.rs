@@ -0,0 +1,207 @@
+//! Batches of independent reads executed concurrently over the client's connection pool.
+
+use crate::client::KhromaClient;
+use crate::error::KhromaError;
+use crate::high_level::Collection;
+use crate::models;
+use futures::StreamExt;
+
+#[derive(Debug, Clone)]
+enum Operation {
+    Get {
+        target: Target,
+        payload: models::GetRequestPayload,
+    },
+    Query {
+        target: Target,
+        payload: models::QueryRequestPayload,
+        limit: Option<i32>,
+        offset: Option<i32>,
+    },
+    Count {
+        target: Target,
+    },
+}
+
+#[derive(Debug, Clone)]
+struct Target {
+    tenant: String,
+    database: String,
+    collection_id: String,
+}
+
+impl Target {
+    fn new(tenant: &str, database: &str, collection_id: &str) -> Self {
+        Self {
+            tenant: tenant.to_string(),
+            database: database.to_string(),
+            collection_id: collection_id.to_string(),
+        }
+    }
+
+    fn of(collection: &Collection) -> Self {
+        Self::new(
+            &collection.tenant_name,
+            &collection.database_name,
+            &collection.id.to_string(),
+        )
+    }
+}
+
+/// The outcome of one pipelined operation, matching the method that queued it.
+#[derive(Debug, Clone)]
+pub enum PipelineResult {
+    Get(models::GetResponse),
+    Query(models::QueryResponse),
+    Count(u32),
+}
+
+/// Collects gets, queries and counts, then runs them concurrently.
+///
+/// Created by [`KhromaClient::pipeline`] or [`Khroma::pipeline`](crate::Khroma::pipeline).
+#[derive(Debug, Clone)]
+pub struct Pipeline<'a> {
+    client: &'a KhromaClient,
+    operations: Vec<Operation>,
+    concurrency: Option<usize>,
+}
+
+impl<'a> Pipeline<'a> {
+    pub(crate) fn new(client: &'a KhromaClient) -> Self {
+        Self {
+            client,
+            operations: Vec::new(),
+            concurrency: None,
+        }
+    }
+
+    /// Limits how many operations are in flight at once. All of them by default.
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = Some(concurrency.max(1));
+        self
+    }
+
+    pub fn collection_get(
+        mut self,
+        tenant: &str,
+        database: &str,
+        collection_id: &str,
+        payload: models::GetRequestPayload,
+    ) -> Self {
+        let target = Target::new(tenant, database, collection_id);
+        self.operations.push(Operation::Get { target, payload });
+        self
+    }
+
+    pub fn collection_query(
+        mut self,
+        tenant: &str,
+        database: &str,
+        collection_id: &str,
+        limit: Option<i32>,
+        offset: Option<i32>,
+        payload: models::QueryRequestPayload,
+    ) -> Self {
+        let target = Target::new(tenant, database, collection_id);
+        self.operations.push(Operation::Query {
+            target,
+            payload,
+            limit,
+            offset,
+        });
+        self
+    }
+
+    pub fn collection_count(mut self, tenant: &str, database: &str, collection_id: &str) -> Self {
+        let target = Target::new(tenant, database, collection_id);
+        self.operations.push(Operation::Count { target });
+        self
+    }
+
+    /// Queues a get on `collection`, applying its default include list.
+    pub fn get(mut self, collection: &Collection, payload: &models::GetRequestPayload) -> Self {
+        let payload = collection.get_payload(payload).into_owned();
+        let target = Target::of(collection);
+        self.operations.push(Operation::Get { target, payload });
+        self
+    }
+
+    /// Queues a query on `collection`, applying its default include list.
+    pub fn query(
+        mut self,
+        collection: &Collection,
+        payload: &models::QueryRequestPayload,
+        limit: Option<i32>,
+        offset: Option<i32>,
+    ) -> Self {
+        let payload = collection.query_payload(payload).into_owned();
+        let target = Target::of(collection);
+        self.operations.push(Operation::Query {
+            target,
+            payload,
+            limit,
+            offset,
+        });
+        self
+    }
+
+    pub fn count(mut self, collection: &Collection) -> Self {
+        let target = Target::of(collection);
+        self.operations.push(Operation::Count { target });
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.operations.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.operations.is_empty()
+    }
+
+    /// Runs every queued operation and returns their results in the order they were queued.
+    /// A failed operation does not stop the others.
+    pub async fn execute(self) -> Vec<Result<PipelineResult, KhromaError>> {
+        let concurrency = self.concurrency.unwrap_or(self.operations.len()).max(1);
+        let client = self.client;
+        futures::stream::iter(self.operations)
+            .map(|operation| run(client, operation))
+            .buffered(concurrency)
+            .collect()
+            .await
+    }
+}
+
+async fn run(client: &KhromaClient, operation: Operation) -> Result<PipelineResult, KhromaError> {
+    match operation {
+        Operation::Get { target, payload } => client
+            .collection_get(
+                &target.tenant,
+                &target.database,
+                &target.collection_id,
+                &payload,
+            )
+            .await
+            .map(PipelineResult::Get),
+        Operation::Query {
+            target,
+            payload,
+            limit,
+            offset,
+        } => client
+            .collection_query(
+                &target.tenant,
+                &target.database,
+                &target.collection_id,
+                limit,
+                offset,
+                &payload,
+            )
+            .await
+            .map(PipelineResult::Query),
+        Operation::Count { target } => client
+            .collection_count(&target.tenant, &target.database, &target.collection_id)
+            .await
+            .map(PipelineResult::Count),
+    }
+}
@@ -0,0 +1,63 @@
+//! Separate concurrency limits for user-facing and bulk requests sharing one client.
+
+use std::sync::Arc;
+use tokio::sync::{Semaphore, SemaphorePermit};
+
+/// The class a request is queued in when the client has [`PriorityLimits`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Priority {
+    /// Requests a user is waiting on, such as queries. The default.
+    #[default]
+    Interactive,
+    /// Bulk work such as ingestion or reindexing.
+    Background,
+}
+
+/// How many requests of each [`Priority`] may be in flight at once.
+///
+/// A request holds a slot while it is sent, and retried, until its response arrives.
+/// Each class waits only for its own slots, so a backlog of background requests never
+/// delays an interactive one. Set `background` low enough that the server has capacity
+/// left for interactive traffic.
+#[derive(Debug, Clone, Copy)]
+pub struct PriorityLimits {
+    pub interactive: usize,
+    pub background: usize,
+}
+
+impl PriorityLimits {
+    pub fn new(interactive: usize, background: usize) -> Self {
+        Self {
+            interactive,
+            background,
+        }
+    }
+}
+
+/// The slots of each class, shared by every clone of a client.
+#[derive(Debug, Clone)]
+pub(crate) struct PriorityQueue {
+    interactive: Arc<Semaphore>,
+    background: Arc<Semaphore>,
+}
+
+impl PriorityQueue {
+    pub(crate) fn new(limits: PriorityLimits) -> Self {
+        Self {
+            interactive: Arc::new(Semaphore::new(limits.interactive.max(1))),
+            background: Arc::new(Semaphore::new(limits.background.max(1))),
+        }
+    }
+
+    /// Waits, in arrival order, for a slot of class `priority`.
+    pub(crate) async fn acquire(&self, priority: Priority) -> SemaphorePermit<'_> {
+        let semaphore = match priority {
+            Priority::Interactive => &self.interactive,
+            Priority::Background => &self.background,
+        };
+        semaphore
+            .acquire()
+            .await
+            .expect("priority semaphores are never closed")
+    }
+}
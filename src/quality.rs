@@ -0,0 +1,123 @@
+//! Data-quality checks over a sample of a collection's records.
+
+use crate::models::GetResponse;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Norms further than this from 1 count as unnormalized.
+const NORM_TOLERANCE: f32 = 1e-3;
+
+/// The result of [`Collection::quality_report`](crate::Collection::quality_report).
+#[derive(Debug, Clone, Default)]
+pub struct QualityReport {
+    /// Records in the collection when the sample was taken.
+    pub total_records: u32,
+    /// Records examined.
+    pub sampled: usize,
+    /// Norm statistics over the finite embeddings in the sample, if there were any.
+    pub norms: Option<NormStats>,
+    /// Finite, non-zero embeddings whose norm is not 1.
+    pub unnormalized: usize,
+    /// Ids of records whose embedding is all zeros.
+    pub zero_vectors: Vec<String>,
+    /// Ids of records whose embedding contains NaN or an infinity.
+    pub non_finite_vectors: Vec<String>,
+    /// Ids of records whose embedding length differs from the collection's dimension.
+    pub dimension_mismatches: Vec<String>,
+    /// Groups of ids whose records hold the same document.
+    pub duplicate_documents: Vec<Vec<String>>,
+    /// Ids of records without metadata.
+    pub missing_metadata: Vec<String>,
+    /// For each metadata key some sampled records have, how many records with metadata
+    /// lack it.
+    pub missing_metadata_keys: BTreeMap<String, usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NormStats {
+    pub min: f32,
+    pub max: f32,
+    pub mean: f32,
+}
+
+impl QualityReport {
+    /// Whether any check found a problem. Unnormalized embeddings are not counted, since
+    /// not every distance function expects unit vectors.
+    pub fn has_issues(&self) -> bool {
+        !self.zero_vectors.is_empty()
+            || !self.non_finite_vectors.is_empty()
+            || !self.dimension_mismatches.is_empty()
+            || !self.duplicate_documents.is_empty()
+            || !self.missing_metadata.is_empty()
+            || !self.missing_metadata_keys.is_empty()
+    }
+
+    pub(crate) fn assess(
+        sample: &GetResponse,
+        total_records: u32,
+        dimension: Option<usize>,
+    ) -> Self {
+        let mut report = QualityReport {
+            total_records,
+            sampled: sample.ids.len(),
+            ..Default::default()
+        };
+
+        let mut norms = Vec::new();
+        for (id, embedding) in sample.ids.iter().zip(sample.embeddings.iter().flatten()) {
+            if dimension.is_some_and(|dimension| embedding.len() != dimension) {
+                report.dimension_mismatches.push(id.clone());
+            }
+            if embedding.iter().any(|x| !x.is_finite()) {
+                report.non_finite_vectors.push(id.clone());
+                continue;
+            }
+            if embedding.iter().all(|x| *x == 0.0) {
+                report.zero_vectors.push(id.clone());
+                continue;
+            }
+            let norm = embedding.iter().map(|x| x * x).sum::<f32>().sqrt();
+            if (norm - 1.0).abs() > NORM_TOLERANCE {
+                report.unnormalized += 1;
+            }
+            norms.push(norm);
+        }
+        if !norms.is_empty() {
+            report.norms = Some(NormStats {
+                min: norms.iter().copied().fold(f32::INFINITY, f32::min),
+                max: norms.iter().copied().fold(f32::NEG_INFINITY, f32::max),
+                mean: norms.iter().sum::<f32>() / norms.len() as f32,
+            });
+        }
+
+        let mut by_document: HashMap<&str, Vec<String>> = HashMap::new();
+        for (id, document) in sample.ids.iter().zip(sample.documents.iter().flatten()) {
+            if let Some(document) = document {
+                by_document.entry(document).or_default().push(id.clone());
+            }
+        }
+        report.duplicate_documents = by_document
+            .into_values()
+            .filter(|ids| ids.len() > 1)
+            .collect();
+        report.duplicate_documents.sort();
+
+        if let Some(metadatas) = &sample.metadatas {
+            let keys: HashSet<&String> =
+                metadatas.iter().flatten().flat_map(|m| m.keys()).collect();
+            for (id, metadata) in sample.ids.iter().zip(metadatas) {
+                let Some(metadata) = metadata else {
+                    report.missing_metadata.push(id.clone());
+                    continue;
+                };
+                for key in keys.iter().filter(|key| !metadata.contains_key(**key)) {
+                    *report
+                        .missing_metadata_keys
+                        .entry((*key).clone())
+                        .or_default() += 1;
+                }
+            }
+        }
+
+        report
+    }
+}
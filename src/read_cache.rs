@@ -0,0 +1,288 @@
+//! Reusing get and query responses for as long as the collection is unchanged.
+
+use crate::models::{Collection, GetResponse, QueryResponse};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+/// A cache of get and query responses, attached with
+/// [`Collection::with_read_cache`](crate::Collection::with_read_cache).
+///
+/// Each response is stored with the collection's `version` and `log_position` as of just
+/// before it was fetched, and is only served while both are unchanged. Checking costs a
+/// `get_collection` round-trip on every read, so a cache hit saves the get or query itself
+/// but not a request.
+///
+/// Writes through a handle using the cache drop that collection's entries at once. The
+/// version and log position only advance when the server compacts its write-ahead log,
+/// though, so writes by other clients, or by handles without this cache, go unnoticed
+/// until they are compacted, and cached reads can miss them until then.
+///
+/// Clones share the same entries, so one cache can serve several handles.
+#[derive(Debug, Clone)]
+pub struct ReadCache {
+    entries: Arc<Mutex<Entries>>,
+}
+
+/// The collection state a cached response was fetched at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct CollectionVersion {
+    version: i32,
+    log_position: i64,
+}
+
+impl From<&Collection> for CollectionVersion {
+    fn from(collection: &Collection) -> Self {
+        Self {
+            version: collection.version,
+            log_position: collection.log_position,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Cached {
+    Get(GetResponse),
+    Query(QueryResponse),
+}
+
+/// Responses by request, evicting the oldest entry once full.
+#[derive(Debug)]
+struct Entries {
+    capacity: usize,
+    responses: HashMap<String, (CollectionVersion, Cached)>,
+    order: VecDeque<String>,
+}
+
+impl ReadCache {
+    /// Keeps up to `capacity` responses.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: Arc::new(Mutex::new(Entries {
+                capacity: capacity.max(1),
+                responses: HashMap::new(),
+                order: VecDeque::new(),
+            })),
+        }
+    }
+
+    pub fn clear(&self) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.responses.clear();
+        entries.order.clear();
+    }
+
+    /// Drops the entries of the collection with id `collection_id`.
+    pub(crate) fn invalidate(&self, collection_id: &str) {
+        let mut entries = self.entries.lock().unwrap();
+        let of_collection = |key: &str| {
+            key.split_once('/')
+                .is_some_and(|(_, rest)| rest.starts_with(collection_id))
+        };
+        entries.responses.retain(|key, _| !of_collection(key));
+        entries.order.retain(|key| !of_collection(key));
+    }
+
+    pub(crate) fn get(&self, key: &str, version: CollectionVersion) -> Option<GetResponse> {
+        match self.lookup(key, version)? {
+            Cached::Get(response) => Some(response),
+            Cached::Query(_) => None,
+        }
+    }
+
+    pub(crate) fn query(&self, key: &str, version: CollectionVersion) -> Option<QueryResponse> {
+        match self.lookup(key, version)? {
+            Cached::Query(response) => Some(response),
+            Cached::Get(_) => None,
+        }
+    }
+
+    pub(crate) fn insert_get(
+        &self,
+        key: String,
+        version: CollectionVersion,
+        response: GetResponse,
+    ) {
+        self.insert(key, version, Cached::Get(response));
+    }
+
+    pub(crate) fn insert_query(
+        &self,
+        key: String,
+        version: CollectionVersion,
+        response: QueryResponse,
+    ) {
+        self.insert(key, version, Cached::Query(response));
+    }
+
+    fn lookup(&self, key: &str, version: CollectionVersion) -> Option<Cached> {
+        let entries = self.entries.lock().unwrap();
+        match entries.responses.get(key) {
+            Some((cached_at, response)) if *cached_at == version => Some(response.clone()),
+            _ => None,
+        }
+    }
+
+    fn insert(&self, key: String, version: CollectionVersion, response: Cached) {
+        let mut entries = self.entries.lock().unwrap();
+        if entries
+            .responses
+            .insert(key.clone(), (version, response))
+            .is_some()
+        {
+            return;
+        }
+        entries.order.push_back(key);
+        if entries.order.len() > entries.capacity
+            && let Some(oldest) = entries.order.pop_front()
+        {
+            entries.responses.remove(&oldest);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const V1: CollectionVersion = CollectionVersion {
+        version: 1,
+        log_position: 10,
+    };
+    const V2: CollectionVersion = CollectionVersion {
+        version: 1,
+        log_position: 11,
+    };
+
+    fn response(ids: &[&str]) -> GetResponse {
+        GetResponse {
+            ids: ids.iter().map(|id| id.to_string()).collect(),
+            include: Vec::new(),
+            metadatas: None,
+            documents: None,
+            uris: None,
+            embeddings: None,
+        }
+    }
+
+    fn ids(response: Option<GetResponse>) -> Option<Vec<String>> {
+        response.map(|response| response.ids)
+    }
+
+    #[test]
+    fn entries_are_served_only_at_their_version() {
+        let cache = ReadCache::new(4);
+        cache.insert_get("get/c1#a".to_string(), V1, response(&["x"]));
+        assert_eq!(ids(cache.get("get/c1#a", V1)), Some(vec!["x".to_string()]));
+        assert!(cache.get("get/c1#a", V2).is_none());
+        assert!(cache.get("get/c1#b", V1).is_none());
+        assert!(cache.query("get/c1#a", V1).is_none());
+    }
+
+    #[test]
+    fn the_oldest_entry_is_evicted_once_full() {
+        let cache = ReadCache::new(2);
+        for key in ["get/c1#a", "get/c1#b", "get/c1#c"] {
+            cache.insert_get(key.to_string(), V1, response(&[key]));
+        }
+        assert!(cache.get("get/c1#a", V1).is_none());
+        assert!(cache.get("get/c1#b", V1).is_some());
+        assert!(cache.get("get/c1#c", V1).is_some());
+    }
+
+    #[test]
+    fn invalidate_drops_only_that_collection() {
+        let cache = ReadCache::new(8);
+        cache.insert_get("get/c1#a".to_string(), V1, response(&["a"]));
+        cache.insert_get("query/c1?limit=None#b".to_string(), V1, response(&["b"]));
+        cache.insert_get("get/c2#a".to_string(), V1, response(&["c"]));
+        cache.invalidate("c1");
+        assert!(cache.get("get/c1#a", V1).is_none());
+        assert!(cache.get("query/c1?limit=None#b", V1).is_none());
+        assert!(cache.get("get/c2#a", V1).is_some());
+        assert_eq!(cache.entries.lock().unwrap().order.len(), 1);
+    }
+
+    #[cfg(feature = "mock")]
+    mod with_collection {
+        use super::*;
+        use crate::high_level::{Collection, Khroma};
+        use crate::mock::MockKhromaClient;
+        use crate::models::{
+            CreateCollectionPayload, DeleteCollectionRecordsPayload, GetRequestPayload, Record,
+        };
+
+        fn block_on<F: std::future::Future>(future: F) -> F::Output {
+            tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .unwrap()
+                .block_on(future)
+        }
+
+        async fn collection(name: &str, cache: &ReadCache) -> Collection {
+            Khroma::from_api(MockKhromaClient::new())
+                .get_tenant("default_tenant")
+                .await
+                .unwrap()
+                .get_database("default_database")
+                .await
+                .unwrap()
+                .create_collection(&CreateCollectionPayload::new(name))
+                .await
+                .unwrap()
+                .with_read_cache(cache.clone())
+        }
+
+        /// Replaces every cached get response, so a hit is told apart from a fresh read.
+        fn plant(cache: &ReadCache, ids: &[&str]) {
+            let mut entries = cache.entries.lock().unwrap();
+            for (_, cached) in entries.responses.values_mut() {
+                *cached = Cached::Get(response(ids));
+            }
+        }
+
+        fn record(id: &str) -> Record {
+            Record::new(id).with_embedding(vec![1.0, 2.0])
+        }
+
+        #[test]
+        fn reads_are_served_from_the_cache_until_the_handle_writes() {
+            block_on(async {
+                let cache = ReadCache::new(8);
+                let collection = collection("docs", &cache).await;
+                collection.add_records(vec![record("a")]).await.unwrap();
+                let payload = GetRequestPayload::default();
+
+                assert_eq!(collection.get(&payload).await.unwrap().ids, ["a"]);
+                plant(&cache, &["planted"]);
+                assert_eq!(collection.get(&payload).await.unwrap().ids, ["planted"]);
+
+                collection.upsert_records(vec![record("b")]).await.unwrap();
+                assert!(cache.entries.lock().unwrap().responses.is_empty());
+                let mut read = collection.get(&payload).await.unwrap().ids;
+                read.sort();
+                assert_eq!(read, ["a", "b"]);
+            });
+        }
+
+        #[test]
+        fn writes_keep_other_collections_cached() {
+            block_on(async {
+                let cache = ReadCache::new(8);
+                let first = collection("first", &cache).await;
+                let second = collection("second", &cache).await;
+                first.add_records(vec![record("a")]).await.unwrap();
+                second.add_records(vec![record("b")]).await.unwrap();
+                let payload = GetRequestPayload::default();
+                first.get(&payload).await.unwrap();
+                second.get(&payload).await.unwrap();
+                plant(&cache, &["planted"]);
+
+                let delete = DeleteCollectionRecordsPayload::by_ids(vec!["a".to_string()]);
+                first.delete(&delete).await.unwrap();
+                assert_eq!(second.get(&payload).await.unwrap().ids, ["planted"]);
+                assert!(first.get(&payload).await.unwrap().ids.is_empty());
+            });
+        }
+    }
+}
@@ -0,0 +1,308 @@
+//! Handles that expose only non-mutating operations, for components that serve reads.
+
+use crate::blob::{DataLoader, LoadedBlobs};
+use crate::compression::Compression;
+use crate::degradation::BudgetedQuery;
+use crate::error::KhromaError;
+use crate::high_level::{Collection, CollectionDetails, Database};
+use crate::models;
+use crate::page::{Page, PageToken};
+use crate::quality::QualityReport;
+use futures::Stream;
+use std::io::Write;
+use std::time::Duration;
+
+/// A `Collection` that can be queried but not written to.
+#[derive(Debug, Clone)]
+pub struct ReadOnlyCollection {
+    inner: Collection,
+}
+
+impl From<Collection> for ReadOnlyCollection {
+    fn from(inner: Collection) -> Self {
+        Self { inner }
+    }
+}
+
+impl ReadOnlyCollection {
+    pub fn id(&self) -> models::CollectionId {
+        self.inner.id
+    }
+
+    pub fn name(&self) -> &models::CollectionName {
+        &self.inner.name
+    }
+
+    pub fn tenant_name(&self) -> &str {
+        &self.inner.tenant_name
+    }
+
+    pub fn database_name(&self) -> &str {
+        &self.inner.database_name
+    }
+
+    pub fn model(&self) -> &models::Collection {
+        self.inner.model()
+    }
+
+    pub fn metadata(&self) -> Option<&models::Metadata> {
+        self.inner.metadata()
+    }
+
+    pub fn configuration(&self) -> &models::CollectionConfiguration {
+        self.inner.configuration()
+    }
+
+    pub fn dimension(&self) -> Option<i32> {
+        self.inner.dimension()
+    }
+
+    pub fn version(&self) -> i32 {
+        self.inner.version()
+    }
+
+    pub fn log_position(&self) -> i64 {
+        self.inner.log_position()
+    }
+
+    pub async fn reload(&mut self) -> Result<(), KhromaError> {
+        self.inner.reload().await
+    }
+
+    pub async fn refresh_if_stale(&mut self) -> Result<bool, KhromaError> {
+        self.inner.refresh_if_stale().await
+    }
+
+    pub async fn query(
+        &self,
+        payload: &models::QueryRequestPayload,
+        limit: Option<i32>,
+        offset: Option<i32>,
+    ) -> Result<models::QueryResponse, KhromaError> {
+        self.inner.query(payload, limit, offset).await
+    }
+
+    pub async fn query_within(
+        &self,
+        payload: &models::QueryRequestPayload,
+        budget: Duration,
+        limit: Option<i32>,
+        offset: Option<i32>,
+    ) -> Result<BudgetedQuery, KhromaError> {
+        self.inner.query_within(payload, budget, limit, offset).await
+    }
+
+    pub async fn get(
+        &self,
+        payload: &models::GetRequestPayload,
+    ) -> Result<models::GetResponse, KhromaError> {
+        self.inner.get(payload).await
+    }
+
+    pub async fn query_records(
+        &self,
+        payload: &models::QueryRequestPayload,
+        limit: Option<i32>,
+        offset: Option<i32>,
+    ) -> Result<Vec<Vec<models::Record>>, KhromaError> {
+        self.inner.query_records(payload, limit, offset).await
+    }
+
+    pub async fn get_records(
+        &self,
+        payload: &models::GetRequestPayload,
+    ) -> Result<Vec<models::Record>, KhromaError> {
+        self.inner.get_records(payload).await
+    }
+
+    pub async fn get_page(
+        &self,
+        payload: &models::GetRequestPayload,
+        page_size: u32,
+        token: Option<&PageToken>,
+    ) -> Result<Page<models::Record>, KhromaError> {
+        self.inner.get_page(payload, page_size, token).await
+    }
+
+    pub async fn peek(&self, n: u32) -> Result<models::GetResponse, KhromaError> {
+        self.inner.peek(n).await
+    }
+
+    pub async fn get_by_ids(
+        &self,
+        ids: &[&str],
+        include: Option<models::IncludeList>,
+    ) -> Result<models::GetResponse, KhromaError> {
+        self.inner.get_by_ids(ids, include).await
+    }
+
+    pub async fn query_arena(
+        &self,
+        payload: &models::QueryRequestPayload,
+        limit: Option<i32>,
+        offset: Option<i32>,
+    ) -> Result<models::ArenaQueryResponse, KhromaError> {
+        self.inner.query_arena(payload, limit, offset).await
+    }
+
+    pub async fn get_arena(
+        &self,
+        payload: &models::GetRequestPayload,
+    ) -> Result<models::ArenaGetResponse, KhromaError> {
+        self.inner.get_arena(payload).await
+    }
+
+    pub fn get_paginated(
+        &self,
+        payload: &models::GetRequestPayload,
+        page_size: u32,
+    ) -> impl Stream<Item = Result<models::GetResponse, KhromaError>> + Send + 'static {
+        self.inner.get_paginated(payload, page_size)
+    }
+
+    pub fn get_paginated_with_prefetch(
+        &self,
+        payload: &models::GetRequestPayload,
+        page_size: u32,
+        prefetch: usize,
+    ) -> impl Stream<Item = Result<models::GetResponse, KhromaError>> + Send + 'static {
+        self.inner
+            .get_paginated_with_prefetch(payload, page_size, prefetch)
+    }
+
+    pub async fn get_with_blobs<L: DataLoader + ?Sized>(
+        &self,
+        loader: &L,
+        payload: &models::GetRequestPayload,
+    ) -> Result<(models::GetResponse, LoadedBlobs), KhromaError> {
+        self.inner.get_with_blobs(loader, payload).await
+    }
+
+    pub async fn export(
+        &self,
+        writer: impl Write + Send + 'static,
+        compression: Option<Compression>,
+    ) -> Result<u64, KhromaError> {
+        self.inner.export(writer, compression).await
+    }
+
+    pub async fn warmup(
+        &self,
+        sample_queries: &[models::QueryRequestPayload],
+    ) -> Result<(), KhromaError> {
+        self.inner.warmup(sample_queries).await
+    }
+
+    pub async fn count(&self) -> Result<u32, KhromaError> {
+        self.inner.count().await
+    }
+
+    pub async fn quality_report(&self, sample_size: u32) -> Result<QualityReport, KhromaError> {
+        self.inner.quality_report(sample_size).await
+    }
+}
+
+/// A `Database` whose collections can be listed and read but not created, changed or deleted.
+#[derive(Debug, Clone)]
+pub struct ReadOnlyDatabase {
+    inner: Database,
+}
+
+impl From<Database> for ReadOnlyDatabase {
+    fn from(inner: Database) -> Self {
+        Self { inner }
+    }
+}
+
+impl ReadOnlyDatabase {
+    pub fn name(&self) -> &str {
+        &self.inner.name
+    }
+
+    pub fn tenant_name(&self) -> &str {
+        &self.inner.tenant_name
+    }
+
+    pub async fn get_collection(
+        &self,
+        collection_id: impl TryInto<models::CollectionId, Error: Into<KhromaError>>,
+    ) -> Result<ReadOnlyCollection, KhromaError> {
+        self.inner.get_collection(collection_id).await.map(Into::into)
+    }
+
+    pub async fn get_collection_by_name(
+        &self,
+        name: &str,
+    ) -> Result<ReadOnlyCollection, KhromaError> {
+        self.inner.get_collection_by_name(name).await.map(Into::into)
+    }
+
+    pub async fn collection_exists(&self, name: &str) -> Result<bool, KhromaError> {
+        self.inner.collection_exists(name).await
+    }
+
+    pub async fn resolve_collection_id(
+        &self,
+        name: impl Into<models::CollectionName>,
+    ) -> Result<models::CollectionId, KhromaError> {
+        self.inner.resolve_collection_id(name).await
+    }
+
+    pub async fn find_collections(
+        &self,
+        where_metadata: &serde_json::Value,
+    ) -> Result<Vec<ReadOnlyCollection>, KhromaError> {
+        Ok(self
+            .inner
+            .find_collections(where_metadata)
+            .await?
+            .into_iter()
+            .map(Into::into)
+            .collect())
+    }
+
+    pub async fn list_collections(
+        &self,
+        limit: Option<i32>,
+        offset: Option<i32>,
+    ) -> Result<Vec<ReadOnlyCollection>, KhromaError> {
+        Ok(self
+            .inner
+            .list_collections(limit, offset)
+            .await?
+            .into_iter()
+            .map(Into::into)
+            .collect())
+    }
+
+    pub async fn list_collections_page(
+        &self,
+        page_size: u32,
+        token: Option<&PageToken>,
+    ) -> Result<Page<ReadOnlyCollection>, KhromaError> {
+        Ok(self
+            .inner
+            .list_collections_page(page_size, token)
+            .await?
+            .map(Into::into))
+    }
+
+    pub async fn list_collections_detailed(
+        &self,
+        limit: Option<i32>,
+        offset: Option<i32>,
+        concurrency: usize,
+    ) -> Result<Vec<CollectionDetails>, KhromaError> {
+        self.inner
+            .list_collections_detailed(limit, offset, concurrency)
+            .await
+    }
+
+    pub async fn total_records(&self, concurrency: usize) -> Result<u64, KhromaError> {
+        self.inner.total_records(concurrency).await
+    }
+
+    pub async fn count_collections(&self) -> Result<u32, KhromaError> {
+        self.inner.count_collections().await
+    }
+}
@@ -0,0 +1,66 @@
+//! Reordering query results on the client, e.g. with a cross-encoder or a reranking API.
+
+use crate::error::KhromaError;
+use crate::models::QueryHit;
+use async_trait::async_trait;
+
+/// How many candidates [`Collection::query_reranked`](crate::Collection::query_reranked)
+/// fetches per result it returns.
+pub const RERANK_OVERFETCH: u32 = 4;
+
+/// Scores query hits for relevance.
+///
+/// Implemented for closures `Fn(usize, &[QueryHit]) -> Result<Vec<f32>, KhromaError>`.
+/// Rerankers that need the query text, like cross-encoders, are built with it.
+#[async_trait]
+pub trait Reranker: Send + Sync {
+    /// Returns one score per hit, in the same order; higher is more relevant. `query` is
+    /// the index of the query embedding the hits belong to.
+    async fn score(&self, query: usize, hits: &[QueryHit]) -> Result<Vec<f32>, KhromaError>;
+}
+
+#[async_trait]
+impl<F> Reranker for F
+where
+    F: Fn(usize, &[QueryHit]) -> Result<Vec<f32>, KhromaError> + Send + Sync,
+{
+    async fn score(&self, query: usize, hits: &[QueryHit]) -> Result<Vec<f32>, KhromaError> {
+        self(query, hits)
+    }
+}
+
+/// A query hit with the score its reranker gave it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RerankedHit {
+    pub hit: QueryHit,
+    pub score: f32,
+}
+
+/// Scores each query's hits with `reranker` and keeps the best `top_k`, highest first.
+/// Hits with equal scores keep their order from the query.
+pub(crate) async fn rerank(
+    reranker: &dyn Reranker,
+    results: Vec<Vec<QueryHit>>,
+    top_k: usize,
+) -> Result<Vec<Vec<RerankedHit>>, KhromaError> {
+    let mut reranked = Vec::with_capacity(results.len());
+    for (query, hits) in results.into_iter().enumerate() {
+        let scores = reranker.score(query, &hits).await?;
+        if scores.len() != hits.len() {
+            return Err(KhromaError::Validation(format!(
+                "Reranker returned {} scores for {} hits",
+                scores.len(),
+                hits.len()
+            )));
+        }
+        let mut hits: Vec<RerankedHit> = hits
+            .into_iter()
+            .zip(scores)
+            .map(|(hit, score)| RerankedHit { hit, score })
+            .collect();
+        hits.sort_by(|a, b| b.score.total_cmp(&a.score));
+        hits.truncate(top_k);
+        reranked.push(hits);
+    }
+    Ok(reranked)
+}
@@ -0,0 +1,258 @@
+use reqwest::{Client, Request, Response, StatusCode};
+use std::time::Duration;
+
+/// How the client retries requests that fail with transient errors.
+///
+/// Only idempotent operations are retried: `GET`, `PUT` and `DELETE` requests, plus the
+/// `POST` endpoints that read records or overwrite them with the same result (get, query,
+/// upsert, update and delete). Creating tenants, databases and collections, adding records,
+/// forking and resetting are sent once. Adds are retried too when the client sends
+/// idempotency keys, since the key identifies repeated deliveries of the same add.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Total number of attempts, including the first one. `1` disables retries.
+    pub max_attempts: u32,
+    /// Delay before the first retry.
+    pub initial_backoff: Duration,
+    /// Upper bound for any single delay, including one requested by a `Retry-After` header.
+    pub max_backoff: Duration,
+    /// Factor applied to the delay after each failed attempt.
+    pub multiplier: f64,
+    /// Picks each delay uniformly between zero and the computed backoff.
+    pub jitter: bool,
+    /// Response statuses that are retried. Connection failures and timeouts always are.
+    pub retry_on: Vec<StatusCode>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(5),
+            multiplier: 2.0,
+            jitter: true,
+            retry_on: vec![
+                StatusCode::TOO_MANY_REQUESTS,
+                StatusCode::BAD_GATEWAY,
+                StatusCode::SERVICE_UNAVAILABLE,
+                StatusCode::GATEWAY_TIMEOUT,
+            ],
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Sends every request exactly once. This is the client default.
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Self::default()
+        }
+    }
+
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    /// Sends `request`, retrying it under this policy. The response of the last attempt is
+    /// returned even when its status is retryable.
+    pub(crate) async fn execute(
+        &self,
+        client: &Client,
+        request: Request,
+    ) -> Result<Response, reqwest::Error> {
+        let mut attempt = 1;
+        loop {
+            // The last attempt, and any request whose body cannot be replayed, sends the original.
+            let Some(retryable) = request.try_clone().filter(|_| attempt < self.max_attempts)
+            else {
+                return client.execute(request).await;
+            };
+            let delay = match client.execute(retryable).await {
+                Ok(res) if self.retries_status(res.status()) => {
+                    self.backoff(attempt, retry_after(&res))
+                }
+                Err(e) if self.retries_error(&e) => self.backoff(attempt, None),
+                result => return result,
+            };
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    fn retries_status(&self, status: StatusCode) -> bool {
+        self.retry_on.contains(&status)
+    }
+
+    fn retries_error(&self, error: &reqwest::Error) -> bool {
+        error.is_connect() || error.is_timeout() || error.is_request()
+    }
+
+    /// The delay after failed attempt number `attempt` (starting at 1).
+    fn backoff(&self, attempt: u32, retry_after: Option<Duration>) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(i32::MAX as u32) as i32;
+        let secs = (self.initial_backoff.as_secs_f64() * self.multiplier.powi(exponent))
+            .min(self.max_backoff.as_secs_f64());
+        let mut delay = Duration::try_from_secs_f64(secs).unwrap_or(self.max_backoff);
+        if self.jitter {
+            delay = delay.mul_f64(fastrand::f64());
+        }
+        match retry_after {
+            Some(after) => delay.max(after.min(self.max_backoff)),
+            None => delay,
+        }
+    }
+}
+
+pub(crate) fn retry_after(res: &Response) -> Option<Duration> {
+    let value = res.headers().get(reqwest::header::RETRY_AFTER)?;
+    value
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+        .map(Duration::from_secs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(1),
+            multiplier: 2.0,
+            jitter: false,
+            ..RetryPolicy::default()
+        }
+    }
+
+    /// Serves `statuses` in order, one connection each, and counts the requests.
+    fn server(statuses: &'static [u16]) -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = requests.clone();
+        std::thread::spawn(move || {
+            for (status, stream) in statuses.iter().zip(listener.incoming()) {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 2 {
+                    line.clear();
+                }
+                counter.fetch_add(1, Ordering::SeqCst);
+                let response = format!(
+                    "HTTP/1.1 {} X\r\nRetry-After: 0\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                    status
+                );
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+        (url, requests)
+    }
+
+    fn execute(policy: &RetryPolicy, url: &str) -> StatusCode {
+        let client = Client::new();
+        let request = client.get(url).build().unwrap();
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+            .block_on(policy.execute(&client, request))
+            .unwrap()
+            .status()
+    }
+
+    #[test]
+    fn backoff_grows_by_the_multiplier_up_to_the_cap() {
+        let policy = policy();
+        let delays: Vec<_> = (1..=5)
+            .map(|attempt| policy.backoff(attempt, None))
+            .collect();
+        let millis = [100, 200, 400, 800, 1000].map(Duration::from_millis);
+        assert_eq!(delays, millis);
+        assert_eq!(policy.backoff(u32::MAX, None), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn jitter_stays_below_the_backoff() {
+        let policy = RetryPolicy {
+            jitter: true,
+            ..policy()
+        };
+        for attempt in 1..=4 {
+            let delay = policy.backoff(attempt, None);
+            assert!(delay <= Duration::from_millis(100 << (attempt - 1)));
+        }
+    }
+
+    #[test]
+    fn retry_after_lengthens_the_delay_up_to_the_cap() {
+        let policy = policy();
+        let after = Some(Duration::from_millis(700));
+        assert_eq!(policy.backoff(1, after), Duration::from_millis(700));
+        assert_eq!(policy.backoff(4, after), Duration::from_millis(800));
+        let long = Some(Duration::from_secs(60));
+        assert_eq!(policy.backoff(1, long), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn only_listed_statuses_are_retried() {
+        let policy = RetryPolicy::default();
+        assert!(policy.retries_status(StatusCode::TOO_MANY_REQUESTS));
+        assert!(policy.retries_status(StatusCode::SERVICE_UNAVAILABLE));
+        assert!(!policy.retries_status(StatusCode::INTERNAL_SERVER_ERROR));
+        assert!(!policy.retries_status(StatusCode::BAD_REQUEST));
+        assert!(!policy.retries_status(StatusCode::CONFLICT));
+    }
+
+    #[test]
+    fn transient_statuses_are_retried_until_success() {
+        let (url, requests) = server(&[503, 429, 200]);
+        let policy = RetryPolicy {
+            initial_backoff: Duration::from_millis(1),
+            ..policy()
+        };
+        assert_eq!(execute(&policy, &url), StatusCode::OK);
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn the_last_attempt_returns_its_response() {
+        let (url, requests) = server(&[503, 503, 503]);
+        let policy = RetryPolicy {
+            max_attempts: 2,
+            initial_backoff: Duration::from_millis(1),
+            ..policy()
+        };
+        assert_eq!(execute(&policy, &url), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn other_statuses_are_returned_at_once() {
+        let (url, requests) = server(&[400, 200]);
+        assert_eq!(execute(&policy(), &url), StatusCode::BAD_REQUEST);
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn none_sends_once() {
+        let (url, requests) = server(&[503, 200]);
+        assert_eq!(
+            execute(&RetryPolicy::none(), &url),
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,86 @@
+//! Fetching the Chroma token from a secrets manager instead of configuration.
+
+#[cfg(feature = "aws-secretsmanager")]
+mod aws;
+#[cfg(feature = "vault")]
+mod vault;
+
+#[cfg(feature = "aws-secretsmanager")]
+pub use aws::AwsSecretsManager;
+#[cfg(feature = "vault")]
+pub use vault::VaultSecrets;
+
+use crate::auth::Auth;
+use crate::error::KhromaError;
+use async_trait::async_trait;
+use futures::lock::Mutex;
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// A source of the token a client authenticates with.
+#[async_trait]
+pub trait SecretsProvider: Send + Sync {
+    async fn fetch_token(&self) -> Result<String, KhromaError>;
+}
+
+#[async_trait]
+impl<P: SecretsProvider + ?Sized> SecretsProvider for Arc<P> {
+    async fn fetch_token(&self) -> Result<String, KhromaError> {
+        (**self).fetch_token().await
+    }
+}
+
+/// A token read from a [`SecretsProvider`] and fetched again once `refresh` has passed.
+///
+/// Refreshing happens on the next request after the interval, not in the background.
+/// If a refresh fails, the previous token keeps being used and the error is logged.
+#[derive(Clone)]
+pub(crate) struct TokenSource {
+    provider: Arc<dyn SecretsProvider>,
+    refresh: Duration,
+    cached: Arc<Mutex<Option<(Auth, Instant)>>>,
+}
+
+impl TokenSource {
+    pub(crate) fn new(provider: impl SecretsProvider + 'static, refresh: Duration) -> Self {
+        Self {
+            provider: Arc::new(provider),
+            refresh,
+            cached: Arc::default(),
+        }
+    }
+
+    /// The current credentials, fetched first if missing or stale.
+    pub(crate) async fn auth(&self) -> Result<Auth, KhromaError> {
+        let mut cached = self.cached.lock().await;
+        match &*cached {
+            Some((auth, fetched_at)) if fetched_at.elapsed() < self.refresh => Ok(auth.clone()),
+            _ => match self.provider.fetch_token().await {
+                Ok(token) => {
+                    let auth = Auth::TokenHeader(token);
+                    *cached = Some((auth.clone(), Instant::now()));
+                    Ok(auth)
+                }
+                Err(e) => match &*cached {
+                    Some((auth, _)) => {
+                        log::warn!(
+                            "Failed to refresh the Chroma token, keeping the old one: {}",
+                            e
+                        );
+                        Ok(auth.clone())
+                    }
+                    None => Err(e),
+                },
+            },
+        }
+    }
+}
+
+impl fmt::Debug for TokenSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TokenSource")
+            .field("refresh", &self.refresh)
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,189 @@
+use super::SecretsProvider;
+use crate::error::KhromaError;
+use crate::sigv4::{self, AwsCredentials, SigningRequest};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::time::SystemTime;
+use url::Url;
+
+const SERVICE: &str = "secretsmanager";
+const TARGET: &str = "secretsmanager.GetSecretValue";
+const CONTENT_TYPE: &str = "application/x-amz-json-1.1";
+
+/// Reads the token from AWS Secrets Manager, signing requests with static credentials.
+///
+/// The secret's `SecretString` is used as the token, or one field of it with
+/// [`with_json_key`](Self::with_json_key) when the secret holds a JSON object.
+#[derive(Clone)]
+pub struct AwsSecretsManager {
+    client: reqwest::Client,
+    endpoint: Url,
+    region: String,
+    secret_id: String,
+    json_key: Option<String>,
+    credentials: AwsCredentials,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct GetSecretValueRequest<'a> {
+    secret_id: &'a str,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct GetSecretValueResponse {
+    secret_string: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ErrorResponse {
+    #[serde(rename = "__type")]
+    kind: Option<String>,
+    #[serde(alias = "Message")]
+    message: Option<String>,
+}
+
+impl AwsSecretsManager {
+    pub fn new(
+        region: impl Into<String>,
+        secret_id: impl Into<String>,
+        access_key_id: impl Into<String>,
+        secret_access_key: impl Into<String>,
+    ) -> Result<Self, KhromaError> {
+        let region = region.into();
+        Ok(Self {
+            client: reqwest::Client::new(),
+            endpoint: Url::parse(&format!("https://{}.{}.amazonaws.com/", SERVICE, region))?,
+            region,
+            secret_id: secret_id.into(),
+            json_key: None,
+            credentials: AwsCredentials {
+                access_key_id: access_key_id.into(),
+                secret_access_key: secret_access_key.into(),
+                session_token: None,
+            },
+        })
+    }
+
+    /// Reads the region and credentials from `AWS_REGION` (or `AWS_DEFAULT_REGION`),
+    /// `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY` and, if set, `AWS_SESSION_TOKEN`.
+    pub fn from_env(secret_id: impl Into<String>) -> Result<Self, KhromaError> {
+        let credentials = AwsCredentials::from_env()?;
+        let mut provider = Self::new(
+            sigv4::region_from_env()?,
+            secret_id,
+            credentials.access_key_id,
+            credentials.secret_access_key,
+        )?;
+        provider.credentials.session_token = credentials.session_token;
+        Ok(provider)
+    }
+
+    /// Signs requests with temporary credentials.
+    pub fn with_session_token(mut self, session_token: impl Into<String>) -> Self {
+        self.credentials.session_token = Some(session_token.into());
+        self
+    }
+
+    /// Uses field `key` of a secret stored as a JSON object.
+    pub fn with_json_key(mut self, key: impl Into<String>) -> Self {
+        self.json_key = Some(key.into());
+        self
+    }
+
+    /// Targets a VPC endpoint or a local emulator instead of the regional endpoint.
+    pub fn with_endpoint(mut self, endpoint: &str) -> Result<Self, KhromaError> {
+        self.endpoint = Url::parse(endpoint)?;
+        Ok(self)
+    }
+
+    pub fn with_http_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    fn token_from(&self, secret: String) -> Result<String, KhromaError> {
+        let Some(key) = &self.json_key else {
+            return Ok(secret);
+        };
+        let fields: serde_json::Value = serde_json::from_str(&secret)
+            .map_err(|e| KhromaError::Parse(format!("Secret is not JSON: {}", e)))?;
+        match fields.get(key) {
+            Some(serde_json::Value::String(token)) => Ok(token.clone()),
+            _ => Err(KhromaError::Config(format!(
+                "Secret '{}' has no string field '{}'",
+                self.secret_id, key
+            ))),
+        }
+    }
+
+    /// Signs the request with AWS Signature Version 4 and returns its headers.
+    fn signed_headers(&self, body: &[u8], now: SystemTime) -> Vec<(&'static str, String)> {
+        let request = SigningRequest {
+            method: "POST",
+            url: &self.endpoint,
+            service: SERVICE,
+            region: &self.region,
+            headers: vec![
+                ("content-type", CONTENT_TYPE.to_string()),
+                ("x-amz-target", TARGET.to_string()),
+            ],
+            payload: body,
+        };
+        sigv4::signed_headers(request, &self.credentials, now)
+    }
+}
+
+#[async_trait]
+impl SecretsProvider for AwsSecretsManager {
+    async fn fetch_token(&self) -> Result<String, KhromaError> {
+        let body = serde_json::to_vec(&GetSecretValueRequest {
+            secret_id: &self.secret_id,
+        })
+        .map_err(|e| KhromaError::Validation(e.to_string()))?;
+        let mut req = self.client.post(self.endpoint.clone());
+        for (name, value) in self.signed_headers(&body, SystemTime::now()) {
+            req = req.header(name, value);
+        }
+        let res = req.body(body).send().await?;
+        let status = res.status();
+        if !status.is_success() {
+            let (kind, message) = match res.json::<ErrorResponse>().await {
+                Ok(err) => (err.kind, err.message.unwrap_or_default()),
+                Err(_) => (
+                    None,
+                    format!("Failed to parse error response. Status: {}", status),
+                ),
+            };
+            return Err(KhromaError::from_status(
+                status,
+                kind.as_deref(),
+                message,
+                None,
+            ));
+        }
+        let secret = res
+            .json::<GetSecretValueResponse>()
+            .await
+            .map_err(|e| KhromaError::Parse(format!("Failed to read secret: {}", e)))?
+            .secret_string
+            .ok_or_else(|| {
+                KhromaError::Config(format!("Secret '{}' has no SecretString", self.secret_id))
+            })?;
+        self.token_from(secret)
+    }
+}
+
+impl fmt::Debug for AwsSecretsManager {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AwsSecretsManager")
+            .field("endpoint", &self.endpoint.as_str())
+            .field("region", &self.region)
+            .field("secret_id", &self.secret_id)
+            .field("json_key", &self.json_key)
+            .field("access_key_id", &self.credentials.access_key_id)
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,111 @@
+use super::SecretsProvider;
+use crate::error::KhromaError;
+use async_trait::async_trait;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+use url::Url;
+
+/// Reads the token from a HashiCorp Vault KV version 2 secrets engine.
+#[derive(Clone)]
+pub struct VaultSecrets {
+    client: reqwest::Client,
+    address: Url,
+    vault_token: String,
+    mount: String,
+    path: String,
+    key: String,
+}
+
+#[derive(Deserialize)]
+struct SecretResponse {
+    data: SecretData,
+}
+
+#[derive(Deserialize)]
+struct SecretData {
+    data: HashMap<String, serde_json::Value>,
+}
+
+impl VaultSecrets {
+    /// Reads field `key` of the secret at `path` in the `secret/` mount of the Vault
+    /// server at `address`, authenticating with `vault_token`.
+    pub fn new(
+        address: &str,
+        vault_token: impl Into<String>,
+        path: impl Into<String>,
+        key: impl Into<String>,
+    ) -> Result<Self, KhromaError> {
+        Ok(Self {
+            client: reqwest::Client::new(),
+            address: Url::parse(address)?,
+            vault_token: vault_token.into(),
+            mount: "secret".to_string(),
+            path: path.into(),
+            key: key.into(),
+        })
+    }
+
+    /// Reads the server address and token from `VAULT_ADDR` and `VAULT_TOKEN`.
+    pub fn from_env(path: impl Into<String>, key: impl Into<String>) -> Result<Self, KhromaError> {
+        let var = |name: &str| {
+            std::env::var(name).map_err(|_| KhromaError::Config(format!("{} is not set", name)))
+        };
+        Self::new(&var("VAULT_ADDR")?, var("VAULT_TOKEN")?, path, key)
+    }
+
+    /// Uses a KV mount other than `secret/`.
+    pub fn with_mount(mut self, mount: impl Into<String>) -> Self {
+        self.mount = mount.into();
+        self
+    }
+
+    pub fn with_http_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+}
+
+#[async_trait]
+impl SecretsProvider for VaultSecrets {
+    async fn fetch_token(&self) -> Result<String, KhromaError> {
+        let url = self.address.join(&format!(
+            "v1/{}/data/{}",
+            self.mount.trim_matches('/'),
+            self.path.trim_matches('/')
+        ))?;
+        let res = self
+            .client
+            .get(url)
+            .header("X-Vault-Token", &self.vault_token)
+            .send()
+            .await?;
+        let status = res.status();
+        if !status.is_success() {
+            let message = res.text().await.unwrap_or_default();
+            return Err(KhromaError::from_status(status, None, message, None));
+        }
+        let secret = res
+            .json::<SecretResponse>()
+            .await
+            .map_err(|e| KhromaError::Parse(format!("Failed to read Vault secret: {}", e)))?;
+        match secret.data.data.get(&self.key) {
+            Some(serde_json::Value::String(token)) => Ok(token.clone()),
+            _ => Err(KhromaError::Config(format!(
+                "Vault secret '{}' has no string field '{}'",
+                self.path, self.key
+            ))),
+        }
+    }
+}
+
+impl fmt::Debug for VaultSecrets {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("VaultSecrets")
+            .field("address", &self.address.as_str())
+            .field("mount", &self.mount)
+            .field("path", &self.path)
+            .field("key", &self.key)
+            .finish_non_exhaustive()
+    }
+}
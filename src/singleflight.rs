@@ -0,0 +1,69 @@
+use crate::error::KhromaError;
+use futures::future::{BoxFuture, FutureExt, Shared, TryFutureExt};
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+
+type SharedCall<T> = Shared<BoxFuture<'static, Result<T, Arc<KhromaError>>>>;
+
+/// Coalesces concurrent calls with the same key into a single execution whose
+/// result is handed to every caller.
+pub(crate) struct SingleFlight<T> {
+    inflight: Arc<Mutex<HashMap<String, SharedCall<T>>>>,
+}
+
+impl<T> Clone for SingleFlight<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inflight: self.inflight.clone(),
+        }
+    }
+}
+
+impl<T> Default for SingleFlight<T> {
+    fn default() -> Self {
+        Self {
+            inflight: Arc::default(),
+        }
+    }
+}
+
+impl<T> fmt::Debug for SingleFlight<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let inflight = self.inflight.lock().unwrap().len();
+        f.debug_struct("SingleFlight")
+            .field("inflight", &inflight)
+            .finish()
+    }
+}
+
+impl<T: Clone + Send + Sync + 'static> SingleFlight<T> {
+    /// Runs the future built by `call`, unless a call with the same `key` is already
+    /// in flight, in which case its result is awaited instead.
+    pub(crate) async fn run<F>(
+        &self,
+        key: String,
+        call: impl FnOnce() -> F,
+    ) -> Result<T, KhromaError>
+    where
+        F: Future<Output = Result<T, KhromaError>> + Send + 'static,
+    {
+        let shared = {
+            let mut inflight = self.inflight.lock().unwrap();
+            inflight
+                .entry(key.clone())
+                .or_insert_with(|| call().map_err(Arc::new).boxed().shared())
+                .clone()
+        };
+        let result = shared.await;
+
+        let mut inflight = self.inflight.lock().unwrap();
+        if inflight.get(&key).is_some_and(|call| call.peek().is_some()) {
+            inflight.remove(&key);
+        }
+        drop(inflight);
+
+        result.map_err(|e| Arc::try_unwrap(e).unwrap_or_else(KhromaError::Shared))
+    }
+}
@@ -0,0 +1,62 @@
+//! Request bodies overwritten with zeros once the HTTP stack is done with them.
+
+use crate::error::KhromaError;
+use bytes::Bytes;
+use serde::Serialize;
+use std::io;
+
+/// Overwrites `buf` with zeros in a way the optimizer does not remove.
+pub(crate) fn zeroize(buf: &mut [u8]) {
+    buf.fill(0);
+    std::hint::black_box(buf);
+}
+
+/// A buffer zeroed when the last `Bytes` sharing it is dropped, after the request and any
+/// retries are done with it.
+struct ZeroOnDrop(Vec<u8>);
+
+impl AsRef<[u8]> for ZeroOnDrop {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl Drop for ZeroOnDrop {
+    fn drop(&mut self) {
+        zeroize(&mut self.0);
+    }
+}
+
+/// Counts the bytes written to it.
+struct Counter(usize);
+
+impl io::Write for Counter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Wraps `body` in a buffer that is zeroed on drop.
+#[cfg_attr(not(any(feature = "gzip", feature = "zstd")), allow(dead_code))]
+pub(crate) fn body(body: Vec<u8>) -> Bytes {
+    Bytes::from_owner(ZeroOnDrop(body))
+}
+
+/// Serializes `payload` to JSON in a buffer that is zeroed on drop.
+///
+/// The encoded length is measured first so the buffer is allocated once at its final
+/// size; growing it would free earlier allocations holding part of the body unzeroed.
+pub(crate) fn json_body<T: Serialize + ?Sized>(payload: &T) -> Result<Bytes, KhromaError> {
+    let mut counter = Counter(0);
+    serde_json::to_writer(&mut counter, payload)
+        .map_err(|e| KhromaError::Validation(e.to_string()))?;
+    let mut body = Vec::with_capacity(counter.0);
+    serde_json::to_writer(&mut body, payload)
+        .map_err(|e| KhromaError::Validation(e.to_string()))?;
+    Ok(self::body(body))
+}